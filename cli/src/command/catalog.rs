@@ -0,0 +1,84 @@
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use chandler::catalog::Catalog;
+use chandler::config::sites::SitesConfig;
+use chandler::ui::*;
+
+use crate::config::CliWatchConfig;
+use crate::watcher::{ThreadWatcher, TICK};
+use crate::{GeneralOptions, ProjectOptions, WatchOptions};
+
+use crate::error::*;
+
+pub fn catalog_watch(
+    board_url: &str,
+    patterns: &[String],
+    watch_options: &WatchOptions,
+    watch_config: &CliWatchConfig,
+    general_options: &GeneralOptions,
+    project_options: &ProjectOptions,
+    ui: &mut dyn ChandlerUiHandler,
+) -> Result<(), CliError> {
+    let catalog = resolve_catalog(board_url, general_options)?;
+
+    let regexes = patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern)
+                .map_err(|err| CliError::new(CliErrorKind::Config, format!("Invalid regex '{pattern}': {err}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut watcher = ThreadWatcher::new(watch_options, watch_config, general_options, project_options)?;
+
+    let catalog_interval = Duration::from_secs(watch_options.interval as u64);
+    let mut next_fetch = Instant::now();
+
+    eprintln!("Watching catalog: {}", catalog.url());
+
+    while !ui.is_cancelled() {
+        // Fetch the catalog and start watching any new matching threads.
+        if next_fetch <= Instant::now() {
+            next_fetch = Instant::now() + catalog_interval;
+
+            match catalog.fetch(watcher.download_engine(), ui) {
+                Ok(threads) => {
+                    for thread in threads.iter().filter(|thread| thread.matches(&regexes)) {
+                        if watcher.is_watching(&thread.url) {
+                            continue;
+                        }
+
+                        eprintln!(
+                            "Matched thread: {} ({})",
+                            thread.url,
+                            thread.subject.as_deref().unwrap_or("no subject")
+                        );
+
+                        watcher.watch(&thread.url, None);
+                    }
+                }
+                Err(err) => eprintln!("Error fetching catalog: {}", err),
+            }
+        }
+
+        // Update all threads that are due.
+        watcher.update_due(ui);
+
+        std::thread::sleep(TICK);
+    }
+
+    Ok(())
+}
+
+fn resolve_catalog(board_url: &str, general_options: &GeneralOptions) -> Result<Catalog, CliError> {
+    let sites_config = SitesConfig::from_config_path(general_options.config_path.as_deref())?;
+
+    sites_config.resolve_catalog(board_url)?.ok_or_else(|| {
+        CliError::new(
+            CliErrorKind::Config,
+            format!("No catalog is configured for board: {board_url}"),
+        )
+    })
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use tracing::warn;
+
+use chandler::error::*;
+use chandler::ui::*;
+
+use crate::config::{CliWatchConfig, WatchList, WatchListState};
+use crate::watcher::{ThreadWatcher, TICK};
+use crate::{GeneralOptions, ProjectOptions, WatchOptions};
+
+use crate::error::*;
+
+pub fn daemon(
+    watch_list_path: &Path,
+    watch_options: &WatchOptions,
+    watch_config: &CliWatchConfig,
+    general_options: &GeneralOptions,
+    project_options: &ProjectOptions,
+    ui: &mut dyn ChandlerUiHandler,
+) -> Result<(), CliError> {
+    let mut watcher = ThreadWatcher::new(watch_options, watch_config, general_options, project_options)?
+        .with_watch_list(watch_list_path);
+
+    // Load the initial watch list.
+    let mut watch_list_modified = watch_list_modified_time(watch_list_path);
+    sync_threads(
+        &mut watcher,
+        WatchList::from_file_or_default(watch_list_path)?,
+        watch_list_path,
+    )?;
+
+    // Modification time of the watch list when it last failed to load, to only report each error once.
+    let mut invalid_watch_list_modified = None;
+
+    eprintln!("Watching threads in: {}", watch_list_path.display());
+
+    while !ui.is_cancelled() {
+        // Reload the watch list if it has changed.
+        // If it fails to load, for example because it is being edited, it is retried on the next tick.
+        let modified = watch_list_modified_time(watch_list_path);
+
+        if modified != watch_list_modified {
+            let result = WatchList::from_file_or_default(watch_list_path)
+                .and_then(|watch_list| sync_threads(&mut watcher, watch_list, watch_list_path));
+
+            match result {
+                Ok(()) => watch_list_modified = modified,
+                Err(err) => {
+                    if invalid_watch_list_modified != Some(modified) {
+                        warn!("Error loading watch list: {}", err);
+                        invalid_watch_list_modified = Some(modified);
+                    }
+                }
+            }
+        }
+
+        // Update all threads that are due.
+        watcher.update_due(ui);
+
+        std::thread::sleep(TICK);
+    }
+
+    Ok(())
+}
+
+fn watch_list_modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Add threads that were added to the watch list, and remove those that were removed from it.
+/// Paused and dead threads are not watched.
+fn sync_threads(
+    watcher: &mut ThreadWatcher,
+    watch_list: WatchList,
+    watch_list_path: &Path,
+) -> Result<(), ChandlerError> {
+    let state = WatchListState::from_file_or_default(&WatchListState::path_for(watch_list_path))?;
+
+    let entries: HashMap<String, Option<u64>> = watch_list
+        .threads
+        .into_iter()
+        .filter(|entry| !entry.paused && !state.is_dead(&entry.url))
+        .map(|entry| (entry.url, entry.interval))
+        .collect();
+
+    // Remove threads that are no longer in the watch list.
+    watcher.retain(|url| entries.contains_key(url));
+
+    for (url, base_interval) in entries.iter() {
+        watcher.watch(url, *base_interval);
+    }
+
+    Ok(())
+}
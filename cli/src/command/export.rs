@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use chandler::project::common::HtmlExportOptions;
+
+use crate::GeneralOptions;
+
+use crate::error::*;
+
+pub fn export_json(path: &Path, output: Option<&Path>, general_options: &GeneralOptions) -> Result<(), CliError> {
+    // Try to load Chandler project.
+    let project = super::load_project(path, general_options)?;
+
+    let output = output.map_or_else(|| path.join("export.json"), Path::to_path_buf);
+
+    project.export_json(&output)?;
+
+    eprintln!("Exported JSON: {}", output.display());
+
+    Ok(())
+}
+
+pub fn export_html(
+    path: &Path,
+    output: Option<&Path>,
+    thumbnails_only: bool,
+    max_size_mib: Option<u64>,
+    general_options: &GeneralOptions,
+) -> Result<(), CliError> {
+    // Try to load Chandler project.
+    let project = super::load_project(path, general_options)?;
+
+    let output = output.map_or_else(|| path.join("export.html"), Path::to_path_buf);
+
+    let options = HtmlExportOptions {
+        thumbnails_only,
+        max_size: max_size_mib.map(|mib| mib * 1024 * 1024),
+    };
+
+    project.export_html(&output, &options)?;
+
+    eprintln!("Exported HTML: {}", output.display());
+
+    Ok(())
+}
+
+pub fn export_warc(path: &Path, output: Option<&Path>, general_options: &GeneralOptions) -> Result<(), CliError> {
+    // Try to load Chandler project.
+    let project = super::load_project(path, general_options)?;
+
+    let output = output.map_or_else(|| path.join("export.warc"), Path::to_path_buf);
+
+    project.export_warc(&output)?;
+
+    eprintln!("Exported WARC: {}", output.display());
+
+    Ok(())
+}
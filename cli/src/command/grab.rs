@@ -1,12 +1,15 @@
 use chandler::project;
 use chandler::ui::*;
 
+use crate::command::update_index;
+use crate::crawl::Crawler;
 use crate::{GeneralOptions, ProjectOptions};
 
 use crate::error::*;
 
 pub fn grab(
     url: &str,
+    crawl_depth: u32,
     general_options: &GeneralOptions,
     project_options: &ProjectOptions,
     ui: &mut dyn ChandlerUiHandler,
@@ -27,5 +30,12 @@ pub fn grab(
 
     project.save()?;
 
+    update_index(project.as_ref(), general_options);
+
+    // Grab linked threads.
+    if crawl_depth > 0 {
+        Crawler::new(crawl_depth, general_options, project_options)?.crawl(project.as_mut(), ui)?;
+    }
+
     Ok(())
 }
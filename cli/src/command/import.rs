@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use chandler::project::{self, ProjectFormat};
+use chandler::ui::*;
+
+use crate::command::update_index;
+use crate::GeneralOptions;
+
+use crate::error::*;
+
+pub fn import_warc(
+    file_path: &Path,
+    path: Option<&Path>,
+    general_options: &GeneralOptions,
+    ui: &mut dyn ChandlerUiHandler,
+) -> Result<(), CliError> {
+    let url = project::common::get_warc_thread_url(file_path)?;
+
+    let mut project = project::builder()
+        .url(&url)
+        .path(path)
+        .config_path(general_options.config_path.as_deref())
+        .use_chandler_config(true)?
+        .use_sites_config(true)?
+        .format(Some(ProjectFormat::V3))
+        .load_or_create()?;
+
+    eprintln!("Project path: {}", project.get_path().display());
+
+    project.import_warc(file_path, ui)?;
+    project.save()?;
+
+    update_index(project.as_ref(), general_options);
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use tracing::warn;
+
+use chandler::config::chandler::ChandlerConfig;
+use chandler::error::*;
+use chandler::index::{self, ArchiveIndex};
+use chandler::project::Project;
+
+use crate::GeneralOptions;
+
+use crate::error::*;
+
+pub fn index(general_options: &GeneralOptions) -> Result<(), CliError> {
+    let download_path = get_download_path(general_options)?;
+
+    let mut index = ArchiveIndex::load(&download_path)?;
+    let indexed_count = index.scan()?;
+    index.save()?;
+
+    eprintln!(
+        "Indexed {} of {} threads: {}",
+        indexed_count,
+        index.entries().count(),
+        index.index_file_path().display()
+    );
+
+    Ok(())
+}
+
+/// Update the index with a project that has just been updated.
+/// Failing to update the index does not affect the project, so errors are only logged.
+pub fn update_index(project: &dyn Project, general_options: &GeneralOptions) {
+    let result =
+        get_download_path(general_options).and_then(|download_path| index::update_index(&download_path, project));
+
+    if let Err(err) = result {
+        warn!("Error updating index: {}", err);
+    }
+}
+
+fn get_download_path(general_options: &GeneralOptions) -> Result<PathBuf, ChandlerError> {
+    let config = match general_options
+        .config_path
+        .clone()
+        .or_else(chandler::config::get_default_config_path)
+    {
+        Some(config_path) => ChandlerConfig::from_location(&config_path)?,
+        None => ChandlerConfig::default(),
+    };
+
+    config.get_download_path()
+}
@@ -1,7 +1,36 @@
+use std::path::Path;
+
+use chandler::error::*;
+use chandler::project::{self, Project};
+
+use crate::GeneralOptions;
+
+mod catalog;
+mod daemon;
+mod export;
 mod grab;
+mod import;
+mod index;
 mod rebuild;
 mod watch;
+mod watchlist;
 
+pub use catalog::*;
+pub use daemon::*;
+pub use export::*;
 pub use grab::*;
+pub use import::*;
+pub use index::*;
 pub use rebuild::*;
 pub use watch::*;
+pub use watchlist::*;
+
+/// Load an existing project, using the configuration specified in the general options.
+fn load_project(path: &Path, general_options: &GeneralOptions) -> Result<Box<dyn Project>, ChandlerError> {
+    project::builder()
+        .path(Some(path))
+        .config_path(general_options.config_path.as_deref())
+        .use_chandler_config(true)?
+        .use_sites_config(true)?
+        .load_or_create()
+}
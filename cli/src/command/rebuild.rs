@@ -1,13 +1,14 @@
 use std::path::Path;
 
-use chandler::project;
 use chandler::ui::*;
 
+use crate::GeneralOptions;
+
 use crate::error::*;
 
-pub fn rebuild(path: &Path, ui: &mut dyn ChandlerUiHandler) -> Result<(), CliError> {
+pub fn rebuild(path: &Path, general_options: &GeneralOptions, ui: &mut dyn ChandlerUiHandler) -> Result<(), CliError> {
     // Try to load Chandler project.
-    let mut project = project::load(path)?;
+    let mut project = super::load_project(path, general_options)?;
 
     project.rebuild(ui)?;
 
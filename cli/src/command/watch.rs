@@ -4,9 +4,14 @@ use indicatif::{ProgressBar, ProgressStyle};
 
 use chandler::error::*;
 use chandler::project;
+use chandler::threadparser::ThreadStatus;
 use chandler::ui::*;
 
-use crate::{GeneralOptions, ProjectOptions};
+use crate::command::update_index;
+use crate::config::CliWatchConfig;
+use crate::crawl::Crawler;
+use crate::schedule::UpdateSchedule;
+use crate::{GeneralOptions, ProjectOptions, StopOn, WatchOptions};
 
 use crate::error::*;
 
@@ -14,7 +19,9 @@ const ONE_SECOND: Duration = Duration::from_secs(1);
 
 pub fn watch(
     url: &str,
-    interval: i64,
+    crawl_depth: u32,
+    watch_options: &WatchOptions,
+    watch_config: &CliWatchConfig,
     general_options: &GeneralOptions,
     project_options: &ProjectOptions,
     ui: &mut dyn ChandlerUiHandler,
@@ -29,7 +36,13 @@ pub fn watch(
 
     eprintln!("Project path: {}", project.get_path().display());
 
-    let interval_seconds = interval as u64;
+    let mut crawler = if crawl_depth > 0 {
+        Some(Crawler::new(crawl_depth, general_options, project_options)?)
+    } else {
+        None
+    };
+
+    let mut schedule = UpdateSchedule::new(watch_options.interval as u64, watch_config);
 
     'watch: loop {
         let update_result = {
@@ -37,7 +50,7 @@ pub fn watch(
 
             if let Err(ChandlerError::Download(_)) = &result {
                 // Wait for retry.
-                if !waiting_bar(interval_seconds, "seconds until retry...", ui) {
+                if !waiting_bar(schedule.next_wait().as_secs(), "seconds until retry...", ui) {
                     // If user requested cancellation, break out of the loop.
                     break 'watch;
                 }
@@ -51,14 +64,36 @@ pub fn watch(
         // Save changes to disk.
         project.save()?;
 
+        update_index(project.as_ref(), general_options);
+
+        // Grab any newly linked threads.
+        if let Some(crawler) = crawler.as_mut() {
+            crawler.crawl(project.as_mut(), ui)?;
+        }
+
         // If the thread is dead, break out of loop.
         if update_result.is_dead {
             eprintln!("Thread is dead.");
             break 'watch;
         }
 
+        // If the thread has reached a status it should stop on, break out of loop.
+        if let Some(reason) = stop_reason(&update_result.status, &watch_options.stop_on) {
+            eprintln!("{}", reason);
+            break 'watch;
+        }
+
+        // Adjust the interval based on the thread's activity.
+        schedule.record_update(&update_result);
+
         // Wait for next update.
-        if !waiting_bar(interval_seconds, "seconds until update...", ui) {
+        let message = format!(
+            "seconds until update (interval {}s: {})...",
+            schedule.interval(),
+            schedule.reason()
+        );
+
+        if !waiting_bar(schedule.next_wait().as_secs(), &message, ui) {
             // If user requested cancellation, break out of the loop.
             break 'watch;
         }
@@ -67,6 +102,16 @@ pub fn watch(
     Ok(())
 }
 
+/// Get the reason to stop watching, if the thread has reached any of the specified statuses.
+pub fn stop_reason(status: &ThreadStatus, stop_on: &[StopOn]) -> Option<&'static str> {
+    stop_on.iter().find_map(|stop_on| match stop_on {
+        StopOn::Locked if status.is_locked => Some("Thread is locked."),
+        StopOn::BumpLimit if status.bump_limit_reached => Some("Thread has reached the bump limit."),
+        StopOn::ImageLimit if status.image_limit_reached => Some("Thread has reached the image limit."),
+        _ => None,
+    })
+}
+
 /// Wait with progress indicator.
 fn waiting_bar(wait_seconds: u64, message: &str, ui: &mut dyn ChandlerUiHandler) -> bool {
     let mut seconds_passed: u64 = 0;
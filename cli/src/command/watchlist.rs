@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+use crate::config::{WatchList, WatchListEntry, WatchListState};
+use crate::GeneralOptions;
+
+use crate::error::*;
+
+/// Get the path of the watch list file in the config directory.
+pub fn watch_list_path(general_options: &GeneralOptions) -> Result<PathBuf, CliError> {
+    general_options
+        .config_path
+        .clone()
+        .or_else(WatchList::default_location)
+        .map(|path| WatchList::path_from_location(&path))
+        .ok_or_else(|| {
+            CliError::new(
+                CliErrorKind::Config,
+                "No config path specified, and no default path could be determined.",
+            )
+        })
+}
+
+pub fn watchlist_add(watch_list_path: &Path, url: &str, interval: Option<u64>) -> Result<(), CliError> {
+    WatchList::modify(watch_list_path, |watch_list| {
+        if watch_list.get_mut(url).is_some() {
+            return Err(CliError::new(
+                CliErrorKind::Other,
+                format!("Thread is already in the watch list: {url}"),
+            ));
+        }
+
+        watch_list.threads.push(WatchListEntry::new(url, interval));
+
+        Ok(())
+    })?;
+
+    eprintln!("Added to watch list: {}", url);
+
+    Ok(())
+}
+
+pub fn watchlist_remove(watch_list_path: &Path, url: &str) -> Result<(), CliError> {
+    modify_entry(watch_list_path, url, |watch_list, url| {
+        watch_list.threads.retain(|entry| entry.url != url);
+    })?;
+
+    // Forget the thread's state, so that it starts over if it is added again.
+    let result = WatchListState::modify(&WatchListState::path_for(watch_list_path), |state| {
+        state.threads.remove(url);
+    });
+
+    if let Err(err) = result {
+        warn!("Error saving watch list state: {}", err);
+    }
+
+    eprintln!("Removed from watch list: {}", url);
+
+    Ok(())
+}
+
+pub fn watchlist_list(watch_list_path: &Path) -> Result<(), CliError> {
+    let watch_list = WatchList::from_file_or_default(watch_list_path)?;
+    let state = WatchListState::from_file_or_default(&WatchListState::path_for(watch_list_path))?;
+
+    for entry in watch_list.threads.iter() {
+        let thread_state = state.get(&entry.url);
+
+        let status = if thread_state.is_some_and(|state| state.is_dead) {
+            "dead"
+        } else if entry.paused {
+            "paused"
+        } else {
+            "watching"
+        };
+
+        let interval = entry
+            .interval
+            .map_or_else(|| "default".to_owned(), |interval| format!("{interval}s"));
+
+        let last_checked = thread_state
+            .and_then(|state| state.last_checked)
+            .map_or_else(|| "never".to_owned(), |last_checked| last_checked.to_rfc3339());
+
+        println!(
+            "{}\t{}\tinterval: {}\tlast checked: {}",
+            entry.url, status, interval, last_checked
+        );
+    }
+
+    Ok(())
+}
+
+pub fn watchlist_pause(watch_list_path: &Path, url: &str) -> Result<(), CliError> {
+    set_paused(watch_list_path, url, true)?;
+
+    eprintln!("Paused: {}", url);
+
+    Ok(())
+}
+
+pub fn watchlist_resume(watch_list_path: &Path, url: &str) -> Result<(), CliError> {
+    set_paused(watch_list_path, url, false)?;
+
+    eprintln!("Resumed: {}", url);
+
+    Ok(())
+}
+
+fn set_paused(watch_list_path: &Path, url: &str, paused: bool) -> Result<(), CliError> {
+    modify_entry(watch_list_path, url, |watch_list, url| {
+        if let Some(entry) = watch_list.get_mut(url) {
+            entry.paused = paused;
+        }
+    })
+}
+
+/// Modify the watch list, failing if it does not contain the specified thread.
+fn modify_entry(watch_list_path: &Path, url: &str, action: impl FnOnce(&mut WatchList, &str)) -> Result<(), CliError> {
+    WatchList::modify(watch_list_path, |watch_list| {
+        if watch_list.get_mut(url).is_none() {
+            return Err(CliError::new(
+                CliErrorKind::Other,
+                format!("Thread is not in the watch list: {url}"),
+            ));
+        }
+
+        action(watch_list, url);
+
+        Ok(())
+    })
+}
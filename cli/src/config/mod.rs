@@ -7,6 +7,10 @@ use serde_derive::{Deserialize, Serialize};
 use chandler::error::*;
 use chandler::util;
 
+mod watchlist;
+
+pub use self::watchlist::*;
+
 pub const CLI_CONFIG_FILENAME: &str = "cli.toml";
 
 pub const DEFAULT_CLI_TOML: &str = include_str!("default_cli.toml");
@@ -35,10 +39,39 @@ impl Default for CliProgressConfig {
     }
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct CliWatchConfig {
+    /// Whether to adapt the update interval to the thread's activity.
+    pub adaptive: bool,
+
+    /// Minimum update interval (seconds).
+    pub min_interval: u64,
+
+    /// Maximum update interval (seconds).
+    pub max_interval: u64,
+
+    /// Random jitter added to or subtracted from the interval (percentage).
+    pub jitter: u64,
+}
+
+impl Default for CliWatchConfig {
+    fn default() -> Self {
+        Self {
+            adaptive: true,
+            min_interval: 60,
+            max_interval: 3600,
+            jitter: 10,
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct CliConfig {
     pub progress: CliProgressConfig,
+    #[serde(default)]
+    pub watch: CliWatchConfig,
 }
 
 impl CliConfig {
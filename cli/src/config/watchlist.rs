@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+use chandler::error::*;
+use chandler::util;
+use chandler::util::pid::PidLock;
+
+pub const WATCH_LIST_FILENAME: &str = "watchlist.toml";
+
+/// Name of the file the state of watched threads is stored in, in the same directory as the watch list.
+pub const WATCH_LIST_STATE_FILENAME: &str = "watchlist-state.toml";
+
+/// Maximum time to wait for another process to finish modifying a file.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// List of threads to watch.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WatchList {
+    #[serde(default)]
+    pub threads: Vec<WatchListEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WatchListEntry {
+    /// Thread URL.
+    pub url: String,
+
+    /// Update interval (seconds). If not specified, the default interval is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+
+    /// Whether watching the thread is paused.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+}
+
+/// State of the threads in a watch list, maintained by the daemon.
+/// This is kept separate from the watch list, so that the daemon never writes to the file users edit.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WatchListState {
+    /// State of each thread, by URL.
+    #[serde(default)]
+    pub threads: BTreeMap<String, WatchedThreadState>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WatchedThreadState {
+    /// When the thread was last checked for updates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_checked: Option<DateTime<Utc>>,
+
+    /// Whether the thread is dead.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_dead: bool,
+}
+
+impl WatchList {
+    pub fn from_file(path: &Path) -> Result<Self, ChandlerError> {
+        let mut file = util::open_file(path).map_err(ChandlerError::OpenFile)?;
+
+        let mut toml_str = String::new();
+        file.read_to_string(&mut toml_str).map_err(ChandlerError::ReadFile)?;
+
+        Self::from_str(&toml_str)
+    }
+
+    pub fn default_location() -> Option<PathBuf> {
+        chandler::config::get_default_config_path()
+    }
+
+    pub fn path_from_location(path: &Path) -> PathBuf {
+        path.join(WATCH_LIST_FILENAME)
+    }
+
+    /// Load the watch list from a file, or return an empty one if it does not exist.
+    pub fn from_file_or_default(path: &Path) -> Result<Self, ChandlerError> {
+        if path.exists() {
+            Self::from_file(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Load the watch list from a file, modify it and save it again.
+    /// The file is locked while doing so, so that concurrent modifications are not lost.
+    /// If the action fails, the watch list is not saved.
+    pub fn modify<T, E: From<ChandlerError>>(
+        path: &Path,
+        action: impl FnOnce(&mut Self) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let _lock = lock_file(path)?;
+
+        let mut watch_list = Self::from_file_or_default(path)?;
+
+        let result = action(&mut watch_list)?;
+
+        save_toml(&watch_list, path)?;
+
+        Ok(result)
+    }
+
+    pub fn get_mut(&mut self, url: &str) -> Option<&mut WatchListEntry> {
+        self.threads.iter_mut().find(|entry| entry.url == url)
+    }
+}
+
+impl WatchListEntry {
+    pub fn new(url: &str, interval: Option<u64>) -> Self {
+        Self {
+            url: url.to_owned(),
+            interval,
+            paused: false,
+        }
+    }
+}
+
+impl WatchListState {
+    /// Get the path of the state file for a watch list.
+    pub fn path_for(watch_list_path: &Path) -> PathBuf {
+        watch_list_path.with_file_name(WATCH_LIST_STATE_FILENAME)
+    }
+
+    /// Load the state from a file, or return an empty one if it does not exist.
+    pub fn from_file_or_default(path: &Path) -> Result<Self, ChandlerError> {
+        if path.exists() {
+            load_toml(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Load the state from a file, modify it and save it again.
+    /// The file is locked while doing so, so that concurrent modifications are not lost.
+    pub fn modify(path: &Path, action: impl FnOnce(&mut Self)) -> Result<(), ChandlerError> {
+        let _lock = lock_file(path)?;
+
+        let mut state = Self::from_file_or_default(path)?;
+
+        action(&mut state);
+
+        save_toml(&state, path)
+    }
+
+    pub fn get(&self, url: &str) -> Option<&WatchedThreadState> {
+        self.threads.get(url)
+    }
+
+    pub fn is_dead(&self, url: &str) -> bool {
+        self.get(url).is_some_and(|state| state.is_dead)
+    }
+}
+
+impl FromStr for WatchList {
+    type Err = ChandlerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let watch_list: Self = toml::from_str(s).map_err(|err| ChandlerError::ParseConfig(err.to_string().into()))?;
+
+        Ok(watch_list)
+    }
+}
+
+fn load_toml<T: DeserializeOwned>(path: &Path) -> Result<T, ChandlerError> {
+    let mut file = util::open_file(path).map_err(ChandlerError::OpenFile)?;
+
+    let mut toml_str = String::new();
+    file.read_to_string(&mut toml_str).map_err(ChandlerError::ReadFile)?;
+
+    toml::from_str(&toml_str).map_err(|err| ChandlerError::ParseConfig(err.to_string().into()))
+}
+
+/// Save a file by replacing it, so that it is never left partially written.
+fn save_toml<T: serde::Serialize>(value: &T, path: &Path) -> Result<(), ChandlerError> {
+    let toml_str = toml::to_string_pretty(value).map_err(|err| ChandlerError::Other(err.to_string().into()))?;
+
+    // Create config directory if necessary.
+    util::create_parent_dir(path).map_err(|err| ChandlerError::Other(err.to_string().into()))?;
+
+    util::write_file_atomic(path, toml_str).map_err(ChandlerError::CreateFile)
+}
+
+/// Lock a file for modification, using a lock file next to it.
+fn lock_file(path: &Path) -> Result<PidLock, ChandlerError> {
+    util::create_parent_dir(path).map_err(|err| ChandlerError::Other(err.to_string().into()))?;
+
+    PidLock::acquire_wait(path.with_extension("lock"), LOCK_TIMEOUT)
+        .ok_or_else(|| ChandlerError::Other(format!("{} is locked by another process!", path.display()).into()))
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chandler::config::sites::SitesConfig;
+use chandler::download::DownloadEngine;
+use chandler::error::*;
+use chandler::project::{self, Project};
+use chandler::ui::*;
+
+use crate::command;
+use crate::watcher::load_project;
+use crate::{GeneralOptions, ProjectOptions};
+
+use crate::error::*;
+
+/// Grabs threads linked from a project, following links up to a maximum depth.
+pub struct Crawler<'a> {
+    depth: u32,
+    sites_config: SitesConfig,
+    general_options: &'a GeneralOptions,
+    project_options: &'a ProjectOptions,
+
+    /// Download engine shared by all linked projects.
+    download_engine: Arc<DownloadEngine>,
+
+    /// Thread files of all threads that have been grabbed, by URL.
+    thread_file_paths: HashMap<String, PathBuf>,
+}
+
+impl<'a> Crawler<'a> {
+    pub fn new(
+        depth: u32,
+        general_options: &'a GeneralOptions,
+        project_options: &'a ProjectOptions,
+    ) -> Result<Self, CliError> {
+        let download_engine = project::builder()
+            .config_path(general_options.config_path.as_deref())
+            .use_chandler_config(true)?
+            .use_sites_config(true)?
+            .create_download_engine()?;
+
+        Ok(Self {
+            depth,
+            sites_config: SitesConfig::from_config_path(general_options.config_path.as_deref())?,
+            general_options,
+            project_options,
+            download_engine,
+            thread_file_paths: HashMap::new(),
+        })
+    }
+
+    /// Grab all threads linked from the project that have not already been grabbed,
+    /// and replace the links to them with links to their local copies.
+    pub fn crawl(&mut self, project: &mut dyn Project, ui: &mut dyn ChandlerUiHandler) -> Result<(), ChandlerError> {
+        // Include the project itself, so that links back to it are not grabbed.
+        self.thread_file_paths.insert(
+            project.get_url().to_owned(),
+            project.get_thread_file_path().to_path_buf(),
+        );
+
+        let mut grabbed_projects: Vec<Box<dyn Project>> = Vec::new();
+        let mut links = project.find_thread_links(&self.sites_config)?;
+
+        for depth in 1..=self.depth {
+            let mut next_links: Vec<String> = Vec::new();
+
+            for url in links {
+                if ui.is_cancelled() {
+                    break;
+                }
+
+                if self.thread_file_paths.contains_key(&url) {
+                    continue;
+                }
+
+                let linked_project = match self.grab(&url, ui) {
+                    Ok(linked_project) => linked_project,
+                    Err(err) => {
+                        // Linked threads are not essential, so carry on with the rest.
+                        // Threads that failed will be retried the next time the project is crawled.
+                        eprintln!("Error grabbing linked thread {}: {}", url, err);
+                        continue;
+                    }
+                };
+
+                self.thread_file_paths
+                    .insert(url, linked_project.get_thread_file_path().to_path_buf());
+
+                // Follow links from the linked thread, if the maximum depth has not been reached.
+                if depth < self.depth {
+                    next_links.extend(linked_project.find_thread_links(&self.sites_config)?);
+                }
+
+                grabbed_projects.push(linked_project);
+            }
+
+            links = next_links;
+        }
+
+        // Replace links now that all linked threads are available locally.
+        project.replace_thread_links(&self.thread_file_paths)?;
+
+        for linked_project in grabbed_projects.iter_mut() {
+            linked_project.replace_thread_links(&self.thread_file_paths)?;
+        }
+
+        Ok(())
+    }
+
+    fn grab(&self, url: &str, ui: &mut dyn ChandlerUiHandler) -> Result<Box<dyn Project>, ChandlerError> {
+        let mut project = load_project(url, &self.download_engine, self.general_options, self.project_options)?;
+
+        eprintln!("Grabbing linked thread: {}", url);
+
+        project.update(ui)?;
+        project.save()?;
+
+        command::update_index(project.as_ref(), self.general_options);
+
+        Ok(project)
+    }
+}
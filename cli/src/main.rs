@@ -7,8 +7,11 @@ use strum_macros::EnumString;
 
 mod command;
 mod config;
+mod crawl;
 mod error;
+mod schedule;
 mod ui;
+mod watcher;
 
 use chandler::project;
 use chandler::ui::*;
@@ -26,6 +29,16 @@ pub enum ProjectFormat {
     V3,
 }
 
+/// Thread status that stops watching.
+/// Watching always stops if the thread is archived or deleted.
+#[derive(Clone, Copy, Debug, EnumString, Eq, PartialEq)]
+#[strum(serialize_all = "kebab-case")]
+pub enum StopOn {
+    Locked,
+    BumpLimit,
+    ImageLimit,
+}
+
 #[derive(Debug, Parser)]
 #[clap(name = "Chandler", version = env!("CARGO_PKG_VERSION"), author = env!("CARGO_PKG_AUTHORS"))]
 struct Opt {
@@ -48,6 +61,18 @@ pub struct ProjectOptions {
     format: ProjectFormat,
 }
 
+#[derive(Debug, Parser)]
+pub struct WatchOptions {
+    #[clap(short = 'i', long = "interval", help = "Interval (seconds)", default_value = "600")]
+    interval: i64,
+    #[clap(
+        long = "stop-on",
+        value_delimiter = ',',
+        help = "Also stop watching threads when they are (locked|bump-limit|image-limit)"
+    )]
+    stop_on: Vec<StopOn>,
+}
+
 #[derive(Debug, Parser)]
 enum Command {
     #[clap(name = "generate-config", about = "Generate default configuration files")]
@@ -57,6 +82,12 @@ enum Command {
     Grab {
         #[clap(help = "URL of threads to download")]
         url: String,
+        #[clap(
+            long = "crawl-depth",
+            default_value = "0",
+            help = "Also grab threads linked from the thread, up to this many links away"
+        )]
+        crawl_depth: u32,
         #[clap(flatten)]
         project_options: ProjectOptions,
     },
@@ -65,17 +96,174 @@ enum Command {
         #[clap(help = "Path to project to rebuild")]
         path: PathBuf,
     },
+    #[clap(name = "export", about = "Export thread")]
+    Export {
+        #[clap(subcommand)]
+        command: ExportCommand,
+    },
+    #[clap(name = "import", about = "Import thread")]
+    Import {
+        #[clap(subcommand)]
+        command: ImportCommand,
+    },
+    #[clap(name = "index", about = "Generate an HTML index of all threads in the download path")]
+    Index,
     #[clap(name = "watch", about = "Watch thread")]
     Watch {
-        #[clap(help = "URL of thread to watch")]
-        url: String,
-        #[clap(short = 'i', long = "interval", help = "Interval (seconds)", default_value = "600")]
-        interval: i64,
+        #[clap(help = "URL of thread to watch", required_unless_present = "all")]
+        url: Option<String>,
+        #[clap(long = "all", help = "Watch all threads in the watch list", conflicts_with = "url")]
+        all: bool,
+        #[clap(
+            long = "crawl-depth",
+            default_value = "0",
+            conflicts_with = "all",
+            help = "Also grab threads linked from the thread, up to this many links away"
+        )]
+        crawl_depth: u32,
+        #[clap(flatten)]
+        watch_options: WatchOptions,
+        #[clap(flatten)]
+        project_options: ProjectOptions,
+    },
+    #[clap(name = "daemon", about = "Watch all threads in a watch list")]
+    Daemon {
+        #[clap(help = "Path to watch list file. If not specified, the watch list in the config directory is used")]
+        watch_list: Option<PathBuf>,
+        #[clap(flatten)]
+        watch_options: WatchOptions,
+        #[clap(flatten)]
+        project_options: ProjectOptions,
+    },
+    #[clap(name = "watchlist", about = "Manage the watch list")]
+    Watchlist {
+        #[clap(subcommand)]
+        command: WatchlistCommand,
+    },
+    #[clap(name = "catalog", about = "Watch board catalogs")]
+    Catalog {
+        #[clap(subcommand)]
+        command: CatalogCommand,
+    },
+}
+
+#[derive(Debug, Parser)]
+enum ExportCommand {
+    #[clap(name = "json", about = "Export posts and the local paths of their files as JSON")]
+    Json {
+        #[clap(help = "Path to project to export")]
+        path: PathBuf,
+        #[clap(
+            short = 'o',
+            long = "output",
+            help = "Path to write JSON to. If not specified, export.json in the project is used"
+        )]
+        output: Option<PathBuf>,
+    },
+    #[clap(
+        name = "html",
+        about = "Export thread as a single HTML file with its downloaded files embedded"
+    )]
+    Html {
+        #[clap(help = "Path to project to export")]
+        path: PathBuf,
+        #[clap(
+            short = 'o',
+            long = "output",
+            help = "Path to write HTML to. If not specified, export.html in the project is used"
+        )]
+        output: Option<PathBuf>,
+        #[clap(
+            long = "thumbnails-only",
+            help = "Only embed thumbnails, and link to full-size files on the site"
+        )]
+        thumbnails_only: bool,
+        #[clap(long = "max-size", help = "Maximum size of the exported file in MiB")]
+        max_size: Option<u64>,
+    },
+    #[clap(name = "warc", about = "Export original HTMLs and downloaded files as WARC")]
+    Warc {
+        #[clap(help = "Path to project to export")]
+        path: PathBuf,
+        #[clap(
+            short = 'o',
+            long = "output",
+            help = "Path to write WARC to. If not specified, export.warc in the project is used"
+        )]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Parser)]
+enum ImportCommand {
+    #[clap(name = "warc", about = "Create a V3 project from a WARC file exported by Chandler")]
+    Warc {
+        #[clap(help = "Path to WARC file to import")]
+        file: PathBuf,
+        #[clap(
+            short = 'p',
+            long = "path",
+            help = "Path to create project at. If not specified, it is created in the download path"
+        )]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Parser)]
+enum CatalogCommand {
+    #[clap(
+        name = "watch",
+        about = "Watch board catalog, and watch all threads matching any of the regexes"
+    )]
+    Watch {
+        #[clap(help = "URL of board to watch")]
+        board_url: String,
+        #[clap(
+            short = 'm',
+            long = "match",
+            required = true,
+            help = "Regex to match thread subjects and OP text against"
+        )]
+        patterns: Vec<String>,
+        #[clap(flatten)]
+        watch_options: WatchOptions,
         #[clap(flatten)]
         project_options: ProjectOptions,
     },
 }
 
+#[derive(Debug, Parser)]
+enum WatchlistCommand {
+    #[clap(name = "add", about = "Add thread to the watch list")]
+    Add {
+        #[clap(help = "URL of thread to add")]
+        url: String,
+        #[clap(
+            short = 'i',
+            long = "interval",
+            help = "Interval (seconds). If not specified, the default is used"
+        )]
+        interval: Option<u64>,
+    },
+    #[clap(name = "remove", about = "Remove thread from the watch list")]
+    Remove {
+        #[clap(help = "URL of thread to remove")]
+        url: String,
+    },
+    #[clap(name = "list", about = "List threads in the watch list")]
+    List,
+    #[clap(name = "pause", about = "Pause watching thread")]
+    Pause {
+        #[clap(help = "URL of thread to pause")]
+        url: String,
+    },
+    #[clap(name = "resume", about = "Resume watching thread")]
+    Resume {
+        #[clap(help = "URL of thread to resume")]
+        url: String,
+    },
+}
+
 impl From<ProjectFormat> for project::ProjectFormat {
     fn from(v: ProjectFormat) -> Self {
         match v {
@@ -157,15 +345,110 @@ fn main() {
 
     let cmd_result = match opt.command {
         Command::GenerateConfig => generate_default_configs(),
-        Command::Grab { url, project_options } => {
-            command::grab(&url, &opt.general_options, &project_options, ui.as_mut())
-        }
-        Command::Rebuild { path } => command::rebuild(&path, ui.as_mut()),
-        Command::Watch {
+        Command::Grab {
             url,
-            interval,
+            crawl_depth,
+            project_options,
+        } => command::grab(&url, crawl_depth, &opt.general_options, &project_options, ui.as_mut()),
+        Command::Rebuild { path } => command::rebuild(&path, &opt.general_options, ui.as_mut()),
+        Command::Export { command } => match command {
+            ExportCommand::Json { path, output } => {
+                command::export_json(&path, output.as_deref(), &opt.general_options)
+            }
+            ExportCommand::Html {
+                path,
+                output,
+                thumbnails_only,
+                max_size,
+            } => command::export_html(
+                &path,
+                output.as_deref(),
+                thumbnails_only,
+                max_size,
+                &opt.general_options,
+            ),
+            ExportCommand::Warc { path, output } => {
+                command::export_warc(&path, output.as_deref(), &opt.general_options)
+            }
+        },
+        Command::Import { command } => match command {
+            ImportCommand::Warc { file, path } => {
+                command::import_warc(&file, path.as_deref(), &opt.general_options, ui.as_mut())
+            }
+        },
+        Command::Index => command::index(&opt.general_options),
+        Command::Watch {
+            url: Some(url),
+            crawl_depth,
+            watch_options,
             project_options,
-        } => command::watch(&url, interval, &opt.general_options, &project_options, ui.as_mut()),
+            ..
+        } => command::watch(
+            &url,
+            crawl_depth,
+            &watch_options,
+            &cfg.watch,
+            &opt.general_options,
+            &project_options,
+            ui.as_mut(),
+        ),
+        Command::Watch {
+            url: None,
+            watch_options,
+            project_options,
+            ..
+        } => command::watch_list_path(&opt.general_options).and_then(|watch_list| {
+            command::daemon(
+                &watch_list,
+                &watch_options,
+                &cfg.watch,
+                &opt.general_options,
+                &project_options,
+                ui.as_mut(),
+            )
+        }),
+        Command::Daemon {
+            watch_list,
+            watch_options,
+            project_options,
+        } => watch_list
+            .map_or_else(|| command::watch_list_path(&opt.general_options), Ok)
+            .and_then(|watch_list| {
+                command::daemon(
+                    &watch_list,
+                    &watch_options,
+                    &cfg.watch,
+                    &opt.general_options,
+                    &project_options,
+                    ui.as_mut(),
+                )
+            }),
+        Command::Watchlist { command } => {
+            command::watch_list_path(&opt.general_options).and_then(|watch_list| match command {
+                WatchlistCommand::Add { url, interval } => command::watchlist_add(&watch_list, &url, interval),
+                WatchlistCommand::Remove { url } => command::watchlist_remove(&watch_list, &url),
+                WatchlistCommand::List => command::watchlist_list(&watch_list),
+                WatchlistCommand::Pause { url } => command::watchlist_pause(&watch_list, &url),
+                WatchlistCommand::Resume { url } => command::watchlist_resume(&watch_list, &url),
+            })
+        }
+        Command::Catalog {
+            command:
+                CatalogCommand::Watch {
+                    board_url,
+                    patterns,
+                    watch_options,
+                    project_options,
+                },
+        } => command::catalog_watch(
+            &board_url,
+            &patterns,
+            &watch_options,
+            &cfg.watch,
+            &opt.general_options,
+            &project_options,
+            ui.as_mut(),
+        ),
     };
 
     ui.clear();
@@ -0,0 +1,247 @@
+use std::fmt;
+use std::time::Duration;
+
+use rand::Rng;
+
+use chandler::project::ProjectUpdateResult;
+
+use crate::config::CliWatchConfig;
+
+/// Number of new posts in a single update for a thread to be considered active.
+const ACTIVE_POST_COUNT: u32 = 10;
+
+/// Number of consecutive updates without new posts before the interval is lengthened.
+const IDLE_UPDATE_COUNT: u32 = 2;
+
+/// Update interval that adapts to the activity of a thread.
+///
+/// The interval is halved when an update brings many new posts,
+/// and lengthened by half after repeated updates without new posts.
+pub struct UpdateSchedule {
+    adaptive: bool,
+    min_interval: u64,
+    max_interval: u64,
+    jitter: u64,
+
+    /// Current interval (seconds), before jitter.
+    interval: u64,
+
+    /// Number of consecutive updates without new posts.
+    idle_updates: u32,
+
+    reason: ScheduleReason,
+}
+
+/// Reason for the current interval.
+#[derive(Clone, Copy, Debug)]
+pub enum ScheduleReason {
+    Initial,
+    Fixed,
+    Active { new_post_count: u32 },
+    NewPosts { new_post_count: u32 },
+    Idle { idle_updates: u32 },
+}
+
+impl UpdateSchedule {
+    /// Create a schedule starting at the specified interval.
+    /// An interval below the configured minimum is honoured, as it was explicitly specified,
+    /// and the interval is never adapted below it.
+    pub fn new(interval: u64, config: &CliWatchConfig) -> Self {
+        let min_interval = config.min_interval.min(interval);
+        let max_interval = config.max_interval.max(min_interval);
+
+        Self {
+            adaptive: config.adaptive,
+            min_interval,
+            max_interval,
+            jitter: config.jitter.min(100),
+            interval: if config.adaptive {
+                interval.min(max_interval)
+            } else {
+                interval
+            },
+            idle_updates: 0,
+            reason: if config.adaptive {
+                ScheduleReason::Initial
+            } else {
+                ScheduleReason::Fixed
+            },
+        }
+    }
+
+    /// Adjust the interval based on the result of an update.
+    pub fn record_update(&mut self, result: &ProjectUpdateResult) {
+        if !self.adaptive {
+            return;
+        }
+
+        let new_post_count = if result.was_updated { result.new_post_count } else { 0 };
+
+        if new_post_count >= ACTIVE_POST_COUNT {
+            self.idle_updates = 0;
+            self.interval /= 2;
+            self.reason = ScheduleReason::Active { new_post_count };
+        } else if new_post_count > 0 {
+            self.idle_updates = 0;
+            self.reason = ScheduleReason::NewPosts { new_post_count };
+        } else {
+            self.idle_updates += 1;
+
+            if self.idle_updates >= IDLE_UPDATE_COUNT {
+                self.interval += self.interval / 2;
+            }
+
+            self.reason = ScheduleReason::Idle {
+                idle_updates: self.idle_updates,
+            };
+        }
+
+        self.interval = self.interval.clamp(self.min_interval, self.max_interval);
+    }
+
+    /// Current interval (seconds), before jitter.
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    pub fn reason(&self) -> ScheduleReason {
+        self.reason
+    }
+
+    /// Get the time to wait until the next update, with random jitter applied.
+    pub fn next_wait(&self) -> Duration {
+        let max_jitter = self.interval * self.jitter / 100;
+
+        let seconds = if max_jitter > 0 {
+            let jitter = rand::thread_rng().gen_range(0..=max_jitter * 2);
+
+            (self.interval + jitter).saturating_sub(max_jitter)
+        } else {
+            self.interval
+        };
+
+        Duration::from_secs(seconds.max(1))
+    }
+}
+
+impl fmt::Display for ScheduleReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Initial => write!(f, "initial interval"),
+            Self::Fixed => write!(f, "fixed interval"),
+            Self::Active { new_post_count } => write!(f, "thread is active, {new_post_count} new posts"),
+            Self::NewPosts { new_post_count } => write!(f, "{new_post_count} new posts"),
+            Self::Idle { idle_updates } => write!(f, "no new posts in {idle_updates} updates"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chandler::threadparser::ThreadStatus;
+
+    use super::*;
+
+    fn config(adaptive: bool, jitter: u64) -> CliWatchConfig {
+        CliWatchConfig {
+            adaptive,
+            min_interval: 60,
+            max_interval: 3600,
+            jitter,
+        }
+    }
+
+    fn update(new_post_count: u32) -> ProjectUpdateResult {
+        ProjectUpdateResult {
+            was_updated: new_post_count > 0,
+            is_dead: false,
+            status: ThreadStatus::default(),
+            new_post_count,
+            edited_post_count: 0,
+            deleted_post_count: 0,
+            new_file_count: 0,
+        }
+    }
+
+    #[test]
+    fn halves_interval_when_active() {
+        let mut schedule = UpdateSchedule::new(600, &config(true, 0));
+
+        schedule.record_update(&update(ACTIVE_POST_COUNT));
+        assert_eq!(schedule.interval(), 300);
+
+        // A few new posts should not change the interval.
+        schedule.record_update(&update(1));
+        assert_eq!(schedule.interval(), 300);
+    }
+
+    #[test]
+    fn lengthens_interval_when_idle() {
+        let mut schedule = UpdateSchedule::new(600, &config(true, 0));
+
+        for _ in 1..IDLE_UPDATE_COUNT {
+            schedule.record_update(&update(0));
+            assert_eq!(schedule.interval(), 600);
+        }
+
+        schedule.record_update(&update(0));
+        assert_eq!(schedule.interval(), 900);
+
+        schedule.record_update(&update(0));
+        assert_eq!(schedule.interval(), 1350);
+
+        // New posts should reset the idle count.
+        schedule.record_update(&update(1));
+        schedule.record_update(&update(0));
+        assert_eq!(schedule.interval(), 1350);
+    }
+
+    #[test]
+    fn clamps_interval() {
+        let mut schedule = UpdateSchedule::new(100, &config(true, 0));
+
+        schedule.record_update(&update(ACTIVE_POST_COUNT));
+        assert_eq!(schedule.interval(), 60);
+
+        let mut schedule = UpdateSchedule::new(3000, &config(true, 0));
+
+        for _ in 0..IDLE_UPDATE_COUNT {
+            schedule.record_update(&update(0));
+        }
+
+        assert_eq!(schedule.interval(), 3600);
+
+        // The initial interval should be limited to the maximum.
+        assert_eq!(UpdateSchedule::new(7200, &config(true, 0)).interval(), 3600);
+    }
+
+    #[test]
+    fn honours_interval_below_minimum() {
+        let mut schedule = UpdateSchedule::new(30, &config(true, 0));
+        assert_eq!(schedule.interval(), 30);
+
+        schedule.record_update(&update(ACTIVE_POST_COUNT));
+        assert_eq!(schedule.interval(), 30);
+    }
+
+    #[test]
+    fn does_not_adapt_fixed_interval() {
+        let mut schedule = UpdateSchedule::new(7200, &config(false, 0));
+
+        schedule.record_update(&update(ACTIVE_POST_COUNT));
+        assert_eq!(schedule.interval(), 7200);
+    }
+
+    #[test]
+    fn applies_jitter_within_bounds() {
+        let schedule = UpdateSchedule::new(600, &config(true, 10));
+
+        for _ in 0..100 {
+            let wait = schedule.next_wait().as_secs();
+            assert!((540..=660).contains(&wait), "wait {wait} out of bounds");
+        }
+
+        let schedule = UpdateSchedule::new(600, &config(true, 0));
+        assert_eq!(schedule.next_wait(), Duration::from_secs(600));
+    }
+}
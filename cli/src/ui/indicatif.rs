@@ -110,12 +110,14 @@ impl ChandlerUiHandler for IndicatifUiHandler {
             UiEvent::UpdateComplete {
                 was_updated,
                 new_post_count,
+                edited_post_count,
+                deleted_post_count,
                 new_file_count,
             } => {
                 if *was_updated {
                     eprintln!(
-                        "Update finished. {} new posts, {} new files.",
-                        new_post_count, new_file_count
+                        "Update finished. {} new posts, {} edited posts, {} deleted posts, {} new files.",
+                        new_post_count, edited_post_count, deleted_post_count, new_file_count
                     );
                 } else {
                     eprintln!("Thread not changed.");
@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use tracing::{info, warn};
+
+use chandler::download::DownloadEngine;
+use chandler::error::*;
+use chandler::project::{self, Project};
+use chandler::ui::*;
+
+use crate::command::{self, stop_reason};
+use crate::config::{CliWatchConfig, WatchListState};
+use crate::schedule::UpdateSchedule;
+use crate::{GeneralOptions, ProjectOptions, StopOn, WatchOptions};
+
+use crate::error::*;
+
+/// How often to check for due updates.
+pub const TICK: Duration = Duration::from_secs(1);
+
+/// Watches multiple threads in a single process, each on its own schedule.
+pub struct ThreadWatcher<'a> {
+    threads: HashMap<String, WatchedThread>,
+    default_interval: u64,
+    stop_on: &'a [StopOn],
+    watch_config: &'a CliWatchConfig,
+    general_options: &'a GeneralOptions,
+    project_options: &'a ProjectOptions,
+
+    /// Download engine shared by all projects.
+    download_engine: Arc<DownloadEngine>,
+
+    /// Watch list to record updates in, if any.
+    watch_list_path: Option<PathBuf>,
+}
+
+/// A thread being watched.
+struct WatchedThread {
+    /// Update interval specified for the thread, if any.
+    base_interval: Option<u64>,
+
+    /// Update schedule.
+    schedule: UpdateSchedule,
+
+    /// Time of the next update.
+    next_update: Instant,
+
+    /// Loaded project. Projects are loaded on the first update.
+    project: Option<Box<dyn Project>>,
+
+    /// Whether the thread is finished, and should no longer be updated.
+    is_finished: bool,
+}
+
+impl<'a> ThreadWatcher<'a> {
+    pub fn new(
+        watch_options: &'a WatchOptions,
+        watch_config: &'a CliWatchConfig,
+        general_options: &'a GeneralOptions,
+        project_options: &'a ProjectOptions,
+    ) -> Result<Self, CliError> {
+        // Create a download engine to be shared by all projects.
+        let download_engine = project::builder()
+            .config_path(general_options.config_path.as_deref())
+            .use_chandler_config(true)?
+            .use_sites_config(true)?
+            .create_download_engine()?;
+
+        Ok(Self {
+            threads: HashMap::new(),
+            default_interval: watch_options.interval as u64,
+            stop_on: &watch_options.stop_on,
+            watch_config,
+            general_options,
+            project_options,
+            download_engine,
+            watch_list_path: None,
+        })
+    }
+
+    /// Record the last checked time and dead status of threads in the watch list.
+    pub fn with_watch_list(mut self, watch_list_path: &Path) -> Self {
+        self.watch_list_path = Some(watch_list_path.to_path_buf());
+
+        self
+    }
+
+    pub fn download_engine(&self) -> &DownloadEngine {
+        &self.download_engine
+    }
+
+    pub fn is_watching(&self, url: &str) -> bool {
+        self.threads.contains_key(url)
+    }
+
+    /// Start watching a thread, or update its interval if it is already being watched.
+    pub fn watch(&mut self, url: &str, base_interval: Option<u64>) {
+        let schedule = UpdateSchedule::new(base_interval.unwrap_or(self.default_interval), self.watch_config);
+
+        if let Some(thread) = self.threads.get_mut(url) {
+            // Restart the schedule if the interval was changed.
+            if thread.base_interval != base_interval {
+                thread.base_interval = base_interval;
+                thread.schedule = schedule;
+            }
+        } else {
+            eprintln!("Started watching: {}", url);
+
+            self.threads.insert(
+                url.to_owned(),
+                WatchedThread {
+                    base_interval,
+                    schedule,
+                    next_update: Instant::now(),
+                    project: None,
+                    is_finished: false,
+                },
+            );
+        }
+    }
+
+    /// Stop watching all threads that do not match the predicate.
+    /// Dropping the project releases its PID lock.
+    pub fn retain(&mut self, mut predicate: impl FnMut(&str) -> bool) {
+        self.threads.retain(|url, _| {
+            let keep = predicate(url);
+
+            if !keep {
+                eprintln!("Stopped watching: {}", url);
+            }
+
+            keep
+        });
+    }
+
+    /// Update all threads that are due.
+    pub fn update_due(&mut self, ui: &mut dyn ChandlerUiHandler) {
+        let urls: Vec<String> = self
+            .threads
+            .iter()
+            .filter(|(_, thread)| !thread.is_finished && thread.next_update <= Instant::now())
+            .map(|(url, _)| url.clone())
+            .collect();
+
+        for url in urls {
+            if ui.is_cancelled() {
+                break;
+            }
+
+            self.update_thread(&url, ui);
+        }
+    }
+
+    fn update_thread(&mut self, url: &str, ui: &mut dyn ChandlerUiHandler) {
+        let Some(thread) = self.threads.get_mut(url) else {
+            return;
+        };
+
+        thread.next_update = Instant::now() + thread.schedule.next_wait();
+
+        // Load the project if it has not already been loaded.
+        // If it is locked by another process, it will be retried on the next update.
+        let project = match &mut thread.project {
+            Some(project) => project,
+            project @ None => {
+                match load_project(url, &self.download_engine, self.general_options, self.project_options) {
+                    Ok(loaded) => project.insert(loaded),
+                    Err(err) => {
+                        eprintln!("Could not load project for {}: {}", url, err);
+                        return;
+                    }
+                }
+            }
+        };
+
+        eprintln!("Updating: {}", url);
+
+        let update_result = match project.update(ui) {
+            Ok(update_result) => update_result,
+            Err(err) => {
+                // The error has already been reported through the UI handler.
+                // Retry on the next update.
+                info!("Error updating {}: {}", url, err);
+                return;
+            }
+        };
+
+        // Save changes to disk.
+        if let Err(err) = project.save() {
+            eprintln!("Error saving project for {}: {}", url, err);
+        }
+
+        command::update_index(project.as_ref(), self.general_options);
+
+        // Record the update in the watch list's state.
+        if let Some(watch_list_path) = &self.watch_list_path {
+            let result = WatchListState::modify(&WatchListState::path_for(watch_list_path), |state| {
+                let entry = state.threads.entry(url.to_owned()).or_default();
+                entry.last_checked = Some(Utc::now());
+                entry.is_dead = update_result.is_dead;
+            });
+
+            if let Err(err) = result {
+                warn!("Error saving watch list state: {}", err);
+            }
+        }
+
+        let reason = if update_result.is_dead {
+            Some("Thread is dead.")
+        } else {
+            stop_reason(&update_result.status, self.stop_on)
+        };
+
+        if let Some(reason) = reason {
+            eprintln!("{} Stopped watching: {}", reason, url);
+
+            // Release the project, as it will no longer be updated.
+            thread.project = None;
+            thread.is_finished = true;
+
+            return;
+        }
+
+        // Adjust the interval based on the thread's activity.
+        thread.schedule.record_update(&update_result);
+
+        let wait = thread.schedule.next_wait();
+        thread.next_update = Instant::now() + wait;
+
+        eprintln!(
+            "Next update of {} in {} seconds (interval {}s: {}).",
+            url,
+            wait.as_secs(),
+            thread.schedule.interval(),
+            thread.schedule.reason()
+        );
+    }
+}
+
+pub fn load_project(
+    url: &str,
+    download_engine: &Arc<DownloadEngine>,
+    general_options: &GeneralOptions,
+    project_options: &ProjectOptions,
+) -> Result<Box<dyn Project>, ChandlerError> {
+    project::builder()
+        .url(url)
+        .config_path(general_options.config_path.as_deref())
+        .use_chandler_config(true)?
+        .use_sites_config(true)?
+        .format(Some(project_options.format.into()))
+        .download_engine(Some(Arc::clone(download_engine)))
+        .load_or_create()
+}
@@ -0,0 +1,93 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use serde_derive::Deserialize;
+use url::Url;
+
+use crate::html;
+use crate::threadapi::fourchan::FourchanApiPost;
+use crate::util;
+
+use super::*;
+
+/// A page of the catalog, as returned by the 4chan API.
+#[derive(Debug, Deserialize)]
+struct FourchanCatalogPage {
+    threads: Vec<FourchanApiPost>,
+}
+
+/// Get the API URL for a board's catalog.
+pub fn get_catalog_api_url(api_url: &str, board: &str) -> String {
+    format!("{}/{}/catalog.json", api_url.trim_end_matches('/'), board)
+}
+
+pub fn parse_catalog_file(path: &Path, board_url: &Url) -> Result<Vec<CatalogThread>, ChandlerError> {
+    let file = util::open_file(path).map_err(ChandlerError::OpenFile)?;
+
+    let pages: Vec<FourchanCatalogPage> = serde_json::from_reader(file).map_err(|err| {
+        ChandlerError::Other(Cow::Owned(format!(
+            "Error parsing 4chan API catalog '{}': {}",
+            path.display(),
+            err
+        )))
+    })?;
+
+    pages
+        .into_iter()
+        .flat_map(|page| page.threads)
+        .map(|op| catalog_thread(&op, board_url))
+        .collect()
+}
+
+fn catalog_thread(op: &FourchanApiPost, board_url: &Url) -> Result<CatalogThread, ChandlerError> {
+    let url = board_url
+        .join(&format!("thread/{}", op.no))
+        .map_err(|err| ChandlerError::Other(format!("Error constructing thread URL: {err}").into()))?;
+
+    // Comments are HTML, so get their text the same way as for posts.
+    // Line breaks are replaced with spaces, so that lines are not joined together.
+    let text = op
+        .com
+        .as_deref()
+        .map(|com| html::post_text(&html::parse_string(&com.replace("<br>", " "))))
+        .unwrap_or_default();
+
+    Ok(CatalogThread {
+        url: url.to_string(),
+        subject: op.sub.as_deref().map(|sub| html::post_text(&html::parse_string(sub))),
+        text,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::test_dir;
+
+    const CATALOG_JSON: &str = r#"[
+        {"page": 1, "threads": [
+            {"no": 1, "sub": "Rust &amp; friends general", "com": "Previous: &gt;&gt;&gt;/g/100<br>Welcome"},
+            {"no": 2, "com": "Just a thread"}
+        ]},
+        {"page": 2, "threads": [{"no": 3, "sub": "Another general"}]}
+    ]"#;
+
+    #[test]
+    fn can_parse_catalog() {
+        let path = test_dir("fourchan_catalog").join("catalog.json");
+        std::fs::write(&path, CATALOG_JSON).unwrap();
+
+        let board_url = Url::parse("https://boards.4chan.org/g/").unwrap();
+        let threads = parse_catalog_file(&path, &board_url).unwrap();
+
+        assert_eq!(threads.len(), 3);
+        assert_eq!(threads[0].url, "https://boards.4chan.org/g/thread/1");
+        assert_eq!(threads[0].subject.as_deref(), Some("Rust & friends general"));
+        assert_eq!(threads[0].text, "Previous: >>>/g/100 Welcome");
+        assert_eq!(threads[1].subject, None);
+
+        let regexes = [Regex::new("(?i)rust").unwrap()];
+        assert!(threads[0].matches(&regexes));
+        assert!(!threads[1].matches(&regexes));
+    }
+}
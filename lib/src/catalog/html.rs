@@ -0,0 +1,108 @@
+use html5ever::local_name;
+use kuchikiki::iter::NodeIterator;
+use kuchikiki::{NodeRef, Selectors};
+use url::Url;
+
+use crate::config::sites::HtmlCatalogDef;
+use crate::html;
+
+use super::*;
+
+/// Parser for HTML catalog pages, using CSS selectors to locate threads.
+pub struct HtmlCatalogParser {
+    thread_selector: Selectors,
+    link_selector: Selectors,
+    subject_selector: Option<Selectors>,
+    text_selector: Option<Selectors>,
+}
+
+impl HtmlCatalogParser {
+    pub fn new(def: &HtmlCatalogDef) -> Result<Self, ChandlerError> {
+        fn compile(selector: &str) -> Result<Selectors, ChandlerError> {
+            Selectors::compile(selector)
+                .map_err(|_| ChandlerError::Config(format!("Invalid CSS selector: {selector}").into()))
+        }
+
+        Ok(Self {
+            thread_selector: compile(&def.thread_selector)?,
+            link_selector: compile(&def.link_selector)?,
+            subject_selector: def.subject_selector.as_deref().map(compile).transpose()?,
+            text_selector: def.text_selector.as_deref().map(compile).transpose()?,
+        })
+    }
+
+    /// Get all threads in a catalog page.
+    /// Thread elements without a link are skipped.
+    pub fn parse(&self, document: &NodeRef, catalog_url: &Url) -> Result<Vec<CatalogThread>, ChandlerError> {
+        Ok(self
+            .thread_selector
+            .filter(document.inclusive_descendants().elements())
+            .filter_map(|e| self.catalog_thread(e.as_node(), catalog_url))
+            .collect())
+    }
+
+    fn catalog_thread(&self, node: &NodeRef, catalog_url: &Url) -> Option<CatalogThread> {
+        let url = {
+            let link = select_first(node, &self.link_selector)?;
+            let data = link.as_element()?;
+            let attrs = data.attributes.borrow();
+
+            catalog_url.join(attrs.get(local_name!("href"))?).ok()?
+        };
+
+        let subject = self
+            .subject_selector
+            .as_ref()
+            .and_then(|selector| select_first(node, selector))
+            .map(|subject| html::post_text(&subject));
+
+        // If no text selector was specified, use all text in the thread element.
+        let text = match &self.text_selector {
+            Some(selector) => select_first(node, selector).map(|text| html::post_text(&text)),
+            None => Some(html::post_text(node)),
+        };
+
+        Some(CatalogThread {
+            url: url.to_string(),
+            subject,
+            text: text.unwrap_or_default(),
+        })
+    }
+}
+
+/// Get the first element matching the selector.
+fn select_first(root: &NodeRef, selector: &Selectors) -> Option<NodeRef> {
+    selector
+        .filter(root.inclusive_descendants().elements())
+        .next()
+        .map(|e| e.as_node().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG_HTML: &str = r#"<div class="threads"><div class="thread"><a href="res/1.html"><img></a><span class="subject">Cats</span><p class="text">Post your cats</p></div><div class="thread"><a href="/b/res/2.html"></a><p class="text">No subject</p></div><div class="thread"><p>No link</p></div></div>"#;
+
+    #[test]
+    fn can_parse_catalog() {
+        let parser = HtmlCatalogParser::new(&HtmlCatalogDef {
+            catalog_path: "catalog.html".to_owned(),
+            thread_selector: "div.thread".to_owned(),
+            link_selector: "a".to_owned(),
+            subject_selector: Some("span.subject".to_owned()),
+            text_selector: Some("p.text".to_owned()),
+        })
+        .unwrap();
+
+        let catalog_url = Url::parse("https://examplechan.org/b/catalog.html").unwrap();
+        let threads = parser.parse(&html::parse_string(CATALOG_HTML), &catalog_url).unwrap();
+
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].url, "https://examplechan.org/b/res/1.html");
+        assert_eq!(threads[0].subject.as_deref(), Some("Cats"));
+        assert_eq!(threads[0].text, "Post your cats");
+        assert_eq!(threads[1].url, "https://examplechan.org/b/res/2.html");
+        assert_eq!(threads[1].subject, None);
+    }
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use tracing::info;
+use url::Url;
+
+use crate::config::sites::{CatalogParserDef, SitesConfig};
+use crate::download::{DownloadEngine, DownloadResult};
+use crate::error::*;
+use crate::ui::*;
+use crate::util;
+
+mod fourchan;
+mod html;
+
+pub use self::html::*;
+
+/// Catalog of a board.
+pub enum Catalog {
+    /// Catalog from 4chan's JSON API.
+    FourChan {
+        /// URL of the board's catalog JSON.
+        url: String,
+
+        /// URL of the board, used to construct thread URLs.
+        board_url: Url,
+    },
+
+    /// HTML catalog page.
+    Html {
+        /// URL of the catalog page.
+        url: Url,
+
+        parser: HtmlCatalogParser,
+    },
+}
+
+/// A thread listed in a board's catalog.
+#[derive(Clone, Debug)]
+pub struct CatalogThread {
+    pub url: String,
+    pub subject: Option<String>,
+    pub text: String,
+}
+
+impl Catalog {
+    pub fn url(&self) -> &str {
+        match self {
+            Self::FourChan { url, .. } => url,
+            Self::Html { url, .. } => url.as_str(),
+        }
+    }
+
+    /// Download the catalog and get all threads listed in it.
+    pub fn fetch(
+        &self,
+        download_engine: &DownloadEngine,
+        ui_handler: &mut dyn ChandlerUiHandler,
+    ) -> Result<Vec<CatalogThread>, ChandlerError> {
+        info!("Fetching catalog: {}", self.url());
+
+        let file_path = util::temp_file_path("catalog", "tmp");
+
+        let result = download_engine
+            .download_file(self.url(), &file_path, None, ui_handler)
+            .and_then(|result| match result {
+                DownloadResult::Success { .. } => self.parse_file(&file_path),
+                result => Err(ChandlerError::Other(
+                    format!("Unexpected download result: {:?}", result).into(),
+                )),
+            });
+
+        // Clean up the downloaded catalog, as it is only needed for parsing.
+        let _ = fs::remove_file(&file_path);
+
+        result
+    }
+
+    pub fn parse_file(&self, path: &Path) -> Result<Vec<CatalogThread>, ChandlerError> {
+        match self {
+            Self::FourChan { board_url, .. } => fourchan::parse_catalog_file(path, board_url),
+            Self::Html { url, parser } => parser.parse(&crate::html::parse_file(path)?, url),
+        }
+    }
+}
+
+impl CatalogThread {
+    /// Check whether the subject or OP text matches any of the regexes.
+    pub fn matches(&self, regexes: &[Regex]) -> bool {
+        regexes.iter().any(|regex| {
+            self.subject.as_deref().is_some_and(|subject| regex.is_match(subject)) || regex.is_match(&self.text)
+        })
+    }
+}
+
+impl SitesConfig {
+    /// Get the catalog of the board at the specified URL, if its site supports catalogs.
+    pub fn resolve_catalog(&self, board_url: &str) -> Result<Option<Catalog>, ChandlerError> {
+        for def in self.sites.values().filter_map(|site| site.catalog.as_ref()) {
+            let regexes = def.url_regexes.build_regexes()?;
+
+            for regex in regexes.iter() {
+                let Some(caps) = regex.captures(board_url) else {
+                    continue;
+                };
+
+                let board_url = parse_board_url(board_url)?;
+
+                return Ok(Some(match &def.parser {
+                    CatalogParserDef::FourChan { url } => {
+                        let board = caps.get(1).map(|m| m.as_str()).ok_or_else(|| {
+                            ChandlerError::Config("4chan catalog URL regexes must capture the board name.".into())
+                        })?;
+
+                        Catalog::FourChan {
+                            url: fourchan::get_catalog_api_url(url, board),
+                            board_url,
+                        }
+                    }
+                    CatalogParserDef::Html(def) => Catalog::Html {
+                        url: board_url.join(&def.catalog_path).map_err(|err| {
+                            ChandlerError::Config(format!("Invalid catalog path '{}': {err}", def.catalog_path).into())
+                        })?,
+                        parser: HtmlCatalogParser::new(def)?,
+                    },
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Parse a board URL, ensuring that it ends with a slash so that relative URLs can be joined to it.
+fn parse_board_url(board_url: &str) -> Result<Url, ChandlerError> {
+    let mut url =
+        Url::parse(board_url).map_err(|err| ChandlerError::Other(format!("Error parsing board URL: {err}").into()))?;
+
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SITES_TOML: &str = r#"
+[sites."examplechan"]
+url-regexes = ['^https://examplechan.org/(\w+)/res/(\d+)']
+parser = "tinyboard"
+
+[sites."examplechan".catalog]
+type = "html"
+url-regexes = ['^https://examplechan.org/(\w+)/?$']
+catalog-path = "catalog.html"
+thread-selector = "div.thread"
+
+[sites."4chan"]
+url-regexes = ['^https://boards.4chan.org/(\w+)/thread/(\d+)']
+parser = "4chan"
+catalog = { type = "4chan", url-regexes = ['^https://boards.4chan.org/(\w+)/?$'] }
+"#;
+
+    #[test]
+    fn can_resolve_catalog() {
+        let sites: SitesConfig = SITES_TOML.parse().unwrap();
+
+        let catalog = sites.resolve_catalog("https://examplechan.org/b").unwrap().unwrap();
+        assert_eq!(catalog.url(), "https://examplechan.org/b/catalog.html");
+
+        let catalog = sites.resolve_catalog("https://boards.4chan.org/g/").unwrap().unwrap();
+        assert_eq!(catalog.url(), "https://a.4cdn.org/g/catalog.json");
+
+        assert!(sites.resolve_catalog("https://otherchan.org/b/").unwrap().is_none());
+    }
+}
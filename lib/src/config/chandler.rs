@@ -13,10 +13,154 @@ pub const CONFIG_FILENAME: &str = "config.toml";
 
 pub const DEFAULT_CONFIG_TOML: &str = include_str!("default_config.toml");
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct ChandlerConfig {
     pub download_path: Option<PathBuf>,
+    #[serde(default)]
+    pub download: DownloadConfig,
+    #[serde(default)]
+    pub update: UpdateConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DownloadConfig {
+    /// Number of files to download simultaneously.
+    #[serde(default = "default_download_workers")]
+    pub workers: usize,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RetryConfig {
+    /// Seconds to wait before retrying a failed download the first time.
+    /// The delay is doubled for each subsequent failure.
+    #[serde(default = "default_retry_initial_delay")]
+    pub initial_delay: u64,
+
+    /// Maximum number of seconds to wait before retrying a failed download.
+    #[serde(default = "default_retry_max_delay")]
+    pub max_delay: u64,
+
+    /// Number of attempts after which a file that was not found (404/410) is given up on.
+    #[serde(default = "default_retry_not_found_attempts")]
+    pub not_found_attempts: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct UpdateConfig {
+    /// What to do when a post that has already been downloaded is changed upstream.
+    #[serde(default)]
+    pub edit_policy: EditPolicy,
+
+    /// What to remove from thread HTML before it is archived.
+    #[serde(default)]
+    pub sanitize: SanitizeProfile,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EditPolicy {
+    /// Keep the original version of the post, and only mark it as edited.
+    #[default]
+    KeepOriginal,
+
+    /// Replace the post with the new version.
+    Replace,
+
+    /// Replace the post with the new version, keeping the original version collapsed inside it.
+    KeepBoth,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SanitizeProfile {
+    /// Only remove scripts.
+    #[default]
+    Minimal,
+
+    /// Also remove event handlers, javascript: URLs, frames, redirects, tracking pixels, <noscript> fallbacks
+    /// and links to prefetch or preconnect, and add a Content Security Policy that prevents the page from making network requests.
+    Strict,
+
+    /// Also remove forms, plugins and base elements.
+    Paranoid,
+}
+
+/// Used to specify serde default value for the "workers" field.
+fn default_download_workers() -> usize {
+    4
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RateLimitConfig {
+    /// Minimum number of milliseconds between requests to the same host.
+    #[serde(default)]
+    pub min_delay: u64,
+
+    /// Maximum number of simultaneous requests to the same host.
+    #[serde(default = "default_rate_limit_max_connections")]
+    pub max_connections: usize,
+
+    /// Maximum total download speed in KiB per second.
+    pub max_bandwidth: Option<u64>,
+}
+
+/// Used to specify serde default value for the "initial-delay" field.
+fn default_retry_initial_delay() -> u64 {
+    60
+}
+
+/// Used to specify serde default value for the "max-delay" field.
+fn default_retry_max_delay() -> u64 {
+    6 * 60 * 60
+}
+
+/// Used to specify serde default value for the "not-found-attempts" field.
+fn default_retry_not_found_attempts() -> u32 {
+    3
+}
+
+/// Used to specify serde default value for the "max-connections" field.
+fn default_rate_limit_max_connections() -> usize {
+    4
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            workers: default_download_workers(),
+            retry: RetryConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: default_retry_initial_delay(),
+            max_delay: default_retry_max_delay(),
+            not_found_attempts: default_retry_not_found_attempts(),
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            min_delay: 0,
+            max_connections: default_rate_limit_max_connections(),
+            max_bandwidth: None,
+        }
+    }
 }
 
 impl ChandlerConfig {
@@ -55,6 +199,21 @@ impl ChandlerConfig {
         }
     }
 
+    /// Get the path projects are created in by default.
+    /// If none is configured, a chandler3 directory in the user's download directory is used.
+    pub fn get_download_path(&self) -> Result<PathBuf, ChandlerError> {
+        if let Some(download_path) = &self.download_path {
+            Ok(util::normalize_path(download_path))
+        } else if let Some(os_download_path) = dirs::download_dir() {
+            Ok(os_download_path.join("chandler3"))
+        } else {
+            Err(ChandlerError::Config(
+                "No default download directory found. A download path must be specified in the Chandler config file."
+                    .into(),
+            ))
+        }
+    }
+
     pub fn write_default() -> Result<(), ChandlerError> {
         if let Some(config_location) = Self::default_location() {
             let config_file_path = Self::path_from_location(&config_location)?;
@@ -1,5 +1,9 @@
+use std::sync::Arc;
+
 use serde_derive::{Deserialize, Serialize};
 
+use crate::error::*;
+use crate::threadparser::custom::CustomParser;
 use crate::threadupdater::ParserType;
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
@@ -14,11 +18,115 @@ pub enum Parser {
     FoolFuuka,
     Ponychan,
     Lainchan,
+    Custom,
 }
 
-impl From<Parser> for ParserType {
-    fn from(parser: Parser) -> Self {
-        match parser {
+/// Definition of a custom parser, used with the "custom" parser type.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CustomParserDef {
+    /// CSS selector matching reply elements.
+    pub reply_selector: String,
+
+    /// CSS selector matching the OP element.
+    pub op_selector: String,
+
+    /// CSS selector matching the element that new replies should be appended to,
+    /// if the thread has no replies yet.
+    /// If not specified, new replies will be inserted after the OP element.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insert_selector: Option<String>,
+
+    /// Regex used to get the reply ID from the "id" attribute of reply elements.
+    /// The first capture group must contain the numeric ID.
+    pub reply_id_regex: String,
+
+    /// Regex used to get the post ID from the "id" attribute of the OP element.
+    /// The first capture group must contain the numeric ID.
+    /// If not specified, the first run of digits in the "id" attribute is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op_id_regex: Option<String>,
+
+    /// CSS selector matching an element that is only present if the thread is locked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_selector: Option<String>,
+
+    /// CSS selector matching an element that is only present if the thread is archived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_selector: Option<String>,
+
+    /// Selectors used to extract post data from post elements.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post: Option<PostSelectorsDef>,
+}
+
+/// CSS selectors used to extract post data, relative to the post element.
+/// Data that has no selector is not extracted.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PostSelectorsDef {
+    /// Element containing the subject.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject_selector: Option<String>,
+
+    /// Element containing the poster's name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_selector: Option<String>,
+
+    /// Element containing the tripcode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tripcode_selector: Option<String>,
+
+    /// Element containing the poster ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poster_id_selector: Option<String>,
+
+    /// Element containing the time of the post, either in a "datetime" attribute
+    /// or as a UNIX timestamp in a "data-utc" or "data-time" attribute.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_selector: Option<String>,
+
+    /// Element containing the comment.
+    /// If not specified, the whole post element is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment_selector: Option<String>,
+
+    /// Element for each attached file. The file selectors below are relative to it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_selector: Option<String>,
+
+    /// Link to the file. Files without one are skipped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_link_selector: Option<String>,
+
+    /// Thumbnail image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_thumbnail_selector: Option<String>,
+
+    /// Element containing the original filename, either in a "title" attribute or as its text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name_selector: Option<String>,
+
+    /// Element containing the file size and dimensions, such as "(123 KB, 800x600)".
+    /// If not specified, the text of the whole file element is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_info_selector: Option<String>,
+
+    /// Placeholder shown instead of a deleted file, relative to the post element.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_deleted_selector: Option<String>,
+
+    /// Elements containing other posts, such as replies nested inside the OP.
+    /// Nothing inside them is considered part of the post.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_selector: Option<String>,
+}
+
+impl Parser {
+    /// Get the parser type for this parser.
+    /// Custom parsers require a custom parser definition.
+    pub fn parser_type(self, custom_parser: Option<&CustomParserDef>) -> Result<ParserType, ChandlerError> {
+        Ok(match self {
             Parser::Basic => ParserType::Basic,
             Parser::FourChan => ParserType::FourChan,
             Parser::Tinyboard => ParserType::Tinyboard,
@@ -27,12 +135,18 @@ impl From<Parser> for ParserType {
             Parser::FoolFuuka => ParserType::FoolFuuka,
             Parser::Ponychan => ParserType::Ponychan,
             Parser::Lainchan => ParserType::Lainchan,
-        }
+            Parser::Custom => {
+                let def = custom_parser
+                    .ok_or_else(|| ChandlerError::Config("No custom parser definition was specified!".into()))?;
+
+                ParserType::Custom(Arc::new(CustomParser::new(def.clone())?))
+            }
+        })
     }
 }
 
-impl From<ParserType> for Parser {
-    fn from(parser: ParserType) -> Self {
+impl From<&ParserType> for Parser {
+    fn from(parser: &ParserType) -> Self {
         match parser {
             ParserType::Basic => Parser::Basic,
             ParserType::FourChan => Parser::FourChan,
@@ -42,6 +156,7 @@ impl From<ParserType> for Parser {
             ParserType::FoolFuuka => Parser::FoolFuuka,
             ParserType::Ponychan => Parser::Ponychan,
             ParserType::Lainchan => Parser::Lainchan,
+            ParserType::Custom(_) => Parser::Custom,
         }
     }
 }
@@ -8,6 +8,7 @@ use serde_derive::Deserialize;
 
 use crate::error::*;
 use crate::misc::site_resolver::{SiteInfo, SiteResolver};
+use crate::threadapi::{fourchan, ThreadApi};
 use crate::util;
 
 use super::*;
@@ -22,6 +23,84 @@ pub const DEFAULT_SITES_TOML: &str = include_str!("default_sites.toml");
 pub struct SiteDef {
     pub url_regexes: Regexes,
     pub parser: Parser,
+    pub custom_parser: Option<CustomParserDef>,
+    pub api: Option<ApiDef>,
+    pub catalog: Option<CatalogDef>,
+    pub rate_limit: Option<SiteRateLimitDef>,
+}
+
+/// JSON API to use for updating threads instead of scraping their HTML.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum ApiDef {
+    #[serde(rename = "4chan")]
+    FourChan {
+        /// Base URL of the API.
+        #[serde(default = "default_fourchan_api_url")]
+        url: String,
+    },
+}
+
+/// Definition of how to get the catalog of a board on a site.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CatalogDef {
+    /// Regexes matching board URLs.
+    pub url_regexes: Regexes,
+
+    #[serde(flatten)]
+    pub parser: CatalogParserDef,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum CatalogParserDef {
+    /// 4chan's JSON API.
+    /// The first capture group of the URL regex must be the board name.
+    #[serde(rename = "4chan")]
+    FourChan {
+        /// Base URL of the API.
+        #[serde(default = "default_fourchan_api_url")]
+        url: String,
+    },
+
+    /// HTML catalog page, using CSS selectors to locate threads.
+    #[serde(rename = "html")]
+    Html(HtmlCatalogDef),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HtmlCatalogDef {
+    /// Path of the catalog page, relative to the board URL.
+    pub catalog_path: String,
+
+    /// CSS selector matching the element of each thread in the catalog.
+    pub thread_selector: String,
+
+    /// CSS selector matching the link to the thread, inside the thread element.
+    #[serde(default = "default_link_selector")]
+    pub link_selector: String,
+
+    /// CSS selector matching the subject of the thread, inside the thread element.
+    pub subject_selector: Option<String>,
+
+    /// CSS selector matching the OP text, inside the thread element.
+    pub text_selector: Option<String>,
+}
+
+/// Rate limits overriding the global ones for the hosts used by a site.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SiteRateLimitDef {
+    /// Hosts the limits apply to, including any separate hosts used for media.
+    pub hosts: Vec<String>,
+
+    /// Minimum number of milliseconds between requests to the same host.
+    pub min_delay: Option<u64>,
+
+    /// Maximum number of simultaneous requests to the same host.
+    pub max_connections: Option<usize>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -37,6 +116,27 @@ fn default_include_builtin_sites() -> bool {
     true
 }
 
+/// Used to specify serde default value for the "url" field of the 4chan API.
+fn default_fourchan_api_url() -> String {
+    fourchan::DEFAULT_API_URL.to_owned()
+}
+
+/// Used to specify serde default value for the "link_selector" field.
+fn default_link_selector() -> String {
+    "a".to_owned()
+}
+
+impl ApiDef {
+    /// Get the API to use for a thread URL, if it is supported by the API.
+    pub fn thread_api(&self, thread_url: &str) -> Option<ThreadApi> {
+        match self {
+            Self::FourChan { url } => {
+                fourchan::get_thread_api_url(url, thread_url).map(|url| ThreadApi::FourChan { url })
+            }
+        }
+    }
+}
+
 impl SitesConfig {
     pub fn from_file(path: &Path) -> Result<Self, ChandlerError> {
         use std::io::Read;
@@ -80,6 +180,15 @@ impl SitesConfig {
         }
     }
 
+    /// Load the sites config from the specified config path, falling back to the default location.
+    pub fn from_config_path(config_path: Option<&Path>) -> Result<Self, ChandlerError> {
+        if let Some(path) = config_path {
+            Self::from_location(path)
+        } else {
+            Self::from_default_location()
+        }
+    }
+
     pub fn load_builtin() -> Result<Self, ChandlerError> {
         BUILTIN_SITES_TOML.parse()
     }
@@ -133,8 +242,9 @@ impl SiteResolver for SitesConfig {
 
                     return Ok(Some(SiteInfo {
                         name: name.clone(),
-                        parser: def.parser.into(),
+                        parser: def.parser.parser_type(def.custom_parser.as_ref())?,
                         path,
+                        api: def.api.as_ref().and_then(|api| api.thread_api(url)),
                     }));
                 }
             }
@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use tracing::debug;
+
+use crate::config::chandler::DownloadConfig;
+use crate::config::sites::SitesConfig;
+use crate::error::*;
+use crate::ui::*;
+
+use super::*;
+
+/// How often to check for cancellation while waiting for workers.
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+static USER_AGENT: Lazy<String> = Lazy::new(|| {
+    let os = os_info::get();
+
+    format!(
+        "Mozilla/5.0 ({} {}; {}) Chandler/{}",
+        os.os_type(),
+        os.version(),
+        os.bitness(),
+        env!("CARGO_PKG_VERSION")
+    )
+});
+
+/// Downloads files using a shared HTTP client and a pool of worker threads.
+pub struct DownloadEngine {
+    client: reqwest::blocking::Client,
+    workers: usize,
+    retry_policy: RetryPolicy,
+    rate_limiter: RateLimiter,
+}
+
+/// A file to be downloaded by the engine.
+///
+/// The tag is passed back along with the result, and can be used to identify the job.
+pub struct DownloadJob<T> {
+    pub tag: T,
+    pub url: String,
+    pub path: PathBuf,
+    pub if_modified_since: Option<DateTime<Utc>>,
+}
+
+pub struct DownloadAllResult<T> {
+    /// Tags and results of all jobs that were processed.
+    pub completed: Vec<(T, Result<DownloadResult, ChandlerError>)>,
+
+    /// Tags of all jobs that were not started due to cancellation.
+    pub cancelled: Vec<T>,
+}
+
+/// UI handler used by worker threads.
+/// Per-file events are discarded, as they cannot be meaningfully displayed for multiple simultaneous downloads.
+struct WorkerUiHandler<'a> {
+    cancel: &'a AtomicBool,
+}
+
+impl DownloadEngine {
+    pub fn new(config: &DownloadConfig) -> Result<Self, ChandlerError> {
+        let client = reqwest::blocking::Client::builder()
+            .user_agent(&*USER_AGENT)
+            .gzip(true)
+            .build()
+            .map_err(|err| ChandlerError::Other(format!("Error building HTTP client: {err}").into()))?;
+
+        Ok(Self {
+            client,
+            workers: config.workers.max(1),
+            retry_policy: RetryPolicy::new(&config.retry),
+            rate_limiter: RateLimiter::new(&config.rate_limit),
+        })
+    }
+
+    /// Apply per-site rate limits from the sites config.
+    pub fn with_sites_config(mut self, sites: &SitesConfig) -> Self {
+        self.rate_limiter.apply_sites_config(sites);
+
+        self
+    }
+
+    /// Policy used to determine when failed downloads should be retried.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Download a single file on the current thread.
+    pub fn download_file(
+        &self,
+        url: &str,
+        path: &Path,
+        if_modified_since: Option<DateTime<Utc>>,
+        ui_handler: &mut dyn ChandlerUiHandler,
+    ) -> Result<DownloadResult, ChandlerError> {
+        download_file(
+            &self.client,
+            &self.rate_limiter,
+            url,
+            path,
+            if_modified_since,
+            ui_handler,
+        )
+    }
+
+    /// Download all jobs using the engine's worker threads.
+    pub fn download_all<T: Send>(
+        &self,
+        jobs: Vec<DownloadJob<T>>,
+        ui_handler: &mut dyn ChandlerUiHandler,
+    ) -> DownloadAllResult<T> {
+        // Report download start.
+        ui_handler.event(&UiEvent::DownloadStart {
+            file_count: jobs.len() as u32,
+        });
+
+        let worker_count = self.workers.min(jobs.len());
+        debug!("Downloading {} files using {} workers.", jobs.len(), worker_count);
+
+        let queue = Mutex::new(VecDeque::from(jobs));
+        let cancel = AtomicBool::new(ui_handler.is_cancelled());
+
+        let mut completed: Vec<(T, Result<DownloadResult, ChandlerError>)> = Vec::new();
+        let mut files_downloaded: u32 = 0;
+        let mut files_failed: u32 = 0;
+
+        std::thread::scope(|s| {
+            let (tx, rx) = mpsc::channel();
+
+            for _ in 0..worker_count {
+                let tx = tx.clone();
+                let queue = &queue;
+                let cancel = &cancel;
+
+                s.spawn(move || {
+                    let mut worker_ui_handler = WorkerUiHandler { cancel };
+
+                    loop {
+                        // If cancellation has been requested, stop picking up new jobs.
+                        if cancel.load(Ordering::SeqCst) {
+                            break;
+                        }
+
+                        let job = queue.lock().unwrap().pop_front();
+                        let Some(job) = job else {
+                            break;
+                        };
+
+                        let result = download_file(
+                            &self.client,
+                            &self.rate_limiter,
+                            &job.url,
+                            &job.path,
+                            job.if_modified_since,
+                            &mut worker_ui_handler,
+                        );
+
+                        if tx.send((job.tag, result)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+
+            // Drop our own sender, so that the channel disconnects once all workers are done.
+            drop(tx);
+
+            loop {
+                match rx.recv_timeout(CANCEL_CHECK_INTERVAL) {
+                    Ok((tag, result)) => {
+                        if result.as_ref().is_ok_and(|r| r.is_success()) {
+                            files_downloaded += 1;
+                        } else {
+                            files_failed += 1;
+                        }
+
+                        completed.push((tag, result));
+
+                        // Report download progress.
+                        ui_handler.event(&UiEvent::DownloadProgress {
+                            files_processed: completed.len() as u32,
+                        });
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+
+                if ui_handler.is_cancelled() {
+                    cancel.store(true, Ordering::SeqCst);
+                }
+            }
+        });
+
+        // Report download complete.
+        ui_handler.event(&UiEvent::DownloadComplete {
+            files_downloaded,
+            files_failed,
+        });
+
+        let cancelled = queue.into_inner().unwrap().into_iter().map(|job| job.tag).collect();
+
+        DownloadAllResult { completed, cancelled }
+    }
+}
+
+impl ChandlerUiHandler for WorkerUiHandler<'_> {
+    fn event(&mut self, _: &UiEvent) {}
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn engine(workers: usize) -> DownloadEngine {
+        DownloadEngine::new(&DownloadConfig {
+            workers,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn jobs(server: &TestServer, dir: &Path, count: u32) -> Vec<DownloadJob<u32>> {
+        (0..count)
+            .map(|i| DownloadJob {
+                tag: i,
+                url: server.url(&format!("/{i}.txt")),
+                path: dir.join(format!("{i}.txt")),
+                if_modified_since: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn can_download_all() {
+        let server = TestServer::start(|request| TestResponse::ok(request.path.as_bytes()));
+        let dir = test_dir("download_all");
+
+        let mut ui_handler = TestUiHandler::default();
+        let result = engine(4).download_all(jobs(&server, &dir, 8), &mut ui_handler);
+
+        assert_eq!(result.completed.len(), 8);
+        assert!(result.cancelled.is_empty());
+        assert!(result.completed.iter().all(|(_, r)| r.as_ref().unwrap().is_success()));
+
+        for i in 0..8 {
+            let content = fs::read_to_string(dir.join(format!("{i}.txt"))).unwrap();
+            assert_eq!(content, format!("/{i}.txt"));
+        }
+
+        let progress_events = ui_handler
+            .events
+            .iter()
+            .filter(|e| e.starts_with("DownloadProgress"))
+            .count();
+
+        assert_eq!(progress_events, 8);
+        assert_eq!(
+            ui_handler.events.last().unwrap(),
+            "DownloadComplete { files_downloaded: 8, files_failed: 0 }"
+        );
+    }
+
+    #[test]
+    fn failed_downloads_are_counted() {
+        let server = TestServer::start(|request| {
+            if request.path == "/1.txt" {
+                TestResponse::status(404)
+            } else {
+                TestResponse::ok(b"OK")
+            }
+        });
+        let dir = test_dir("download_all_failed");
+
+        let mut ui_handler = TestUiHandler::default();
+        let result = engine(2).download_all(jobs(&server, &dir, 3), &mut ui_handler);
+
+        let (_, not_found) = result.completed.iter().find(|(tag, _)| *tag == 1).unwrap();
+        assert!(matches!(not_found, Ok(DownloadResult::NotFound)));
+
+        assert_eq!(
+            ui_handler.events.last().unwrap(),
+            "DownloadComplete { files_downloaded: 2, files_failed: 1 }"
+        );
+    }
+
+    #[test]
+    fn cancelled_jobs_are_returned() {
+        let server = TestServer::start(|_| TestResponse::ok(b"OK"));
+        let dir = test_dir("download_all_cancelled");
+
+        let mut ui_handler = TestUiHandler {
+            cancelled: true,
+            ..Default::default()
+        };
+
+        let result = engine(4).download_all(jobs(&server, &dir, 5), &mut ui_handler);
+
+        assert!(result.completed.is_empty());
+        assert_eq!(result.cancelled, vec![0, 1, 2, 3, 4]);
+    }
+}
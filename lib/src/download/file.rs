@@ -0,0 +1,403 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use reqwest::header;
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::error::*;
+use crate::ui::*;
+use crate::util;
+
+use super::*;
+
+const BUF_SIZE: usize = 65535;
+
+/// Extension appended to the filename of files that are still being downloaded.
+const PART_EXTENSION: &str = "part";
+
+#[derive(Debug)]
+pub enum DownloadResult {
+    Success {
+        last_modified: Option<DateTime<Utc>>,
+        etag: Option<String>,
+        size: u64,
+        sha256: String,
+    },
+    NotModified,
+    NotFound,
+    OtherHttpError {
+        status_code: u16,
+        description: String,
+        retry_after: Option<DateTime<Utc>>,
+    },
+}
+
+impl DownloadResult {
+    /// Whether the file is available locally after this result.
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success { .. } | Self::NotModified)
+    }
+}
+
+/// Download a file to the specified path.
+///
+/// The file is first downloaded to a ".part" file next to the destination,
+/// which is only renamed into place once the download has completed.
+/// If a ".part" file from an earlier interrupted download exists, an attempt
+/// will be made to resume it.
+///
+/// The request is scheduled according to the limits of the rate limiter.
+pub fn download_file(
+    client: &reqwest::blocking::Client,
+    rate_limiter: &RateLimiter,
+    url: &str,
+    path: &Path,
+    if_modified_since: Option<DateTime<Utc>>,
+    ui_handler: &mut dyn ChandlerUiHandler,
+) -> Result<DownloadResult, ChandlerError> {
+    info!("Download starting: '{}' (to '{}')", url, path.display());
+
+    ui_handler.event(&UiEvent::DownloadFileStart {
+        url: url.to_owned(),
+        destination: path.to_path_buf(),
+    });
+
+    let part_path = get_part_path(path);
+
+    let result = (|| {
+        // Wait for our turn to make a request to this host.
+        let _permit = rate_limiter.acquire(url);
+
+        // If a partially downloaded file exists, try to resume from where it left off.
+        let mut resume_from = fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+
+        let mut response = loop {
+            // Download the file.
+            let mut request = client.get(url);
+
+            if resume_from > 0 {
+                // Request only the remaining part of the file.
+                request = request.header(header::RANGE, format!("bytes={resume_from}-"));
+            } else if let Some(if_modified_since) = if_modified_since {
+                // If specified, add If-Modified-Since header.
+                request = request.header(header::IF_MODIFIED_SINCE, &if_modified_since.to_rfc2822());
+            }
+
+            // Send request and get response.
+            let response = request
+                .send()
+                .map_err(|err| ChandlerError::Download(DownloadError::Network(err.to_string().into())))?;
+
+            if resume_from > 0 {
+                match response.status() {
+                    StatusCode::PARTIAL_CONTENT if get_content_range_start(&response) == Some(resume_from) => {
+                        info!("Resuming download from byte {}: '{}'", resume_from, url);
+                    }
+                    StatusCode::PARTIAL_CONTENT | StatusCode::RANGE_NOT_SATISFIABLE => {
+                        // The partial file cannot be resumed. Discard it and start over.
+                        info!("Cannot resume download, restarting: '{}'", url);
+
+                        fs::remove_file(&part_path).map_err(ChandlerError::WriteFile)?;
+                        resume_from = 0;
+
+                        continue;
+                    }
+                    StatusCode::OK => {
+                        // The server does not support ranges, and sent the entire file.
+                        info!("Server does not support resuming, restarting: '{}'", url);
+
+                        resume_from = 0;
+                    }
+                    _ => {}
+                }
+            }
+
+            break response;
+        };
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let status_code: u16 = status.into();
+
+            info!("Download failed: '{}' (status code: {})", url, status_code);
+
+            return match status_code {
+                304 => Ok(DownloadResult::NotModified),
+                404 | 410 => Ok(DownloadResult::NotFound),
+                _ => {
+                    // If the server specified when to try again, pass it along.
+                    let retry_after = response
+                        .headers()
+                        .get(header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| parse_retry_after(v, Utc::now()));
+
+                    Ok(DownloadResult::OtherHttpError {
+                        status_code,
+                        description: status.to_string(),
+                        retry_after,
+                    })
+                }
+            };
+        }
+
+        ui_handler.event(&UiEvent::DownloadFileInfo {
+            size: response.content_length().map(|l| resume_from + l),
+        });
+
+        ui_handler.event(&UiEvent::DownloadFileProgress {
+            bytes_downloaded: resume_from,
+        });
+
+        // Create parent directory if necessary.
+        util::create_parent_dir(path).map_err(ChandlerError::CreateFile)?;
+
+        // Open partial file for writing, appending to it if resuming.
+        let mut file = if resume_from > 0 {
+            util::append_file(&part_path).map_err(ChandlerError::OpenFile)?
+        } else {
+            util::create_file(&part_path).map_err(ChandlerError::CreateFile)?
+        };
+
+        let mut hasher = Sha256::new();
+
+        // If resuming, the hash must include the previously downloaded part.
+        if resume_from > 0 {
+            let mut part_file = util::open_file(&part_path).map_err(ChandlerError::OpenFile)?;
+            std::io::copy(&mut part_file, &mut hasher).map_err(ChandlerError::ReadFile)?;
+        }
+
+        let mut bytes_downloaded: u64 = resume_from;
+
+        // Copy response content to file.
+        'copy: loop {
+            use std::io::{Read, Write};
+
+            let mut buf: [u8; BUF_SIZE] = [0; BUF_SIZE];
+
+            match response.read(&mut buf) {
+                Ok(bytes_read) => {
+                    if bytes_read == 0 {
+                        break 'copy;
+                    }
+                    bytes_downloaded += bytes_read as u64;
+
+                    rate_limiter.consume(bytes_read);
+
+                    ui_handler.event(&UiEvent::DownloadFileProgress { bytes_downloaded });
+
+                    file.write_all(&buf[..bytes_read]).map_err(ChandlerError::WriteFile)?;
+                    hasher.update(&buf[..bytes_read]);
+                }
+                Err(err) => return Err(ChandlerError::Download(DownloadError::Other(err.to_string().into()))),
+            }
+        }
+
+        // Close the partial file, and move it into place.
+        drop(file);
+        fs::rename(&part_path, path).map_err(ChandlerError::WriteFile)?;
+
+        let last_modified: Option<DateTime<Utc>> = if let Some(value) = response.headers().get(header::LAST_MODIFIED) {
+            if let Ok(value_str) = value.to_str() {
+                let last_modified = DateTime::parse_from_rfc2822(value_str)
+                    .map_err(|err| ChandlerError::Download(DownloadError::Other(err.to_string().into())))?;
+
+                Some(last_modified.into())
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_owned());
+
+        info!("Download completed: '{}'", url);
+        Ok(DownloadResult::Success {
+            last_modified,
+            etag,
+            size: bytes_downloaded,
+            sha256: util::finish_hash(hasher),
+        })
+    })();
+
+    // Report download complete progress event.
+    match result {
+        Ok(_) => ui_handler.event(&UiEvent::DownloadFileComplete(DownloadFileCompleteResult::Success)),
+        Err(_) => ui_handler.event(&UiEvent::DownloadFileComplete(DownloadFileCompleteResult::Error)),
+    };
+
+    result
+}
+
+/// Get the path of the partial file used while downloading to the specified path.
+pub fn get_part_path(path: &Path) -> PathBuf {
+    let mut filename = path.file_name().unwrap_or_default().to_os_string();
+    filename.push(".");
+    filename.push(PART_EXTENSION);
+
+    path.with_file_name(filename)
+}
+
+/// Get the start offset from the Content-Range header of a response.
+fn get_content_range_start(response: &reqwest::blocking::Response) -> Option<u64> {
+    // Content-Range is on the form "bytes <start>-<end>/<total>".
+    let value = response.headers().get(header::CONTENT_RANGE)?.to_str().ok()?;
+    let range = value.strip_prefix("bytes ")?;
+    let (start, _) = range.split_once('-')?;
+
+    start.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    const CONTENT: &[u8] = b"0123456789";
+
+    /// Serve CONTENT, honoring Range requests.
+    fn range_response(request: &TestRequest) -> TestResponse {
+        if let Some(start) = request
+            .headers
+            .get("range")
+            .and_then(|r| r.strip_prefix("bytes="))
+            .and_then(|r| r.trim_end_matches('-').parse::<usize>().ok())
+        {
+            if start >= CONTENT.len() {
+                return TestResponse::status(416);
+            }
+
+            TestResponse {
+                status: 206,
+                ..TestResponse::ok(&CONTENT[start..])
+            }
+            .header(
+                "Content-Range",
+                &format!("bytes {}-{}/{}", start, CONTENT.len() - 1, CONTENT.len()),
+            )
+        } else {
+            TestResponse::ok(CONTENT)
+        }
+    }
+
+    fn download(url: &str, path: &Path) -> Result<DownloadResult, ChandlerError> {
+        let client = reqwest::blocking::Client::new();
+
+        download_file(
+            &client,
+            &RateLimiter::default(),
+            url,
+            path,
+            None,
+            &mut TestUiHandler::default(),
+        )
+    }
+
+    #[test]
+    fn completed_download_is_moved_into_place() {
+        let server = TestServer::start(range_response);
+        let path = test_dir("download_complete").join("file.txt");
+
+        let result = download(&server.url("/file.txt"), &path).unwrap();
+
+        assert!(result.is_success());
+        assert_eq!(fs::read(&path).unwrap(), CONTENT);
+        assert!(!get_part_path(&path).exists());
+    }
+
+    #[test]
+    fn can_resume_partial_download() {
+        let ranges: Arc<Mutex<Vec<String>>> = Default::default();
+        let server = TestServer::start({
+            let ranges = Arc::clone(&ranges);
+
+            move |request| {
+                if let Some(range) = request.headers.get("range") {
+                    ranges.lock().unwrap().push(range.clone());
+                }
+
+                range_response(request)
+            }
+        });
+
+        let path = test_dir("download_resume").join("file.txt");
+        fs::write(get_part_path(&path), &CONTENT[..4]).unwrap();
+
+        let result = download(&server.url("/file.txt"), &path).unwrap();
+
+        assert_eq!(*ranges.lock().unwrap(), vec!["bytes=4-".to_owned()]);
+        assert_eq!(fs::read(&path).unwrap(), CONTENT);
+
+        // The hash must cover the entire file, not just the resumed part.
+        let DownloadResult::Success { size, sha256, .. } = result else {
+            panic!("Unexpected result: {result:?}");
+        };
+        assert_eq!(size, CONTENT.len() as u64);
+        assert_eq!(sha256, util::hash_file(&path).unwrap());
+        assert!(!get_part_path(&path).exists());
+    }
+
+    #[test]
+    fn restarts_if_range_is_not_supported() {
+        let server = TestServer::start(|_| TestResponse::ok(CONTENT));
+
+        let path = test_dir("download_no_range").join("file.txt");
+        fs::write(get_part_path(&path), &CONTENT[..4]).unwrap();
+
+        download(&server.url("/file.txt"), &path).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), CONTENT);
+    }
+
+    #[test]
+    fn restarts_if_range_is_not_satisfiable() {
+        let server = TestServer::start(range_response);
+
+        let path = test_dir("download_bad_range").join("file.txt");
+        fs::write(get_part_path(&path), b"0123456789ABCDEF").unwrap();
+
+        download(&server.url("/file.txt"), &path).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), CONTENT);
+    }
+
+    #[test]
+    fn retry_after_is_returned() {
+        let server = TestServer::start(|_| TestResponse::status(429).header("Retry-After", "120"));
+
+        let path = test_dir("download_retry_after").join("file.txt");
+
+        let result = download(&server.url("/file.txt"), &path).unwrap();
+
+        assert!(matches!(
+            result,
+            DownloadResult::OtherHttpError {
+                status_code: 429,
+                retry_after: Some(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn interrupted_download_is_not_moved_into_place() {
+        // Claim more content than is actually sent.
+        let server = TestServer::start(|_| TestResponse::ok(&CONTENT[..4]).header("Content-Length", "10"));
+
+        let path = test_dir("download_interrupted").join("file.txt");
+
+        assert!(download(&server.url("/file.txt"), &path).is_err());
+        assert!(!path.exists());
+        assert_eq!(fs::read(get_part_path(&path)).unwrap(), &CONTENT[..4]);
+    }
+}
@@ -0,0 +1,15 @@
+mod engine;
+mod file;
+mod rate_limit;
+mod retry;
+
+#[cfg(test)]
+mod test_only;
+
+pub use self::engine::*;
+pub use self::file::*;
+pub use self::rate_limit::*;
+pub use self::retry::*;
+
+#[cfg(test)]
+pub use self::test_only::*;
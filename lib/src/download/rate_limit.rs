@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+use url::Url;
+
+use crate::config::chandler::RateLimitConfig;
+use crate::config::sites::SitesConfig;
+
+/// Limits applied to requests to a single host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HostLimits {
+    /// Minimum time between the start of two requests.
+    pub min_delay: Duration,
+
+    /// Maximum number of simultaneous requests.
+    pub max_connections: usize,
+}
+
+/// Schedules requests so that per-host limits and the global bandwidth cap are respected.
+pub struct RateLimiter {
+    default_limits: HostLimits,
+    host_limits: HashMap<String, HostLimits>,
+    hosts: Mutex<HashMap<String, HostState>>,
+    host_released: Condvar,
+    bandwidth: Option<BandwidthLimiter>,
+}
+
+/// Keeps a request slot for a host occupied until dropped.
+pub struct HostPermit<'a> {
+    limiter: &'a RateLimiter,
+    host: Option<String>,
+}
+
+#[derive(Default)]
+struct HostState {
+    active: usize,
+    last_request: Option<Instant>,
+}
+
+/// Token bucket limiting the total number of bytes per second.
+struct BandwidthLimiter {
+    bytes_per_second: u64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// Bytes that can currently be consumed without waiting. Negative if in debt.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl HostLimits {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            min_delay: Duration::from_millis(config.min_delay),
+            max_connections: config.max_connections.max(1),
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            default_limits: HostLimits::new(config),
+            host_limits: HashMap::new(),
+            hosts: Mutex::new(HashMap::new()),
+            host_released: Condvar::new(),
+            bandwidth: config
+                .max_bandwidth
+                .filter(|kib| *kib > 0)
+                .map(|kib| BandwidthLimiter::new(kib * 1024)),
+        }
+    }
+
+    /// Override the limits for a specific host.
+    pub fn set_host_limits(&mut self, host: &str, limits: HostLimits) {
+        self.host_limits.insert(host.to_lowercase(), limits);
+    }
+
+    /// Apply the per-site limits specified in the sites config to all hosts they list.
+    pub fn apply_sites_config(&mut self, sites: &SitesConfig) {
+        for def in sites.sites.values() {
+            let Some(rate_limit) = &def.rate_limit else {
+                continue;
+            };
+
+            let limits = HostLimits {
+                min_delay: rate_limit
+                    .min_delay
+                    .map_or(self.default_limits.min_delay, Duration::from_millis),
+                max_connections: rate_limit
+                    .max_connections
+                    .map_or(self.default_limits.max_connections, |c| c.max(1)),
+            };
+
+            for host in rate_limit.hosts.iter() {
+                self.set_host_limits(host, limits);
+            }
+        }
+    }
+
+    /// Get the limits that apply to a host.
+    pub fn limits_for(&self, host: &str) -> HostLimits {
+        self.host_limits
+            .get(&host.to_lowercase())
+            .copied()
+            .unwrap_or(self.default_limits)
+    }
+
+    /// Wait until a request to the specified URL is allowed, and reserve a slot for it.
+    ///
+    /// The slot is released when the returned permit is dropped.
+    pub fn acquire(&self, url: &str) -> HostPermit<'_> {
+        let Some(host) = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
+        else {
+            return HostPermit {
+                limiter: self,
+                host: None,
+            };
+        };
+
+        let limits = self.limits_for(&host);
+        let mut hosts = self.hosts.lock().unwrap();
+
+        loop {
+            let state = hosts.entry(host.clone()).or_default();
+
+            if state.active >= limits.max_connections {
+                // Wait for another request to this host to finish.
+                hosts = self.host_released.wait(hosts).unwrap();
+                continue;
+            }
+
+            let now = Instant::now();
+            let ready_at = state.last_request.map(|t| t + limits.min_delay);
+
+            match ready_at {
+                Some(ready_at) if ready_at > now => {
+                    // Wait until enough time has passed since the last request to this host.
+                    let wait = ready_at - now;
+                    debug!("Waiting {:?} before next request to {}.", wait, host);
+
+                    hosts = self.host_released.wait_timeout(hosts, wait).unwrap().0;
+                }
+                _ => {
+                    state.active += 1;
+                    state.last_request = Some(now);
+
+                    return HostPermit {
+                        limiter: self,
+                        host: Some(host),
+                    };
+                }
+            }
+        }
+    }
+
+    /// Account for bytes received, waiting if the bandwidth cap has been exceeded.
+    pub fn consume(&self, bytes: usize) {
+        if let Some(bandwidth) = &self.bandwidth {
+            bandwidth.consume(bytes as u64);
+        }
+    }
+
+    fn release(&self, host: &str) {
+        let mut hosts = self.hosts.lock().unwrap();
+
+        if let Some(state) = hosts.get_mut(host) {
+            state.active = state.active.saturating_sub(1);
+        }
+
+        self.host_released.notify_all();
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(&RateLimitConfig::default())
+    }
+}
+
+impl Drop for HostPermit<'_> {
+    fn drop(&mut self) {
+        if let Some(host) = &self.host {
+            self.limiter.release(host);
+        }
+    }
+}
+
+impl BandwidthLimiter {
+    fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second,
+            bucket: Mutex::new(Bucket {
+                tokens: bytes_per_second as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    fn consume(&self, bytes: u64) {
+        let rate = self.bytes_per_second as f64;
+
+        // The lock is held while sleeping, so that all downloads share the same budget.
+        let mut bucket = self.bucket.lock().unwrap();
+
+        // Refill the bucket according to the time passed, allowing at most one second worth of burst.
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate);
+        bucket.last_refill = now;
+
+        bucket.tokens -= bytes as f64;
+
+        if bucket.tokens < 0.0 {
+            std::thread::sleep(Duration::from_secs_f64(-bucket.tokens / rate));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn limiter(min_delay: u64, max_connections: usize) -> RateLimiter {
+        RateLimiter::new(&RateLimitConfig {
+            min_delay,
+            max_connections,
+            max_bandwidth: None,
+        })
+    }
+
+    #[test]
+    fn min_delay_is_enforced() {
+        let limiter = limiter(50, 4);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            drop(limiter.acquire("http://example.com/file.jpg"));
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn hosts_are_limited_separately() {
+        let limiter = limiter(1000, 4);
+        let start = Instant::now();
+
+        drop(limiter.acquire("http://a.example.com/file.jpg"));
+        drop(limiter.acquire("http://b.example.com/file.jpg"));
+
+        assert!(start.elapsed() < Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn max_connections_is_enforced() {
+        let limiter = limiter(0, 2);
+        let active = AtomicUsize::new(0);
+        let max_active = AtomicUsize::new(0);
+
+        std::thread::scope(|s| {
+            for _ in 0..6 {
+                s.spawn(|| {
+                    let _permit = limiter.acquire("http://example.com/file.jpg");
+
+                    let now_active = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_active.fetch_max(now_active, Ordering::SeqCst);
+
+                    std::thread::sleep(Duration::from_millis(20));
+
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        assert_eq!(max_active.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn host_limits_can_be_overridden() {
+        let sites: SitesConfig = r#"
+            [sites."examplechan"]
+            url-regexes = ['^http(?:s)?://examplechan.org/(.+)/res/(\d+)']
+            parser = "tinyboard"
+
+            [sites."examplechan".rate-limit]
+            hosts = ["examplechan.org", "media.examplechan.org"]
+            min-delay = 2000
+        "#
+        .parse()
+        .unwrap();
+
+        let mut limiter = limiter(0, 4);
+        limiter.apply_sites_config(&sites);
+
+        let expected = HostLimits {
+            min_delay: Duration::from_millis(2000),
+            max_connections: 4,
+        };
+
+        assert_eq!(limiter.limits_for("examplechan.org"), expected);
+        assert_eq!(limiter.limits_for("Media.examplechan.org"), expected);
+        assert_eq!(limiter.limits_for("example.com").min_delay, Duration::ZERO);
+    }
+
+    #[test]
+    fn bandwidth_is_limited() {
+        let limiter = BandwidthLimiter::new(100_000);
+        let start = Instant::now();
+
+        // The first second worth of data is allowed as a burst.
+        limiter.consume(100_000);
+        limiter.consume(10_000);
+
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
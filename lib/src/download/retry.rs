@@ -0,0 +1,245 @@
+use chrono::{DateTime, Duration, Utc};
+use tracing::warn;
+
+use crate::config::chandler::RetryConfig;
+use crate::error::*;
+
+use super::*;
+
+/// Retry state of a link that has failed to download.
+#[derive(Clone, Debug, Default)]
+pub struct RetryState {
+    /// Number of failed attempts so far.
+    pub attempts: u32,
+
+    /// Description of the last error.
+    pub last_error: Option<String>,
+
+    /// Time at which the link becomes eligible for another attempt.
+    pub next_attempt: Option<DateTime<Utc>>,
+
+    /// Whether the link has failed permanently, and should not be attempted again.
+    pub permanent: bool,
+}
+
+/// Determines when failed downloads should be retried.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    initial_delay: Duration,
+    max_delay: Duration,
+    not_found_attempts: u32,
+}
+
+/// Classification of a download failure, for the purpose of deciding when to retry it.
+enum Failure {
+    /// The file does not exist (404/410).
+    NotFound,
+
+    /// The server asked us to slow down (429/503).
+    Throttled { retry_after: Option<DateTime<Utc>> },
+
+    /// Anything else, such as network errors or other server errors.
+    Transient,
+}
+
+impl RetryState {
+    /// Whether the link should be attempted at the specified time.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        !self.permanent && self.next_attempt.is_none_or(|t| t <= now)
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(config: &RetryConfig) -> Self {
+        Self {
+            initial_delay: Duration::seconds(config.initial_delay as i64),
+            max_delay: Duration::seconds(config.max_delay as i64),
+            not_found_attempts: config.not_found_attempts,
+        }
+    }
+
+    /// Update retry state after a failed download attempt.
+    pub fn record_failure(
+        &self,
+        state: &mut RetryState,
+        result: &Result<DownloadResult, ChandlerError>,
+        now: DateTime<Utc>,
+    ) {
+        state.attempts += 1;
+        state.last_error = Some(describe_failure(result));
+
+        let backoff_until = now + self.backoff(state.attempts);
+
+        state.next_attempt = Some(match classify_failure(result) {
+            Failure::NotFound => {
+                if state.attempts >= self.not_found_attempts {
+                    state.permanent = true;
+                }
+
+                backoff_until
+            }
+            Failure::Throttled {
+                retry_after: Some(retry_after),
+            } if retry_after > now => retry_after,
+            Failure::Throttled { .. } | Failure::Transient => backoff_until,
+        });
+    }
+
+    /// Get the delay before the next attempt, after the specified number of failed attempts.
+    fn backoff(&self, attempts: u32) -> Duration {
+        // Double the delay for every failed attempt after the first.
+        let factor = 2i32.saturating_pow(attempts.saturating_sub(1));
+
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |d| d.min(self.max_delay))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(&RetryConfig::default())
+    }
+}
+
+/// Parse the value of a Retry-After header, which can be either a number of seconds or an HTTP date.
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<u32>() {
+        Some(now + Duration::seconds(seconds as i64))
+    } else if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+        Some(date.into())
+    } else {
+        warn!("Invalid Retry-After value: {}", value);
+        None
+    }
+}
+
+fn classify_failure(result: &Result<DownloadResult, ChandlerError>) -> Failure {
+    match result {
+        Ok(DownloadResult::NotFound) => Failure::NotFound,
+        Ok(DownloadResult::OtherHttpError {
+            status_code: 429 | 503,
+            retry_after,
+            ..
+        }) => Failure::Throttled {
+            retry_after: *retry_after,
+        },
+        _ => Failure::Transient,
+    }
+}
+
+fn describe_failure(result: &Result<DownloadResult, ChandlerError>) -> String {
+    match result {
+        Ok(DownloadResult::NotFound) => "File not found".to_owned(),
+        Ok(DownloadResult::OtherHttpError {
+            status_code,
+            description,
+            ..
+        }) => format!("HTTP error: {status_code} {description}"),
+        Ok(r) => format!("Unexpected result: {r:?}"),
+        Err(ChandlerError::Download(err)) => match err {
+            DownloadError::Http { code, description } => format!("HTTP error: {code} {description}"),
+            DownloadError::Network(description) => description.to_string(),
+            DownloadError::Other(description) => description.to_string(),
+        },
+        Err(err) => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new(&RetryConfig {
+            initial_delay: 60,
+            max_delay: 600,
+            not_found_attempts: 3,
+        })
+    }
+
+    fn http_error(status_code: u16, retry_after: Option<DateTime<Utc>>) -> Result<DownloadResult, ChandlerError> {
+        Ok(DownloadResult::OtherHttpError {
+            status_code,
+            description: "Error".to_owned(),
+            retry_after,
+        })
+    }
+
+    fn network_error() -> Result<DownloadResult, ChandlerError> {
+        Err(ChandlerError::Download(DownloadError::Network(
+            "Connection reset".into(),
+        )))
+    }
+
+    #[test]
+    fn transient_errors_back_off_exponentially() {
+        let policy = policy();
+        let now = Utc::now();
+        let mut state = RetryState::default();
+
+        let mut delays: Vec<i64> = Vec::new();
+        for _ in 0..6 {
+            policy.record_failure(&mut state, &network_error(), now);
+            delays.push((state.next_attempt.unwrap() - now).num_seconds());
+        }
+
+        assert_eq!(delays, vec![60, 120, 240, 480, 600, 600]);
+        assert_eq!(state.last_error.as_deref(), Some("Connection reset"));
+        assert!(!state.permanent);
+    }
+
+    #[test]
+    fn not_found_becomes_permanent() {
+        let policy = policy();
+        let now = Utc::now();
+        let mut state = RetryState::default();
+
+        policy.record_failure(&mut state, &Ok(DownloadResult::NotFound), now);
+        policy.record_failure(&mut state, &Ok(DownloadResult::NotFound), now);
+        assert!(!state.permanent);
+
+        policy.record_failure(&mut state, &Ok(DownloadResult::NotFound), now);
+        assert!(state.permanent);
+        assert!(!state.is_due(now + Duration::days(365)));
+    }
+
+    #[test]
+    fn retry_after_is_honored() {
+        let policy = policy();
+        let now = Utc::now();
+        let mut state = RetryState::default();
+
+        let retry_after = now + Duration::seconds(3600);
+        policy.record_failure(&mut state, &http_error(429, Some(retry_after)), now);
+
+        assert_eq!(state.next_attempt, Some(retry_after));
+        assert!(!state.is_due(now + Duration::seconds(3599)));
+        assert!(state.is_due(retry_after));
+    }
+
+    #[test]
+    fn throttling_without_retry_after_backs_off() {
+        let policy = policy();
+        let now = Utc::now();
+        let mut state = RetryState::default();
+
+        policy.record_failure(&mut state, &http_error(503, None), now);
+
+        assert_eq!(state.next_attempt, Some(now + Duration::seconds(60)));
+    }
+
+    #[test]
+    fn can_parse_retry_after() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:00:00Z").unwrap().into();
+
+        assert_eq!(parse_retry_after("120", now), Some(now + Duration::seconds(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z").unwrap().into())
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
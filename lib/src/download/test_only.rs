@@ -0,0 +1,172 @@
+// This module is only used in tests.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::ui::*;
+
+/// Minimal HTTP server for testing downloads against.
+pub struct TestServer {
+    addr: SocketAddr,
+}
+
+#[derive(Debug)]
+pub struct TestRequest {
+    pub method: String,
+    pub path: String,
+
+    /// Request headers, with lowercase names.
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub struct TestResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// UI handler that records all events as debug strings.
+#[derive(Default)]
+pub struct TestUiHandler {
+    pub events: Vec<String>,
+    pub cancelled: bool,
+}
+
+impl TestServer {
+    /// Start a server on a random local port, answering every request using the handler.
+    pub fn start(handler: impl Fn(&TestRequest) -> TestResponse + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler = Arc::new(handler);
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let handler = Arc::clone(&handler);
+
+                std::thread::spawn(move || handle_connection(stream, handler.as_ref()));
+            }
+        });
+
+        Self { addr }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+}
+
+impl TestResponse {
+    pub fn ok(body: &[u8]) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: body.to_vec(),
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+
+        self
+    }
+}
+
+impl ChandlerUiHandler for TestUiHandler {
+    fn event(&mut self, e: &UiEvent) {
+        self.events.push(format!("{:?}", e));
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+}
+
+/// Create a new empty directory for a test to write files to.
+pub fn test_dir(name: &str) -> PathBuf {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    let path = std::env::temp_dir().join(format!(
+        "chandler-test-{}-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst),
+        name
+    ));
+
+    if path.exists() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    std::fs::create_dir_all(&path).unwrap();
+
+    path
+}
+
+fn handle_connection(mut stream: TcpStream, handler: &dyn Fn(&TestRequest) -> TestResponse) {
+    let request = {
+        let mut reader = BufReader::new(&stream);
+
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).is_err() {
+            return;
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_owned();
+        let path = parts.next().unwrap_or_default().to_owned();
+
+        let mut headers = HashMap::new();
+
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).is_err() {
+                return;
+            }
+
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_lowercase(), value.trim().to_owned());
+            }
+        }
+
+        TestRequest { method, path, headers }
+    };
+
+    let response = handler(&request);
+
+    let mut head = format!("HTTP/1.1 {} Test\r\nConnection: close\r\n", response.status);
+
+    // Unless explicitly overridden by the handler, send the actual content length.
+    if !response
+        .headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+    {
+        head.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+    }
+
+    for (name, value) in response.headers.iter() {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+
+    head.push_str("\r\n");
+
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(&response.body);
+}
@@ -0,0 +1,88 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::error::*;
+
+/// Matches URLs referenced using `url()`, with or without quotes, and `@import` with a string.
+/// `@import url()` is matched as a regular `url()`.
+static CSS_URL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"url\(\s*(?:"([^"]*)"|'([^']*)'|([^)"'\s]*))\s*\)|@import\s+(?:"([^"]*)"|'([^']*)')"#).unwrap()
+});
+
+/// Replace all URLs referenced in CSS.
+/// URLs for which the replacement function returns None are left as they are.
+pub fn replace_css_urls(
+    css: &str,
+    mut replace: impl FnMut(&str) -> Result<Option<String>, ChandlerError>,
+) -> Result<String, ChandlerError> {
+    let mut result = String::with_capacity(css.len());
+    let mut last_end = 0;
+
+    for caps in CSS_URL_REGEX.captures_iter(css) {
+        let whole = caps.get(0).unwrap();
+
+        let (url, is_import) = if let Some(url) = get_first(&caps, &[1, 2, 3]) {
+            (url, false)
+        } else if let Some(url) = get_first(&caps, &[4, 5]) {
+            (url, true)
+        } else {
+            continue;
+        };
+
+        let Some(new_url) = replace(url)? else {
+            continue;
+        };
+
+        result.push_str(&css[last_end..whole.start()]);
+
+        if is_import {
+            result.push_str(&format!("@import \"{new_url}\""));
+        } else {
+            result.push_str(&format!("url(\"{new_url}\")"));
+        }
+
+        last_end = whole.end();
+    }
+
+    result.push_str(&css[last_end..]);
+
+    Ok(result)
+}
+
+/// Get the first of the capture groups that matched.
+fn get_first<'t>(caps: &Captures<'t>, groups: &[usize]) -> Option<&'t str> {
+    groups.iter().find_map(|i| caps.get(*i)).map(|m| m.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSS: &str = r#"@import "base.css"; @import url(theme.css); body { background: url('img/bg.png') repeat; } .a { background-image: url( "img/sprite.png" ); } .b { background: url(data:image/png;base64,AAAA); }"#;
+
+    const EXPECTED_CSS: &str = r#"@import "local/base.css"; @import url("local/theme.css"); body { background: url("local/img/bg.png") repeat; } .a { background-image: url("local/img/sprite.png"); } .b { background: url(data:image/png;base64,AAAA); }"#;
+
+    #[test]
+    fn can_replace_css_urls() {
+        let mut urls: Vec<String> = Vec::new();
+
+        let result = replace_css_urls(CSS, |url| {
+            urls.push(url.to_owned());
+
+            Ok((!url.starts_with("data:")).then(|| format!("local/{url}")))
+        })
+        .unwrap();
+
+        assert_eq!(
+            urls,
+            vec![
+                "base.css",
+                "theme.css",
+                "img/bg.png",
+                "img/sprite.png",
+                "data:image/png;base64,AAAA"
+            ]
+        );
+        assert_eq!(result, EXPECTED_CSS);
+    }
+}
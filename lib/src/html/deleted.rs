@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use kuchikiki::*;
+
+use super::*;
+
+/// Class added to posts that have been deleted upstream.
+pub const DELETED_CLASS: &str = "chandler-deleted";
+
+/// Attribute recording when a post was first found to be deleted.
+const DELETED_AT_ATTR: &str = "data-chandler-deleted-at";
+
+/// Mark an element as deleted.
+/// Returns false if it was already marked.
+pub fn mark_deleted(node: &NodeRef, at: DateTime<Utc>) -> bool {
+    let Some(data) = node.as_element() else {
+        return false;
+    };
+
+    let mut attrs = data.attributes.borrow_mut();
+
+    if attrs.contains(DELETED_AT_ATTR) {
+        return false;
+    }
+
+    add_class(&mut attrs, DELETED_CLASS);
+    attrs.insert(DELETED_AT_ATTR, at.to_rfc3339());
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use html5ever::local_name;
+
+    use super::*;
+    use crate::html;
+
+    #[test]
+    fn can_mark_deleted() {
+        let document = html::parse_string(r#"<div class="replyContainer" id="pc2"></div>"#);
+        let node = html::find_elements(document.clone(), |_| true)
+            .find(|n| n.as_element().is_some_and(|e| e.name.local == local_name!("div")))
+            .unwrap();
+
+        let at = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().into();
+
+        assert!(mark_deleted(&node, at));
+        assert!(!mark_deleted(&node, Utc::now()));
+
+        assert_eq!(
+            html::to_string(document),
+            html::normalize(
+                r#"<div class="replyContainer chandler-deleted" id="pc2" data-chandler-deleted-at="2024-01-01T00:00:00+00:00"></div>"#
+            )
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use html5ever::{local_name, namespace_url, ns, QualName};
+use kuchikiki::*;
+
+use super::*;
+
+/// Class added to posts that have been edited upstream.
+pub const EDITED_CLASS: &str = "chandler-edited";
+
+/// Class of the collapsed element containing the original version of an edited post.
+pub const EDIT_ORIGINAL_CLASS: &str = "chandler-edit-original";
+
+/// Attribute recording when a post was found to be edited.
+const EDITED_AT_ATTR: &str = "data-chandler-edited-at";
+
+/// Get the text of a post, for detecting edits.
+/// Scripts, styles and kept original versions are ignored, and whitespace is collapsed.
+pub fn post_text(node: &NodeRef) -> String {
+    fn collect_text(node: &NodeRef, text: &mut String) {
+        for child in node.children() {
+            match child.data() {
+                NodeData::Text(t) => text.push_str(&t.borrow()),
+                NodeData::Element(data) => {
+                    let is_ignored = matches!(data.name.local, local_name!("script") | local_name!("style"))
+                        || has_classes(data, &[EDIT_ORIGINAL_CLASS]);
+
+                    if !is_ignored {
+                        collect_text(&child, text);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut text = String::new();
+    collect_text(node, &mut text);
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Mark an element as edited.
+/// Returns false if it was already marked.
+pub fn mark_edited(node: &NodeRef, at: DateTime<Utc>) -> bool {
+    let Some(data) = node.as_element() else {
+        return false;
+    };
+
+    let mut attrs = data.attributes.borrow_mut();
+
+    if attrs.contains(EDITED_AT_ATTR) {
+        return false;
+    }
+
+    add_class(&mut attrs, EDITED_CLASS);
+    attrs.insert(EDITED_AT_ATTR, at.to_rfc3339());
+
+    true
+}
+
+/// Replace an edited post with its new version.
+/// If keep_original is true, the content of the original version is kept
+/// collapsed inside the new one, along with any versions kept from previous edits.
+pub fn replace_edited(original: &NodeRef, replacement: &NodeRef, at: DateTime<Utc>, keep_original: bool) {
+    if keep_original {
+        let children: Vec<NodeRef> = original.children().collect();
+
+        let details = NodeRef::new_element(
+            QualName::new(None, ns!(html), local_name!("details")),
+            [
+                (
+                    ExpandedName::new(ns!(), local_name!("class")),
+                    Attribute {
+                        prefix: None,
+                        value: EDIT_ORIGINAL_CLASS.to_owned(),
+                    },
+                ),
+                (
+                    ExpandedName::new(ns!(), EDITED_AT_ATTR),
+                    Attribute {
+                        prefix: None,
+                        value: at.to_rfc3339(),
+                    },
+                ),
+            ],
+        );
+
+        let summary = NodeRef::new_element(QualName::new(None, ns!(html), local_name!("summary")), []);
+        summary.append(NodeRef::new_text("Original version"));
+        details.append(summary);
+
+        for child in children {
+            let is_kept_version = child
+                .as_element()
+                .is_some_and(|data| has_classes(data, &[EDIT_ORIGINAL_CLASS]));
+
+            if is_kept_version {
+                // Carry over versions kept from previous edits.
+                replacement.append(child);
+            } else {
+                details.append(child);
+            }
+        }
+
+        replacement.append(details);
+    }
+
+    original.insert_before(replacement.clone());
+    original.detach();
+
+    mark_edited(replacement, at);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html;
+
+    fn find_post(document: &NodeRef, id: &str) -> NodeRef {
+        html::find_elements(document.clone(), |data| {
+            data.attributes.borrow().get(local_name!("id")) == Some(id)
+        })
+        .next()
+        .unwrap()
+    }
+
+    #[test]
+    fn post_text_ignores_scripts_and_kept_versions() {
+        let document = html::parse_string(
+            r#"<div id="p1">Hello <b>world</b><script>x()</script><details class="chandler-edit-original">Old</details></div>"#,
+        );
+
+        assert_eq!(post_text(&find_post(&document, "p1")), "Hello world");
+    }
+
+    #[test]
+    fn can_replace_edited_keeping_original() {
+        let document = html::parse_string(r#"<div class="thread"><div class="reply" id="p1">Hello</div></div>"#);
+        let new_document =
+            html::parse_string(r#"<div class="reply" id="p1">Hello (USER WAS BANNED FOR THIS POST)</div>"#);
+
+        let at = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().into();
+
+        replace_edited(&find_post(&document, "p1"), &find_post(&new_document, "p1"), at, true);
+
+        assert_eq!(
+            html::to_string(document),
+            html::normalize(
+                r#"<div class="thread"><div class="reply chandler-edited" id="p1" data-chandler-edited-at="2024-01-01T00:00:00+00:00">Hello (USER WAS BANNED FOR THIS POST)<details class="chandler-edit-original" data-chandler-edited-at="2024-01-01T00:00:00+00:00"><summary>Original version</summary>Hello</details></div></div>"#
+            )
+        );
+    }
+}
@@ -14,3 +14,13 @@ pub fn has_classes(data: &ElementData, class_names: &[&str]) -> bool {
         false
     }
 }
+
+/// Add a class to an element's attributes.
+pub fn add_class(attrs: &mut Attributes, class_name: &str) {
+    let class = match attrs.get(local_name!("class")) {
+        Some(class) if !class.is_empty() => format!("{class} {class_name}"),
+        _ => class_name.to_owned(),
+    };
+
+    attrs.insert(local_name!("class"), class);
+}
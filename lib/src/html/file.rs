@@ -15,3 +15,10 @@ pub fn parse_file(filename: &Path) -> Result<kuchikiki::NodeRef, ChandlerError>
 
     Ok(dom)
 }
+
+/// Parse string into Kuchiki node.
+pub fn parse_string(html_str: &str) -> kuchikiki::NodeRef {
+    use html5ever::tendril::TendrilSink;
+
+    kuchikiki::parse_html().from_utf8().one(html_str.as_bytes())
+}
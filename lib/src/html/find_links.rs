@@ -3,66 +3,134 @@ use kuchikiki::*;
 
 use super::*;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum LinkTag {
     A,
     Img,
     Link,
+
+    /// <source> element, inside <video>, <audio> or <picture>.
+    Source,
+    Video,
+    Audio,
+    Object,
+
+    /// Element with inline CSS in a style attribute.
+    StyleAttribute,
+
+    /// <style> element.
+    StyleElement,
 }
 
 #[derive(Debug)]
 pub struct Link {
     node: NodeRef,
     tag: LinkTag,
+
+    /// Name of the attribute containing the link.
+    attr_name: LocalName,
 }
 
 impl LinkTag {
-    pub fn attr_name(&self) -> LocalName {
+    pub fn from_name(name: &LocalName) -> Option<Self> {
+        Some(match *name {
+            local_name!("a") => LinkTag::A,
+            local_name!("img") => LinkTag::Img,
+            local_name!("link") => LinkTag::Link,
+            local_name!("source") => LinkTag::Source,
+            local_name!("video") => LinkTag::Video,
+            local_name!("audio") => LinkTag::Audio,
+            local_name!("object") => LinkTag::Object,
+            _ => return None,
+        })
+    }
+
+    /// Get the names of all attributes that can contain links.
+    /// Lazy-loading attributes are included, as they contain the actual image to display.
+    pub fn attr_names(&self) -> Vec<LocalName> {
         match self {
-            LinkTag::A => local_name!("href"),
-            LinkTag::Img => local_name!("src"),
-            LinkTag::Link => local_name!("href"),
+            LinkTag::A => vec![local_name!("href")],
+            LinkTag::Img => vec![
+                local_name!("src"),
+                local_name!("srcset"),
+                LocalName::from("data-src"),
+                LocalName::from("data-srcset"),
+                LocalName::from("data-original"),
+            ],
+            LinkTag::Link => vec![local_name!("href")],
+            LinkTag::Source => vec![local_name!("src"), local_name!("srcset")],
+            LinkTag::Video => vec![local_name!("src"), local_name!("poster"), LocalName::from("data-src")],
+            LinkTag::Audio => vec![local_name!("src")],
+            LinkTag::Object => vec![local_name!("data")],
+            LinkTag::StyleAttribute => vec![local_name!("style")],
+            LinkTag::StyleElement => vec![local_name!("style")],
         }
     }
+
+    /// Whether the link is CSS that may reference files, rather than a single URL.
+    pub fn is_css(&self) -> bool {
+        matches!(self, LinkTag::StyleAttribute | LinkTag::StyleElement)
+    }
 }
 
 impl Link {
-    pub fn from_node(node: NodeRef) -> Option<Self> {
+    /// Get all links in an element, one for each attribute containing links.
+    pub fn from_node(node: NodeRef) -> Vec<Self> {
+        let mut links: Vec<Self> = Vec::new();
+
         if let NodeData::Element(data) = node.data() {
-            match data.name.local {
-                // <a> element
-                local_name!("a") => {
-                    return Some(Link { node, tag: LinkTag::A });
-                }
-                // <img> element
-                local_name!("img") => {
-                    return Some(Link {
-                        node,
-                        tag: LinkTag::Img,
-                    });
-                }
-                // <link> element
-                local_name!("link") => {
-                    return Some(Link {
-                        node,
-                        tag: LinkTag::Link,
-                    });
+            let attrs = data.attributes.borrow();
+
+            if let Some(tag) = LinkTag::from_name(&data.name.local) {
+                for attr_name in tag.attr_names() {
+                    if attrs.contains(attr_name.clone()) {
+                        links.push(Link {
+                            node: node.clone(),
+                            tag,
+                            attr_name,
+                        });
+                    }
                 }
+            }
 
-                _ => {}
+            // <style> element
+            if data.name.local == local_name!("style") {
+                links.push(Link {
+                    node: node.clone(),
+                    tag: LinkTag::StyleElement,
+                    attr_name: local_name!("style"),
+                });
+            } else if attrs.contains(local_name!("style")) {
+                // Element with style attribute
+                links.push(Link {
+                    node: node.clone(),
+                    tag: LinkTag::StyleAttribute,
+                    attr_name: local_name!("style"),
+                });
             }
         }
 
-        None
+        links
+    }
+
+    pub fn tag(&self) -> &LinkTag {
+        &self.tag
     }
 
+    pub fn attr_name(&self) -> &LocalName {
+        &self.attr_name
+    }
+
+    /// Get the value of the link attribute.
     pub fn link(&self) -> Option<String> {
-        if let NodeData::Element(data) = self.node.data() {
-            let attr_name = self.tag.attr_name();
+        if self.tag.is_css() {
+            return None;
+        }
 
+        if let NodeData::Element(data) = self.node.data() {
             let attrs = data.attributes.borrow();
 
-            if let Some(attr_value) = attrs.get(attr_name) {
+            if let Some(attr_value) = attrs.get(self.attr_name.clone()) {
                 return Some(attr_value.to_owned());
             }
         }
@@ -70,52 +138,246 @@ impl Link {
         None
     }
 
-    pub fn file_link(&self) -> Option<String> {
-        self.link().filter(|link| {
-            if link.is_empty() {
-                return false;
-            }
+    /// Get all URLs in the link.
+    /// For srcset attributes, this is the URL of each image candidate.
+    pub fn urls(&self) -> Vec<String> {
+        let Some(link) = self.link() else {
+            return Vec::new();
+        };
+
+        if is_srcset(&self.attr_name) {
+            parse_srcset(&link).into_iter().map(|(url, _)| url.to_owned()).collect()
+        } else {
+            vec![link]
+        }
+    }
 
-            if link.starts_with('#') {
-                return false;
-            }
+    /// Get all URLs the link had before it was first replaced with local paths.
+    /// For srcset attributes, this is the URL of each image candidate.
+    pub fn original_urls(&self) -> Vec<String> {
+        if self.tag.is_css() {
+            return Vec::new();
+        }
 
-            if link.ends_with('/') {
-                return false;
-            }
+        let Some(data) = self.node.as_element() else {
+            return Vec::new();
+        };
+
+        let attrs = data.attributes.borrow();
+
+        let Some(original) = attrs.get(format!("data-original-{}", self.attr_name).as_str()) else {
+            return Vec::new();
+        };
+
+        if is_srcset(&self.attr_name) {
+            parse_srcset(original)
+                .into_iter()
+                .map(|(url, _)| url.to_owned())
+                .collect()
+        } else {
+            vec![original.to_owned()]
+        }
+    }
 
-            if link.starts_with("javascript:") {
-                return false;
+    /// Get the CSS, if this is a style attribute or element.
+    pub fn css(&self) -> Option<String> {
+        match self.tag {
+            LinkTag::StyleElement => Some(self.node.text_contents()),
+            LinkTag::StyleAttribute => {
+                let data = self.node.as_element()?;
+                let attrs = data.attributes.borrow();
+
+                attrs.get(local_name!("style")).map(|s| s.to_owned())
             }
+            _ => None,
+        }
+    }
 
-            true
-        })
+    /// Check whether the link has already been replaced with a local path.
+    pub fn is_replaced(&self) -> bool {
+        if let NodeData::Element(data) = self.node.data() {
+            let attr_name = format!("data-original-{}", self.attr_name);
+
+            return data.attributes.borrow().contains(attr_name);
+        }
+
+        false
+    }
+
+    /// Get all URLs in the link that could be files.
+    pub fn file_links(&self) -> Vec<String> {
+        if self.is_replaced() {
+            return Vec::new();
+        }
+
+        self.urls()
+            .into_iter()
+            .filter(|link| {
+                if link.is_empty() {
+                    return false;
+                }
+
+                if link.starts_with('#') {
+                    return false;
+                }
+
+                if link.ends_with('/') {
+                    return false;
+                }
+
+                if link.starts_with("javascript:") {
+                    return false;
+                }
+
+                true
+            })
+            .collect()
     }
 
     pub fn replace(&mut self, with: &str) {
+        if let LinkTag::StyleElement = self.tag {
+            self.replace_text(with);
+            return;
+        }
+
         if let NodeData::Element(data) = self.node.data() {
-            let attr_name = self.tag.attr_name();
+            let attr_name = &self.attr_name;
 
             let mut attrs = data.attributes.borrow_mut();
 
             let mut original_value: Option<String> = None;
 
-            if let Some(attr_value) = attrs.get_mut(&attr_name) {
+            if let Some(attr_value) = attrs.get_mut(attr_name.clone()) {
                 original_value = Some(attr_value.clone());
 
                 attr_value.clear();
                 attr_value.push_str(with);
             }
 
-            if let Some(original_value) = original_value {
-                attrs.insert(format!("data-original-{}", &attr_name), original_value);
+            // Keep the value from before the link was first replaced.
+            let original_attr_name = format!("data-original-{}", attr_name);
+
+            if let Some(original_value) = original_value.filter(|_| !attrs.contains(original_attr_name.as_str())) {
+                attrs.insert(original_attr_name, original_value);
             }
         }
     }
+
+    /// Replace each URL in the link independently.
+    /// URLs for which the replacement function returns None are left as they are.
+    pub fn replace_urls(&mut self, mut replace: impl FnMut(&str) -> Option<String>) {
+        let Some(link) = self.link() else {
+            return;
+        };
+
+        let new_link = if is_srcset(&self.attr_name) {
+            let candidates: Vec<(&str, &str)> = parse_srcset(&link);
+
+            if candidates.iter().all(|(url, _)| replace(url).is_none()) {
+                return;
+            }
+
+            candidates
+                .into_iter()
+                .map(|(url, descriptor)| {
+                    let url = replace(url).unwrap_or_else(|| url.to_owned());
+
+                    if descriptor.is_empty() {
+                        url
+                    } else {
+                        format!("{url} {descriptor}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        } else if let Some(new_link) = replace(&link) {
+            new_link
+        } else {
+            return;
+        };
+
+        self.replace(&new_link);
+    }
+
+    /// Replace the text of a <style> element, keeping the original in an attribute.
+    fn replace_text(&mut self, with: &str) {
+        if let NodeData::Element(data) = self.node.data() {
+            let original_value = self.node.text_contents();
+
+            for child in self.node.children().collect::<Vec<_>>() {
+                child.detach();
+            }
+
+            self.node.append(NodeRef::new_text(with));
+
+            let mut attrs = data.attributes.borrow_mut();
+            let original_attr_name = format!("data-original-{}", self.attr_name);
+
+            if !attrs.contains(original_attr_name.as_str()) {
+                attrs.insert(original_attr_name, original_value);
+            }
+        }
+    }
+}
+
+/// Check whether the attribute contains a set of image candidates, rather than a single URL.
+fn is_srcset(attr_name: &LocalName) -> bool {
+    *attr_name == local_name!("srcset") || &**attr_name == "data-srcset"
+}
+
+/// Parse a srcset attribute into the URL and descriptor of each image candidate.
+///
+/// As in browsers, each URL extends up to the next whitespace, so URLs may contain commas.
+/// Commas at the end of a URL separate it from the next candidate.
+fn parse_srcset(srcset: &str) -> Vec<(&str, &str)> {
+    let mut candidates = Vec::new();
+    let mut rest = srcset;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+
+        if rest.is_empty() {
+            break;
+        }
+
+        let (url, after_url) = rest.split_at(rest.find(|c: char| c.is_ascii_whitespace()).unwrap_or(rest.len()));
+        rest = after_url;
+
+        // A URL ending with commas has no descriptor.
+        let trimmed_url = url.trim_end_matches(',');
+
+        if trimmed_url.len() < url.len() {
+            candidates.push((trimmed_url, ""));
+            continue;
+        }
+
+        // The descriptor extends up to the next comma outside parentheses.
+        let mut depth: u32 = 0;
+
+        let descriptor_end = rest
+            .find(|c: char| match c {
+                '(' => {
+                    depth += 1;
+                    false
+                }
+                ')' => {
+                    depth = depth.saturating_sub(1);
+                    false
+                }
+                ',' => depth == 0,
+                _ => false,
+            })
+            .unwrap_or(rest.len());
+
+        candidates.push((url, rest[..descriptor_end].trim()));
+        rest = &rest[descriptor_end..];
+    }
+
+    candidates
 }
 
 pub fn find_links(node: NodeRef) -> Vec<Link> {
-    find_elements(node, |_| true).filter_map(Link::from_node).collect()
+    find_elements(node, |_| true).flat_map(Link::from_node).collect()
 }
 
 #[cfg(test)]
@@ -142,6 +404,7 @@ mod tests {
         <a href="#"></a>
         <a href="path/to/dir/"></a>
         <a href="javascript:doStuff()"></a>
+        <img src="content/file.png" data-original-src="images/other.png">
     </div>
     "###;
 
@@ -162,7 +425,7 @@ mod tests {
         let expected_links = vec!["a".to_owned(), "images/file.png".to_owned(), "css/style.css".to_owned()];
 
         let links = find_links(node);
-        let links: Vec<String> = links.into_iter().filter_map(|link| link.file_link()).collect();
+        let links: Vec<String> = links.into_iter().flat_map(|link| link.file_links()).collect();
 
         assert_eq!(links, expected_links);
     }
@@ -197,9 +460,12 @@ mod tests {
     fn can_replace_links() {
         let node = parse_string(&normalize(HTML_REPLACE_LINKS));
 
-        for link in find_links(node.clone()).iter_mut() {
-            if let Some(value) = link.link() {
-                link.replace(&value.to_uppercase());
+        // Replacing links again should keep the original values.
+        for _ in 0..2 {
+            for link in find_links(node.clone()).iter_mut() {
+                if let Some(value) = link.link() {
+                    link.replace(&value.to_uppercase());
+                }
             }
         }
 
@@ -207,4 +473,74 @@ mod tests {
 
         assert_eq!(result, normalize(HTML_REPLACE_LINKS_EXPECTED_RESULT));
     }
+
+    const HTML_MEDIA_LINKS: &str = r###"
+    <div>
+        <img src="thumb.gif" data-src="images/lazy.jpg" srcset="images/small.jpg 1x, images/large.jpg 2x">
+        <video poster="images/poster.jpg"><source src="media/video.mp4" type="video/mp4"></video>
+        <audio src="media/audio.ogg"></audio>
+        <object data="media/file.svg"></object>
+    </div>
+    "###;
+
+    #[test]
+    fn can_find_media_links() {
+        let node = parse_string(HTML_MEDIA_LINKS);
+        let mut expected_links = vec![
+            "thumb.gif".to_owned(),
+            "images/small.jpg".to_owned(),
+            "images/large.jpg".to_owned(),
+            "images/lazy.jpg".to_owned(),
+            "images/poster.jpg".to_owned(),
+            "media/video.mp4".to_owned(),
+            "media/audio.ogg".to_owned(),
+            "media/file.svg".to_owned(),
+        ];
+
+        let links = find_links(node);
+        let mut links: Vec<String> = links.into_iter().flat_map(|link| link.file_links()).collect();
+
+        // Child elements are not necessarily found in document order.
+        links.sort();
+        expected_links.sort();
+
+        assert_eq!(links, expected_links);
+    }
+
+    #[test]
+    fn can_replace_srcset_urls() {
+        let node = parse_string(r#"<img srcset="images/small.jpg 480w,images/large.jpg  800w">"#);
+
+        let mut links = find_links(node.clone());
+        assert_eq!(links.len(), 1);
+
+        links[0].replace_urls(|url| (url == "images/large.jpg").then(|| "content/large.jpg".to_owned()));
+
+        assert_eq!(
+            to_string(node),
+            normalize(
+                r#"<img srcset="images/small.jpg 480w, content/large.jpg 800w" data-original-srcset="images/small.jpg 480w,images/large.jpg  800w">"#
+            )
+        );
+        assert!(links[0].file_links().is_empty());
+    }
+
+    #[test]
+    fn can_parse_srcset_with_commas_in_urls() {
+        assert_eq!(
+            parse_srcset("https://cdn.example.com/img,w_400.jpg 1x, https://cdn.example.com/img,w_800.jpg 2x"),
+            [
+                ("https://cdn.example.com/img,w_400.jpg", "1x"),
+                ("https://cdn.example.com/img,w_800.jpg", "2x")
+            ]
+        );
+        assert_eq!(
+            parse_srcset("images/small.jpg,images/large.jpg 2x"),
+            [("images/small.jpg,images/large.jpg", "2x")]
+        );
+        assert_eq!(
+            parse_srcset(" images/small.jpg, images/large.jpg 2x,"),
+            [("images/small.jpg", ""), ("images/large.jpg", "2x")]
+        );
+    }
 }
@@ -1,17 +1,29 @@
+mod css;
+mod deleted;
+mod edited;
 mod element;
+mod escape;
 mod file;
 mod find_elements;
 mod find_links;
 mod purge_scripts;
+mod sanitize;
+mod style;
 
 #[cfg(test)]
 mod test_only;
 
+pub use self::css::*;
+pub use self::deleted::*;
+pub use self::edited::*;
 pub use self::element::*;
+pub use self::escape::*;
 pub use self::file::*;
 pub use self::find_elements::*;
 pub use self::find_links::*;
 pub use self::purge_scripts::*;
+pub use self::sanitize::*;
+pub use self::style::*;
 
 #[cfg(test)]
 pub use self::test_only::*;
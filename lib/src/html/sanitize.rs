@@ -0,0 +1,229 @@
+use html5ever::{local_name, namespace_url, ns, LocalName, QualName};
+use kuchikiki::*;
+
+use crate::config::chandler::SanitizeProfile;
+
+use super::*;
+
+/// Content Security Policy injected by the strict and paranoid profiles.
+/// It only allows local files, inline styles and embedded data, so that the page cannot make network requests.
+/// Local files are also allowed by scheme, as browsers do not consider other files to be 'self' when a page is opened from disk.
+const CONTENT_SECURITY_POLICY: &str = "default-src 'self' file: data:; style-src 'self' file: 'unsafe-inline' data:; script-src 'none'; object-src 'none'; frame-src 'none'; connect-src 'none'; form-action 'none'; base-uri 'none'";
+
+/// Elements removed by the strict profile.
+const STRICT_ELEMENTS: &[&str] = &["embed", "frame", "frameset", "iframe", "noscript"];
+
+/// Elements removed by the paranoid profile, in addition to those removed by the strict profile.
+const PARANOID_ELEMENTS: &[&str] = &["applet", "base", "form", "object"];
+
+/// Attributes removed by the strict profile, in addition to event handlers.
+const STRICT_ATTRIBUTES: &[&str] = &["ping"];
+
+/// Link relations kept by the strict and paranoid profiles. All other <link> elements are removed.
+const STRICT_LINK_RELS: &[&str] = &["icon", "shortcut icon", "stylesheet"];
+
+/// Sanitize the node and all its children according to the profile.
+pub fn sanitize(node: NodeRef, profile: SanitizeProfile) {
+    purge_scripts(node.clone());
+
+    if profile == SanitizeProfile::Minimal {
+        return;
+    }
+
+    let elements: Vec<NodeRef> = find_elements(node.clone(), |_| true).collect();
+
+    for element in elements {
+        let Some(data) = element.as_element() else {
+            continue;
+        };
+
+        if should_remove_element(data, profile) {
+            element.detach();
+            continue;
+        }
+
+        remove_unsafe_attributes(data);
+    }
+
+    inject_content_security_policy(&node);
+}
+
+fn should_remove_element(data: &ElementData, profile: SanitizeProfile) -> bool {
+    let name: &str = &data.name.local;
+    let attrs = data.attributes.borrow();
+
+    if STRICT_ELEMENTS.contains(&name) {
+        return true;
+    }
+
+    // Remove redirects.
+    if data.name.local == local_name!("meta")
+        && attrs
+            .get(local_name!("http-equiv"))
+            .is_some_and(|v| v.eq_ignore_ascii_case("refresh"))
+    {
+        return true;
+    }
+
+    // Remove tracking pixels.
+    if data.name.local == local_name!("img")
+        && is_tiny(&attrs, local_name!("width"))
+        && is_tiny(&attrs, local_name!("height"))
+    {
+        return true;
+    }
+
+    // Remove prefetch, preconnect and similar links.
+    if data.name.local == local_name!("link") {
+        let rel = attrs
+            .get(local_name!("rel"))
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        if !STRICT_LINK_RELS.contains(&rel.as_str()) {
+            return true;
+        }
+    }
+
+    if profile == SanitizeProfile::Paranoid && PARANOID_ELEMENTS.contains(&name) {
+        return true;
+    }
+
+    false
+}
+
+/// Check whether a size attribute is at most one pixel.
+fn is_tiny(attrs: &Attributes, name: LocalName) -> bool {
+    attrs
+        .get(name)
+        .and_then(|v| v.trim().trim_end_matches("px").parse::<u32>().ok())
+        .is_some_and(|v| v <= 1)
+}
+
+/// Remove event handlers, javascript: URLs and other attributes that can run code or make requests.
+fn remove_unsafe_attributes(data: &ElementData) {
+    let mut attrs = data.attributes.borrow_mut();
+
+    let unsafe_attrs: Vec<ExpandedName> = attrs
+        .map
+        .iter()
+        .filter(|(name, attr)| {
+            let local: &str = &name.local;
+
+            local.starts_with("on")
+                || STRICT_ATTRIBUTES.contains(&local)
+                || attr.value.trim_start().to_ascii_lowercase().starts_with("javascript:")
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    for name in unsafe_attrs {
+        attrs.map.remove(&name);
+    }
+}
+
+/// Inject a Content Security Policy into <head>, or the document itself if it has none.
+/// Nothing is done if it is already present.
+fn inject_content_security_policy(node: &NodeRef) {
+    let is_injected = find_elements(node.clone(), |data| {
+        data.name.local == local_name!("meta")
+            && data.attributes.borrow().get(local_name!("content")) == Some(CONTENT_SECURITY_POLICY)
+    })
+    .next()
+    .is_some();
+
+    if is_injected {
+        return;
+    }
+
+    let parent = find_elements(node.clone(), |data| data.name.local == local_name!("head"))
+        .next()
+        .unwrap_or_else(|| node.clone());
+
+    let meta = NodeRef::new_element(
+        QualName::new(None, ns!(html), local_name!("meta")),
+        [
+            (
+                ExpandedName::new(ns!(), local_name!("http-equiv")),
+                Attribute {
+                    prefix: None,
+                    value: "Content-Security-Policy".to_owned(),
+                },
+            ),
+            (
+                ExpandedName::new(ns!(), local_name!("content")),
+                Attribute {
+                    prefix: None,
+                    value: CONTENT_SECURITY_POLICY.to_owned(),
+                },
+            ),
+        ],
+    );
+
+    // The policy only applies to content after it, so it must come first.
+    parent.prepend(meta);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html;
+
+    const HTML: &str = r#"<html><head><meta http-equiv="refresh" content="0; url=https://example.org/"><link rel="preconnect" href="https://cdn.example.org"><link rel="stylesheet" href="style.css"></head><body onload="track()"><script>track()</script><noscript><img src="https://example.org/pixel.gif"></noscript><iframe src="https://ads.example.org/"></iframe><a href="javascript:void(0)" onclick="x()" ping="https://example.org/ping">Link</a><img src="https://example.org/t.gif" width="1" height="1"><img src="image.png" width="100" height="100"><form action="/post"><input name="com"></form></body></html>"#;
+
+    #[test]
+    fn can_sanitize_minimal() {
+        let document = html::parse_string(HTML);
+        sanitize(document.clone(), SanitizeProfile::Minimal);
+
+        let result = html::to_string(document);
+
+        assert!(!result.contains("<script>"));
+        assert!(result.contains("<iframe"));
+        assert!(result.contains(r#"onclick="x()""#));
+        assert!(!result.contains("Content-Security-Policy"));
+    }
+
+    #[test]
+    fn can_sanitize_strict() {
+        let document = html::parse_string(HTML);
+        sanitize(document.clone(), SanitizeProfile::Strict);
+        sanitize(document.clone(), SanitizeProfile::Strict);
+
+        let result = html::to_string(document);
+
+        for removed in [
+            "<script>",
+            "<noscript>",
+            "<iframe",
+            "refresh",
+            "onload",
+            "onclick",
+            "javascript:",
+            "ping",
+            "t.gif",
+        ] {
+            assert!(!result.contains(removed), "{removed} was not removed");
+        }
+
+        assert!(result.contains(r#"<img src="image.png""#));
+        assert!(result.contains("<form"));
+        assert!(!result.contains("preconnect"));
+        assert!(result.contains(r#"<link rel="stylesheet" href="style.css">"#));
+        assert_eq!(result.matches("Content-Security-Policy").count(), 1);
+        assert!(result.starts_with(r#"<html><head><meta http-equiv="Content-Security-Policy""#));
+        assert!(result.contains("default-src 'self' file: data:;"));
+    }
+
+    #[test]
+    fn can_sanitize_paranoid() {
+        let document = html::parse_string(HTML);
+        sanitize(document.clone(), SanitizeProfile::Paranoid);
+
+        let result = html::to_string(document);
+
+        assert!(!result.contains("<form"));
+        assert!(result.contains(r#"<link rel="stylesheet" href="style.css">"#));
+    }
+}
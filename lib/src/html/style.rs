@@ -0,0 +1,75 @@
+use html5ever::{local_name, namespace_url, ns, QualName};
+use kuchikiki::*;
+
+use super::*;
+
+/// ID of the style element injected to make Chandler's annotations visible.
+const STYLE_ID: &str = "chandler-style";
+
+/// Style for posts that have been deleted or edited upstream.
+const STYLE: &str = concat!(
+    ".chandler-deleted { border-left: 3px solid #d00 !important; opacity: 0.7; }",
+    " .chandler-edited { border-left: 3px solid #d80 !important; }",
+    " .chandler-edit-original { opacity: 0.7; }",
+);
+
+/// Inject Chandler's style into the document containing the node.
+/// Returns false if it was already present.
+pub fn inject_style(node: &NodeRef) -> bool {
+    let root = node.inclusive_ancestors().last().unwrap_or_else(|| node.clone());
+
+    let is_injected = find_elements(root.clone(), |data| {
+        data.name.local == local_name!("style") && data.attributes.borrow().get(local_name!("id")) == Some(STYLE_ID)
+    })
+    .next()
+    .is_some();
+
+    if is_injected {
+        return false;
+    }
+
+    // Insert the style into <head>, or the document itself if it has none.
+    let parent = find_elements(root.clone(), |data| data.name.local == local_name!("head"))
+        .next()
+        .unwrap_or(root);
+
+    let style = NodeRef::new_element(
+        QualName::new(None, ns!(html), local_name!("style")),
+        [(
+            ExpandedName::new(ns!(), local_name!("id")),
+            Attribute {
+                prefix: None,
+                value: STYLE_ID.to_owned(),
+            },
+        )],
+    );
+    style.append(NodeRef::new_text(STYLE));
+
+    parent.append(style);
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html;
+
+    #[test]
+    fn can_inject_style() {
+        let document = html::parse_string(r#"<html><head></head><body><div id="pc2"></div></body></html>"#);
+        let node = html::find_elements(document.clone(), |data| data.name.local == local_name!("div"))
+            .next()
+            .unwrap();
+
+        assert!(inject_style(&node));
+        assert!(!inject_style(&node));
+
+        assert_eq!(
+            html::to_string(document),
+            html::normalize(&format!(
+                r#"<html><head><style id="{STYLE_ID}">{STYLE}</style></head><body><div id="pc2"></div></body></html>"#
+            ))
+        );
+    }
+}
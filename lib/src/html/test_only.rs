@@ -2,12 +2,7 @@
 
 use kuchikiki::*;
 
-/// Parse string into Kuchiki node.
-pub fn parse_string(html_str: &str) -> NodeRef {
-    use html5ever::tendril::TendrilSink;
-
-    kuchikiki::parse_html().from_utf8().one(html_str.as_bytes())
-}
+use super::*;
 
 /// Serialize Kuchiki node to string.
 pub fn to_string(node: NodeRef) -> String {
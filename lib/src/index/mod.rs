@@ -0,0 +1,453 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::error::*;
+use crate::project::{self, Project, ProjectSummary};
+use crate::util;
+use crate::util::pid::PidLock;
+
+mod render;
+
+pub use self::render::*;
+
+/// Name of the generated index file, in the download path.
+pub const INDEX_FILE_NAME: &str = "index.html";
+
+/// Name of the file the index entries are stored in, in the download path.
+const INDEX_DATA_FILE_NAME: &str = ".chandler-index.json";
+
+/// Name of the lock file that is held while the index is loaded, in the download path.
+const INDEX_LOCK_FILE_NAME: &str = ".chandler-index.lock";
+
+/// Maximum time to wait for another process to finish updating the index.
+const INDEX_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum number of characters in the excerpt of the first post.
+const EXCERPT_LENGTH: usize = 200;
+
+/// An archived thread in the index.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IndexEntry {
+    /// Path of the project, relative to the download path.
+    pub path: String,
+
+    /// Path of the thread file, relative to the download path.
+    pub thread_file: String,
+
+    /// URL of the thread.
+    pub url: String,
+
+    /// Subject of the first post.
+    pub subject: Option<String>,
+
+    /// Beginning of the text of the first post.
+    pub excerpt: Option<String>,
+
+    /// Number of posts, if the thread's parser can identify posts.
+    pub post_count: Option<usize>,
+
+    /// Number of files attached to posts, or of all downloaded files if posts cannot be identified.
+    pub file_count: usize,
+
+    /// When the thread was last downloaded or updated.
+    pub last_update: Option<DateTime<Utc>>,
+
+    pub is_dead: bool,
+
+    /// When the project was last written, used to detect projects that need to be indexed again.
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct IndexData {
+    /// Entries, by project path.
+    entries: BTreeMap<String, IndexEntry>,
+}
+
+/// Index of all projects in a download path.
+/// The index is locked for as long as it is loaded, so that concurrent updates are not lost.
+pub struct ArchiveIndex {
+    download_path: PathBuf,
+    data: IndexData,
+    _lock: PidLock,
+}
+
+impl IndexEntry {
+    /// Create an entry for a project. Returns None if the project is not inside the download path.
+    /// The time of the last update is taken to be when the project was last written.
+    pub fn from_project(download_path: &Path, project: &dyn Project) -> Result<Option<Self>, ChandlerError> {
+        Ok(Self::from_summary(
+            download_path,
+            &ProjectSummary::from_project(project)?,
+        ))
+    }
+
+    /// Create an entry from the summary of a project. Returns None if the project is not inside the download path.
+    /// The time of the last update is taken to be when the project was last written.
+    pub fn from_summary(download_path: &Path, summary: &ProjectSummary) -> Option<Self> {
+        let project_path = util::normalize_path(&summary.root_path);
+
+        let (Some(path), Some(thread_file)) = (
+            relative_path(download_path, &project_path),
+            relative_path(download_path, &util::normalize_path(&summary.thread_file_path)),
+        ) else {
+            return None;
+        };
+
+        let posts = &summary.posts;
+        let op = posts.first();
+
+        let (post_count, file_count) = if posts.is_empty() {
+            (None, summary.downloaded_file_count)
+        } else {
+            (Some(posts.len()), posts.iter().map(|post| post.files.len()).sum())
+        };
+
+        let modified_at = project::modified_at(&project_path);
+
+        Some(Self {
+            path,
+            thread_file,
+            url: summary.thread_url.clone(),
+            subject: op.and_then(|op| op.subject.clone()),
+            excerpt: op.map(|op| excerpt(&op.comment_text)).filter(|e| !e.is_empty()),
+            post_count,
+            file_count,
+            last_update: modified_at,
+            is_dead: summary.is_dead,
+            modified_at,
+        })
+    }
+
+    /// Get the site the thread is from, which is the first directory of its path.
+    pub fn site(&self) -> &str {
+        self.path.split_once('/').map_or("", |(site, _)| site)
+    }
+
+    /// Get the board the thread is on, which is the directories between the site and the thread.
+    pub fn board(&self) -> &str {
+        self.path
+            .split_once('/')
+            .and_then(|(_, rest)| rest.rsplit_once('/'))
+            .map_or("", |(board, _)| board)
+    }
+}
+
+impl ArchiveIndex {
+    /// Load the index of a download path, or create an empty one if it has not been indexed before.
+    pub fn load(download_path: &Path) -> Result<Self, ChandlerError> {
+        let download_path = util::normalize_path(download_path);
+
+        let lock = PidLock::acquire_wait(download_path.join(INDEX_LOCK_FILE_NAME), INDEX_LOCK_TIMEOUT)
+            .ok_or_else(|| ChandlerError::Other("Index is locked by another process!".into()))?;
+
+        let data_file_path = download_path.join(INDEX_DATA_FILE_NAME);
+
+        let data = if data_file_path.is_file() {
+            let file = util::open_file(&data_file_path).map_err(ChandlerError::OpenFile)?;
+
+            serde_json::from_reader(file)
+                .map_err(|err| ChandlerError::Other(format!("Error reading index: {}", err).into()))?
+        } else {
+            IndexData::default()
+        };
+
+        Ok(Self {
+            download_path,
+            data,
+            _lock: lock,
+        })
+    }
+
+    /// Check whether the download path has been indexed before.
+    pub fn exists_at(download_path: &Path) -> bool {
+        download_path.join(INDEX_DATA_FILE_NAME).is_file()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &IndexEntry> {
+        self.data.entries.values()
+    }
+
+    /// Find all projects in the download path, and index those that have changed since they were last indexed.
+    /// Projects are read without being loaded, so projects that are being updated are also indexed.
+    /// Projects that cannot be read keep their existing entry.
+    /// Returns the number of projects that were indexed.
+    pub fn scan(&mut self) -> Result<usize, ChandlerError> {
+        let mut project_paths: Vec<PathBuf> = Vec::new();
+        find_projects(&self.download_path, &mut project_paths).map_err(ChandlerError::ReadFile)?;
+
+        let mut entries: BTreeMap<String, IndexEntry> = BTreeMap::new();
+        let mut indexed_count = 0;
+
+        for project_path in project_paths {
+            let Some(path) = relative_path(&self.download_path, &project_path) else {
+                continue;
+            };
+
+            let existing_entry = self.data.entries.remove(&path);
+
+            // If the project has not changed, there is no need to read it.
+            if let Some(entry) = existing_entry
+                .as_ref()
+                .filter(|entry| entry.modified_at.is_some() && entry.modified_at == project::modified_at(&project_path))
+            {
+                entries.insert(path, entry.clone());
+                continue;
+            }
+
+            info!("Indexing project: {}", project_path.display());
+
+            let entry = project::read_summary(&project_path)
+                .map(|summary| IndexEntry::from_summary(&self.download_path, &summary));
+
+            match entry {
+                Ok(Some(entry)) => {
+                    entries.insert(path, entry);
+                    indexed_count += 1;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    warn!("Could not index project at {}: {}", project_path.display(), err);
+
+                    if let Some(entry) = existing_entry {
+                        entries.insert(path, entry);
+                    }
+                }
+            }
+        }
+
+        // Any remaining entries are for projects that no longer exist.
+        self.data.entries = entries;
+
+        Ok(indexed_count)
+    }
+
+    /// Index a single project that has just been downloaded or updated, replacing any existing entry for it.
+    /// Projects that are not inside the download path are ignored.
+    pub fn update_project(&mut self, project: &dyn Project) -> Result<(), ChandlerError> {
+        if let Some(mut entry) = IndexEntry::from_project(&self.download_path, project)? {
+            entry.last_update = Some(Utc::now());
+            self.data.entries.insert(entry.path.clone(), entry);
+        }
+
+        Ok(())
+    }
+
+    /// Write the index entries and the generated index file.
+    pub fn save(&self) -> Result<(), ChandlerError> {
+        let data = serde_json::to_vec(&self.data)
+            .map_err(|err| ChandlerError::Other(format!("Error writing index: {}", err).into()))?;
+
+        util::write_file_atomic(self.download_path.join(INDEX_DATA_FILE_NAME), data)
+            .map_err(ChandlerError::CreateFile)?;
+        util::write_file_atomic(self.index_file_path(), render_index(self.entries(), Utc::now()))
+            .map_err(ChandlerError::CreateFile)?;
+
+        Ok(())
+    }
+
+    pub fn index_file_path(&self) -> PathBuf {
+        self.download_path.join(INDEX_FILE_NAME)
+    }
+}
+
+/// Update the index of a download path with a project that has just been downloaded or updated.
+/// If the download path has not been indexed yet, nothing is done, as building the index
+/// requires reading every project in it.
+pub fn update_index(download_path: &Path, project: &dyn Project) -> Result<(), ChandlerError> {
+    if !ArchiveIndex::exists_at(download_path) {
+        return Ok(());
+    }
+
+    let mut index = ArchiveIndex::load(download_path)?;
+    index.update_project(project)?;
+    index.save()
+}
+
+/// Find all projects in a directory and its subdirectories.
+fn find_projects(dir: &Path, project_paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+
+        // Skip files and hidden directories, such as those containing project data.
+        if !path.is_dir()
+            || path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+
+        // Projects are not nested, so there is no need to look inside them.
+        if project::exists_at(&path).is_some() {
+            project_paths.push(path);
+        } else {
+            find_projects(&path, project_paths)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Get a path relative to a directory, using forward slashes.
+fn relative_path(from_dir: &Path, path: &Path) -> Option<String> {
+    let path = path.strip_prefix(from_dir).ok()?;
+
+    let components: Vec<_> = path
+        .components()
+        .map(|c| match c {
+            Component::Normal(c) => Some(c.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+
+    Some(components.join("/"))
+}
+
+/// Get the beginning of a post's text, on a single line.
+fn excerpt(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if text.chars().count() <= EXCERPT_LENGTH {
+        return text;
+    }
+
+    let mut excerpt: String = text.chars().take(EXCERPT_LENGTH).collect();
+    excerpt.push('…');
+
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::download::{test_dir, TestResponse, TestServer, TestUiHandler};
+    use crate::project::ProjectFormat;
+    use crate::threadupdater::ParserType;
+
+    use super::*;
+
+    const THREAD_HTML: &str = r#"<html><body><div class="thread"><div class="post op" id="op_1"><p class="intro"><span class="subject">Cats</span></p><div class="body">Post   cats</div></div><div class="post reply" id="reply_2"><div class="file"><p class="fileinfo">File: <a href="/b/src/2.png">2.png</a></p></div><div class="body">Cat</div></div></div></body></html>"#;
+
+    fn create_project(url: &str, path: &Path, format: ProjectFormat, parser: ParserType) -> Box<dyn Project> {
+        let mut project = project::builder()
+            .url(url)
+            .path(Some(path))
+            .format(Some(format))
+            .parser(Some(parser))
+            .load_or_create()
+            .unwrap();
+
+        project.update(&mut TestUiHandler::default()).unwrap();
+        project.save().unwrap();
+
+        project
+    }
+
+    #[test]
+    fn can_index_projects() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/b/src/2.png" => TestResponse::ok(b"file"),
+            _ => TestResponse::ok(THREAD_HTML.as_bytes()),
+        });
+
+        let download_path = test_dir("index");
+
+        // Projects that are loaded, and so locked, should be indexed too.
+        let _project = create_project(
+            &server.url("/b/res/1.html"),
+            &download_path.join("site/b/1"),
+            ProjectFormat::V3,
+            ParserType::Tinyboard,
+        );
+        create_project(
+            &server.url("/g/res/2.html"),
+            &download_path.join("site/g/2"),
+            ProjectFormat::V2,
+            ParserType::Basic,
+        );
+
+        let mut index = ArchiveIndex::load(&download_path).unwrap();
+        assert_eq!(index.scan().unwrap(), 2);
+        index.save().unwrap();
+
+        let entries: Vec<_> = index.entries().collect();
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].path, "site/b/1");
+        assert_eq!(entries[0].thread_file, "site/b/1/thread.html");
+        assert_eq!(entries[0].site(), "site");
+        assert_eq!(entries[0].board(), "b");
+        assert_eq!(entries[0].subject.as_deref(), Some("Cats"));
+        assert_eq!(entries[0].excerpt.as_deref(), Some("Post cats"));
+        assert_eq!(entries[0].post_count, Some(2));
+        assert_eq!(entries[0].file_count, 1);
+
+        assert_eq!(entries[1].board(), "g");
+        assert_eq!(entries[1].post_count, None);
+
+        let html = fs::read_to_string(index.index_file_path()).unwrap();
+        assert!(html.contains(r#"href="site/b/1/thread.html""#));
+        assert!(html.contains("Cats"));
+
+        drop(index);
+
+        // Projects that have not changed should not be indexed again.
+        let mut index = ArchiveIndex::load(&download_path).unwrap();
+        assert_eq!(index.scan().unwrap(), 0);
+        assert_eq!(index.entries().count(), 2);
+
+        // Projects that no longer exist should be removed.
+        fs::remove_dir_all(download_path.join("site/g")).unwrap();
+
+        index.scan().unwrap();
+        assert_eq!(
+            index.entries().map(|e| e.path.as_str()).collect::<Vec<_>>(),
+            ["site/b/1"]
+        );
+    }
+
+    #[test]
+    fn can_update_index() {
+        let server = TestServer::start(|_| TestResponse::ok(THREAD_HTML.as_bytes()));
+
+        let download_path = test_dir("update_index");
+        let project = create_project(
+            &server.url("/b/res/1.html"),
+            &download_path.join("site/b/1"),
+            ProjectFormat::V3,
+            ParserType::Tinyboard,
+        );
+
+        // The index should not be created when updating a project.
+        update_index(&download_path, project.as_ref()).unwrap();
+        assert!(!ArchiveIndex::exists_at(&download_path));
+
+        let mut index = ArchiveIndex::load(&download_path).unwrap();
+        index.scan().unwrap();
+        index.save().unwrap();
+        drop(index);
+
+        let updated_at = Utc::now();
+        update_index(&download_path, project.as_ref()).unwrap();
+
+        let index = ArchiveIndex::load(&download_path).unwrap();
+        let entry = index.entries().next().unwrap();
+        assert!(entry.last_update.is_some_and(|last_update| last_update >= updated_at));
+    }
+
+    #[test]
+    fn can_get_excerpt() {
+        assert_eq!(excerpt("Line 1\n\nLine  2"), "Line 1 Line 2");
+
+        let long_text = "a".repeat(EXCERPT_LENGTH + 1);
+        assert_eq!(excerpt(&long_text).chars().count(), EXCERPT_LENGTH + 1);
+        assert!(excerpt(&long_text).ends_with('…'));
+    }
+}
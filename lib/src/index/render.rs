@@ -0,0 +1,121 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::html::escape;
+
+use super::*;
+
+const INDEX_CSS: &str = "body{font-family:sans-serif;margin:1em 2em}\
+table{border-collapse:collapse;width:100%;margin-bottom:1em}\
+th,td{border-bottom:1px solid #ccc;padding:.3em .5em;text-align:left;vertical-align:top}\
+.subject{font-weight:bold}.excerpt{margin:.2em 0 0;color:#555}\
+.dead .status{color:#a00}.alive .status{color:#070}";
+
+/// Render an HTML index of archived threads, grouped by site and board.
+/// Threads on each board are ordered by when they were last updated, most recent first.
+pub fn render_index<'a>(entries: impl Iterator<Item = &'a IndexEntry>, generated_at: DateTime<Utc>) -> String {
+    let mut sites: BTreeMap<&str, BTreeMap<&str, Vec<&IndexEntry>>> = BTreeMap::new();
+    let mut thread_count = 0;
+
+    for entry in entries {
+        sites
+            .entry(entry.site())
+            .or_default()
+            .entry(entry.board())
+            .or_default()
+            .push(entry);
+
+        thread_count += 1;
+    }
+
+    let mut html = String::new();
+
+    html.push_str("<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Chandler archive</title>");
+    let _ = write!(html, "<style>{INDEX_CSS}</style>");
+    html.push_str("</head><body><h1>Chandler archive</h1>");
+    let _ = write!(
+        html,
+        "<p>Threads: {thread_count}. Generated {}.</p>",
+        render_time(generated_at)
+    );
+
+    for (site, boards) in sites {
+        let _ = write!(html, "<section class=\"site\"><h2>{}</h2>", escape(site));
+
+        for (board, mut threads) in boards {
+            threads.sort_by_key(|thread| Reverse(thread.last_update));
+
+            let _ = write!(html, "<h3>/{}/</h3>", escape(board));
+            html.push_str("<table><thead><tr><th>Thread</th><th>Posts</th><th>Files</th><th>Last update</th><th>Status</th></tr></thead><tbody>");
+
+            for thread in threads {
+                render_thread(&mut html, thread);
+            }
+
+            html.push_str("</tbody></table>");
+        }
+
+        html.push_str("</section>");
+    }
+
+    html.push_str("</body></html>");
+
+    html
+}
+
+fn render_thread(html: &mut String, thread: &IndexEntry) {
+    let (class, status) = if thread.is_dead {
+        ("dead", "Dead")
+    } else {
+        ("alive", "Alive")
+    };
+
+    let _ = write!(html, "<tr class=\"thread {class}\"><td>");
+    let _ = write!(
+        html,
+        "<a class=\"subject\" href=\"{}\">{}</a>",
+        escape(&encode_path(&thread.thread_file)),
+        escape(thread.subject.as_deref().unwrap_or(&thread.url))
+    );
+
+    if let Some(excerpt) = &thread.excerpt {
+        let _ = write!(html, "<p class=\"excerpt\">{}</p>", escape(excerpt));
+    }
+
+    let post_count = thread.post_count.map_or_else(|| "-".to_owned(), |c| c.to_string());
+    let last_update = thread.last_update.map(render_time).unwrap_or_default();
+
+    let _ = write!(
+        html,
+        "</td><td>{post_count}</td><td>{}</td><td>{last_update}</td><td class=\"status\">{status}</td></tr>",
+        thread.file_count
+    );
+}
+
+fn render_time(time: DateTime<Utc>) -> String {
+    format!(
+        "<time datetime=\"{}\">{}</time>",
+        time.to_rfc3339_opts(SecondsFormat::Secs, true),
+        time.format("%Y-%m-%d %H:%M UTC")
+    )
+}
+
+/// Encode characters in a path that have a special meaning in URLs.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+
+    for c in path.chars() {
+        match c {
+            '%' => encoded.push_str("%25"),
+            '#' => encoded.push_str("%23"),
+            '?' => encoded.push_str("%3F"),
+            ' ' => encoded.push_str("%20"),
+            _ => encoded.push(c),
+        }
+    }
+
+    encoded
+}
@@ -1,11 +1,16 @@
+pub mod catalog;
 pub mod config;
+pub mod download;
 pub mod error;
 mod html;
+pub mod index;
 pub mod misc;
 pub mod project;
+pub mod threadapi;
 pub mod threadparser;
 pub mod threadupdater;
 pub mod ui;
 pub mod util;
+pub mod warc;
 
 pub use self::error::*;
@@ -5,6 +5,7 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 
 use crate::error::*;
+use crate::threadapi::ThreadApi;
 use crate::threadupdater::ParserType;
 
 static REGEX_SPLIT_URL: Lazy<Regex> =
@@ -14,6 +15,7 @@ pub struct SiteInfo {
     pub name: String,
     pub parser: ParserType,
     pub path: PathBuf,
+    pub api: Option<ThreadApi>,
 }
 
 pub trait SiteResolver {
@@ -38,6 +40,7 @@ pub fn unknown_site(url: &str) -> Result<SiteInfo, ChandlerError> {
         name: "unknown".to_owned(),
         parser: ParserType::Basic,
         path,
+        api: None,
     })
 }
 
@@ -1,213 +1,145 @@
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
-use once_cell::sync::Lazy;
-use tracing::error;
-use tracing::info;
+use tracing::{error, warn};
+use url::Url;
 
+use crate::download::*;
 use crate::error::*;
 use crate::project::ProjectState;
 use crate::ui::*;
 use crate::util;
 
-const BUF_SIZE: usize = 65535;
-
-static USER_AGENT: Lazy<String> = Lazy::new(|| {
-    let os = os_info::get();
-
-    format!(
-        "Mozilla/5.0 ({} {}; {}) Chandler/{}",
-        os.os_type(),
-        os.version(),
-        os.bitness(),
-        env!("CARGO_PKG_VERSION")
-    )
-});
-
-#[derive(Debug)]
-pub enum DownloadResult {
-    Success { last_modified: Option<DateTime<Utc>> },
-    NotModified,
-    NotFound,
-    OtherHttpError { status_code: u16, description: String },
-}
-
-pub fn download_file(
-    url: &str,
-    path: &Path,
-    if_modified_since: Option<DateTime<Utc>>,
-    ui_handler: &mut dyn ChandlerUiHandler,
-) -> Result<DownloadResult, ChandlerError> {
-    info!("Download starting: '{}' (to '{}')", url, path.display());
-
-    ui_handler.event(&UiEvent::DownloadFileStart {
-        url: url.to_owned(),
-        destination: path.to_path_buf(),
-    });
-
-    let result = (|| {
-        let client = reqwest::blocking::Client::builder()
-            .user_agent(&*USER_AGENT)
-            .gzip(true)
-            .build()
-            .map_err(|err| ChandlerError::Other(format!("Error building HTTP client: {err}").into()))?;
-
-        // Download the thread HTML.
-        let mut request = client.get(url);
-
-        // If specified, add If-Modified-Since header.
-        if let Some(if_modified_since) = if_modified_since {
-            request = request.header(reqwest::header::IF_MODIFIED_SINCE, &if_modified_since.to_rfc2822());
-        }
-
-        // Send request and get response.
-        let mut response = request
-            .send()
-            .map_err(|err| ChandlerError::Download(DownloadError::Network(err.to_string().into())))?;
-
-        let status = response.status();
-
-        if !status.is_success() {
-            let status_code: u16 = status.into();
-
-            info!("Download failed: '{}' (status code: {})", url, status_code);
-
-            return match status_code {
-                304 => Ok(DownloadResult::NotModified),
-                404 => Ok(DownloadResult::NotFound),
-                _ => Ok(DownloadResult::OtherHttpError {
-                    status_code,
-                    description: status.to_string(),
-                }),
-            };
-        }
-
-        ui_handler.event(&UiEvent::DownloadFileInfo {
-            size: response.content_length(),
-        });
-
-        ui_handler.event(&UiEvent::DownloadFileProgress { bytes_downloaded: 0 });
-
-        // Create file for writing.
-        let mut file = util::create_file(path).map_err(ChandlerError::CreateFile)?;
-
-        let mut bytes_downloaded: usize = 0;
-
-        // Copy response content to file.
-        'copy: loop {
-            use std::io::{Read, Write};
-
-            let mut buf: [u8; BUF_SIZE] = [0; BUF_SIZE];
-
-            match response.read(&mut buf) {
-                Ok(bytes_read) => {
-                    if bytes_read == 0 {
-                        break 'copy;
-                    }
-                    bytes_downloaded += bytes_read;
-
-                    ui_handler.event(&UiEvent::DownloadFileProgress {
-                        bytes_downloaded: bytes_downloaded as u64,
-                    });
-
-                    file.write(&buf[..bytes_read]).map_err(ChandlerError::WriteFile)?;
-                }
-                Err(err) => return Err(ChandlerError::Download(DownloadError::Other(err.to_string().into()))),
-            }
-        }
+use super::*;
 
-        let last_modified: Option<DateTime<Utc>> =
-            if let Some(value) = response.headers().get(reqwest::header::LAST_MODIFIED) {
-                if let Ok(value_str) = value.to_str() {
-                    let last_modified = DateTime::parse_from_rfc2822(value_str)
-                        .map_err(|err| ChandlerError::Download(DownloadError::Other(err.to_string().into())))?;
-
-                    Some(last_modified.into())
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
-
-        info!("Download completed: '{}'", url);
-        Ok(DownloadResult::Success { last_modified })
-    })();
-
-    // Report download complete progress event.
-    match result {
-        Ok(_) => ui_handler.event(&UiEvent::DownloadFileComplete(DownloadFileCompleteResult::Success)),
-        Err(_) => ui_handler.event(&UiEvent::DownloadFileComplete(DownloadFileCompleteResult::Error)),
-    };
-
-    result
-}
+/// Maximum number of levels of stylesheets referencing other stylesheets to follow in one pass.
+const MAX_STYLESHEET_DEPTH: u32 = 5;
 
 /// Download all links for this project.
 pub fn download_linked_content(
     state: &mut ProjectState,
     ui_handler: &mut dyn ChandlerUiHandler,
 ) -> Result<(), ChandlerError> {
-    state.new_links.append(&mut state.failed_links);
+    let now = Utc::now();
 
-    // Report download start.
-    ui_handler.event(&UiEvent::DownloadStart {
-        file_count: state.new_links.len() as u32,
-    });
+    // Retry failed links that are due for another attempt.
+    // Links that have failed permanently are left alone.
+    let (mut due_links, not_due_links): (Vec<_>, Vec<_>) =
+        state.failed_links.drain(..).partition(|l| l.retry.is_due(now));
 
-    let mut files_processed: u32 = 0;
-    let mut files_downloaded: u32 = 0;
-    let mut files_failed: u32 = 0;
+    state.failed_links = not_due_links;
+    state.new_links.append(&mut due_links);
 
-    let download_path = &state.root_path;
-    let new_links = &mut state.new_links;
-    let failed_links = &mut state.failed_links;
+    // Files referenced from downloaded stylesheets are downloaded in the same pass,
+    // which may in turn reference more files.
+    let mut depth = 0;
 
     loop {
-        // If cancellation has been requested, break out immediately.
-        if ui_handler.is_cancelled() {
-            break;
-        }
+        download_new_links(state, ui_handler)?;
 
-        if new_links.is_empty() {
+        if state.new_links.is_empty() || ui_handler.is_cancelled() {
             break;
         }
 
-        let link_info = new_links.remove(0);
+        depth += 1;
 
-        let url = &link_info.url;
-        let path = download_path.join(&link_info.path);
+        if depth > MAX_STYLESHEET_DEPTH {
+            warn!(
+                "Stylesheets are nested more than {MAX_STYLESHEET_DEPTH} levels deep. Skipping {} files.",
+                state.new_links.len()
+            );
 
-        if let Some(parent_path) = path.parent() {
-            fs::create_dir_all(parent_path).map_err(|err| {
-                ChandlerError::Other(format!("Error creating path: {}: {err}", parent_path.display()).into())
-            })?;
+            state.new_links.clear();
+            break;
         }
+    }
+
+    Ok(())
+}
 
-        let mut if_modified_since: Option<DateTime<Utc>> = None;
+/// Download all new links, queueing files referenced from any stylesheets among them.
+fn download_new_links(state: &mut ProjectState, ui_handler: &mut dyn ChandlerUiHandler) -> Result<(), ChandlerError> {
+    let download_path = &state.root_path;
 
-        // If the file already exists, try to get its modification time
-        // so that we can pass it to the request's If-Modified-Since header.
-        if path.exists() {
-            if let Ok(m) = fs::metadata(&path) {
-                if let Ok(st) = m.modified() {
-                    if_modified_since = Some(st.into());
+    let jobs: Vec<DownloadJob<_>> = state
+        .new_links
+        .drain(..)
+        .map(|link_info| {
+            let url = link_info.url.clone();
+            let path = download_path.join(&link_info.path);
+
+            let mut if_modified_since: Option<DateTime<Utc>> = None;
+
+            // If the file already exists, try to get its modification time
+            // so that we can pass it to the request's If-Modified-Since header.
+            if path.exists() {
+                if let Ok(m) = fs::metadata(&path) {
+                    if let Ok(st) = m.modified() {
+                        if_modified_since = Some(st.into());
+                    }
                 }
             }
-        }
 
-        let success = match download_file(url, &path, if_modified_since, ui_handler) {
+            DownloadJob {
+                tag: link_info,
+                url,
+                path,
+                if_modified_since,
+            }
+        })
+        .collect();
+
+    let result = state.download_engine.download_all(jobs, ui_handler);
+
+    // Keep a reference to the download engine, as stylesheets are processed while its retry policy is in use.
+    let download_engine = Arc::clone(&state.download_engine);
+    let retry_policy = download_engine.retry_policy();
+
+    let mut stylesheet_links: Vec<LinkInfo> = Vec::new();
+
+    for (mut link_info, result) in result.completed {
+        let success = match &result {
             Ok(r) => match r {
-                DownloadResult::Success { .. } => true,
-                DownloadResult::NotModified => true,
+                DownloadResult::Success { .. } | DownloadResult::NotModified => {
+                    // Replace URLs in newly downloaded stylesheets.
+                    let stylesheet_result = match r {
+                        DownloadResult::Success { .. } if link_info.path.ends_with(".css") => {
+                            match process_stylesheet(state, &link_info, r) {
+                                Ok((result, mut links)) => {
+                                    stylesheet_links.append(&mut links);
+                                    result
+                                }
+                                Err(err) => {
+                                    error!("Error processing stylesheet {}: {}", link_info.url, err);
+                                    None
+                                }
+                            }
+                        }
+                        _ => None,
+                    };
+
+                    // Record the downloaded file in the manifest.
+                    if let Err(err) = state.manifest.record_success(
+                        &state.root_path,
+                        &link_info.url,
+                        &link_info.path,
+                        stylesheet_result.as_ref().unwrap_or(r),
+                    ) {
+                        error!("Error updating manifest for {}: {}", link_info.url, err);
+                    }
+
+                    true
+                }
                 DownloadResult::NotFound => {
-                    error!("File not found: {}", url);
+                    error!("File not found: {}", link_info.url);
                     false
                 }
                 DownloadResult::OtherHttpError {
                     status_code,
                     description,
+                    ..
                 } => {
                     error!("Server returned HTTP error: {} {}", status_code, description);
                     false
@@ -219,24 +151,147 @@ pub fn download_linked_content(
             }
         };
 
-        files_processed += 1;
+        if !success {
+            retry_policy.record_failure(&mut link_info.retry, &result, Utc::now());
 
-        if success {
-            files_downloaded += 1;
-        } else {
-            failed_links.push(link_info);
-            files_failed += 1;
-        }
+            if link_info.retry.permanent {
+                error!(
+                    "Giving up on link after {} attempts: {}",
+                    link_info.retry.attempts, link_info.url
+                );
+            }
+
+            state
+                .manifest
+                .record_failure(&link_info.url, &link_info.path, link_info.retry.permanent);
 
-        // Report download progress.
-        ui_handler.event(&UiEvent::DownloadProgress { files_processed });
+            state.failed_links.push(link_info);
+        }
     }
 
-    // Report download complete.
-    ui_handler.event(&UiEvent::DownloadComplete {
-        files_downloaded,
-        files_failed,
-    });
+    // Put links that were never attempted due to cancellation back in the queue.
+    state.new_links = result.cancelled;
+    state.new_links.append(&mut stylesheet_links);
 
     Ok(())
 }
+
+/// Replace URLs referenced in a downloaded stylesheet with local paths, and get the files to download.
+/// If the file was changed, a download result with its new size and hash is returned.
+fn process_stylesheet(
+    state: &mut ProjectState,
+    link_info: &LinkInfo,
+    result: &DownloadResult,
+) -> Result<(Option<DownloadResult>, Vec<LinkInfo>), ChandlerError> {
+    let file_path = state.root_path.join(&link_info.path);
+
+    let css = fs::read_to_string(&file_path).map_err(ChandlerError::ReadFile)?;
+
+    let css_url = Url::parse(&link_info.url)
+        .map_err(|err| ChandlerError::Other(format!("Error parsing stylesheet URL: {err}").into()))?;
+
+    // Local paths are relative to the stylesheet's own directory.
+    let from_dir = Path::new(&link_info.path).parent().unwrap_or(Path::new(""));
+
+    let (new_css, links) = process_css(state, &css, &css_url, from_dir)?;
+
+    if new_css == css {
+        return Ok((None, links));
+    }
+
+    fs::write(&file_path, &new_css).map_err(ChandlerError::WriteFile)?;
+
+    let DownloadResult::Success {
+        last_modified, etag, ..
+    } = result
+    else {
+        return Ok((None, links));
+    };
+
+    let result = DownloadResult::Success {
+        last_modified: *last_modified,
+        etag: etag.clone(),
+        size: new_css.len() as u64,
+        sha256: util::hash_file(&file_path).map_err(ChandlerError::OpenFile)?,
+    };
+
+    Ok((Some(result), links))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::{test_dir, TestResponse, TestServer};
+    use crate::project::test_project;
+    use crate::threadupdater::ParserType;
+
+    const CSS_THREAD_HTML: &str = r#"<html><head><link href="/css/style.css" rel="stylesheet"><style>.x { background: url('/img/x.png'); }</style></head><body><div class="thread"><div class="post op" id="op_1"></div><div class="post reply" id="reply_2" style="background: url(/img/bg.png)"></div></div></body></html>"#;
+
+    const STYLE_CSS: &str = r#"@import "fonts.css"; body { background: url(../img/body.png); }"#;
+
+    #[test]
+    fn downloads_css_resources() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/css/style.css" => TestResponse::ok(STYLE_CSS.as_bytes()),
+            "/css/fonts.css" => TestResponse::ok(b"@font-face { src: url(font.woff2); }"),
+            path if path.starts_with("/img/") || path.ends_with(".woff2") => TestResponse::ok(b"file"),
+            _ => TestResponse::ok(CSS_THREAD_HTML.as_bytes()),
+        });
+
+        let path = test_dir("css_resources");
+        let project = test_project(&server, &path, Some(ParserType::Tinyboard));
+
+        let content_path = path.join("content").join("127.0.0.1");
+        for file in [
+            "img/x.png",
+            "img/bg.png",
+            "img/body.png",
+            "css/fonts.css",
+            "css/font.woff2",
+        ] {
+            assert!(content_path.join(file).exists(), "{file} was not downloaded");
+        }
+
+        let html = fs::read_to_string(project.get_thread_file_path()).unwrap();
+        assert!(html.contains(r#"url(&quot;content/127.0.0.1/img/bg.png&quot;)"#));
+        assert!(html.contains(r#".x { background: url("content/127.0.0.1/img/x.png"); }"#));
+
+        let css = fs::read_to_string(content_path.join("css/style.css")).unwrap();
+        assert_eq!(
+            css,
+            r#"@import "fonts.css"; body { background: url("../img/body.png"); }"#
+        );
+
+        // The manifest should match the rewritten stylesheet.
+        let entry = project.get_manifest().get(&server.url("/css/style.css")).unwrap();
+        assert!(entry.verify(&path).unwrap());
+    }
+
+    #[test]
+    fn limits_stylesheet_depth() {
+        let server = TestServer::start(|request| {
+            match request.path.strip_prefix("/css/") {
+            // Every stylesheet imports another one, and references a file that should not be downloaded.
+            Some(name) => {
+                let n: u32 = name.trim_end_matches(".css").parse().unwrap();
+                TestResponse::ok(format!(r#"@import "{}.css"; body {{ background: url(x.php); }}"#, n + 1).as_bytes())
+            }
+            None => TestResponse::ok(
+                br#"<html><head><link href="/css/0.css" rel="stylesheet"></head><body><div class="thread"><div class="post op" id="op_1"></div></div></body></html>"#,
+            ),
+        }
+        });
+
+        let path = test_dir("css_depth");
+        test_project(&server, &path, Some(ParserType::Tinyboard));
+
+        let css_path = path.join("content").join("127.0.0.1").join("css");
+        assert!(css_path.join(format!("{MAX_STYLESHEET_DEPTH}.css")).exists());
+        assert!(!css_path.join(format!("{}.css", MAX_STYLESHEET_DEPTH + 1)).exists());
+        assert!(!css_path.join("x.php").exists());
+
+        // Links to files that are not downloaded are left alone.
+        let css = fs::read_to_string(css_path.join("0.css")).unwrap();
+        assert!(css.contains("url(x.php)"));
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use base64::Engine;
+use url::Url;
+
+use crate::error::*;
+use crate::html::{self, LinkTag};
+use crate::project::ProjectState;
+use crate::util;
+use crate::warc;
+
+/// Options for exporting a thread as a single self-contained HTML file.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HtmlExportOptions {
+    /// Only embed files that are displayed on the page, such as thumbnails and stylesheets.
+    /// Files that are only linked to, such as full-size images and videos, link to their original URLs instead.
+    pub thumbnails_only: bool,
+
+    /// Maximum size of the exported file in bytes.
+    pub max_size: Option<u64>,
+}
+
+/// Write the thread to a single HTML file, with all downloaded files it references embedded as data URIs.
+/// Links to files that are not embedded are restored to their original URLs.
+pub fn export_html(state: &ProjectState, file_path: &Path, options: HtmlExportOptions) -> Result<(), ChandlerError> {
+    let document = html::parse_file(&state.thread_file_path)?;
+
+    let thread_url = Url::parse(&state.thread_url)
+        .map_err(|err| ChandlerError::Other(format!("Error parsing thread URL: {err}").into()))?;
+
+    let mut embedder = Embedder {
+        root_path: &state.root_path,
+        embedded: HashMap::new(),
+        max_size: options.max_size,
+        size: 0,
+    };
+
+    for mut link in html::find_links(document.clone()) {
+        // Only links that were replaced with local paths can refer to downloaded files.
+        if !link.is_replaced() {
+            continue;
+        }
+
+        if let Some(css) = link.css() {
+            let new_css = embedder.embed_css(&css, "")?;
+            link.replace(&new_css);
+
+            continue;
+        }
+
+        let embed = !options.thumbnails_only
+            || matches!(link.tag(), LinkTag::Img | LinkTag::Link)
+            || &**link.attr_name() == "poster";
+
+        // Get the original URL of each local path in the link.
+        let original_urls: HashMap<String, String> = link
+            .urls()
+            .into_iter()
+            .zip(link.original_urls())
+            .filter_map(|(url, original_url)| Some((url, thread_url.join(&original_url).ok()?.into())))
+            .collect();
+
+        let mut result = Ok(());
+
+        link.replace_urls(|url| {
+            let path = resolve_local_path("", url)?;
+
+            let data_uri = if embed {
+                embedder.embed_file(&path).unwrap_or_else(|err| {
+                    result = Err(err);
+                    None
+                })
+            } else {
+                None
+            };
+
+            // Files that are not embedded, or were never downloaded, link to their original URL.
+            data_uri.or_else(|| original_urls.get(url).cloned())
+        });
+
+        result?;
+    }
+
+    let mut data = Vec::new();
+    html5ever::serialize(&mut data, &document, Default::default()).map_err(ChandlerError::WriteFile)?;
+
+    embedder.check_size(data.len() as u64)?;
+
+    let mut file = util::create_file(file_path).map_err(ChandlerError::CreateFile)?;
+    std::io::Write::write_all(&mut file, &data).map_err(ChandlerError::WriteFile)?;
+
+    Ok(())
+}
+
+struct Embedder<'a> {
+    root_path: &'a Path,
+
+    /// Data URIs of files that have already been embedded, by their path.
+    embedded: HashMap<String, String>,
+
+    max_size: Option<u64>,
+
+    /// Total size of all data URIs created so far.
+    size: u64,
+}
+
+impl Embedder<'_> {
+    /// Get a data URI for a downloaded file, or None if it has not been downloaded.
+    fn embed_file(&mut self, path: &str) -> Result<Option<String>, ChandlerError> {
+        if let Some(data_uri) = self.embedded.get(path) {
+            return Ok(Some(data_uri.clone()));
+        }
+
+        let file_path = self.root_path.join(path);
+
+        if !file_path.is_file() {
+            return Ok(None);
+        }
+        let content_type = warc::guess_content_type(&file_path);
+
+        let data = if content_type == "text/css" {
+            // Files referenced by stylesheets are relative to the stylesheet, and must be embedded as well.
+            let css = fs::read_to_string(&file_path).map_err(ChandlerError::ReadFile)?;
+            let from_dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+            self.embed_css(&css, from_dir)?.into_bytes()
+        } else {
+            fs::read(&file_path).map_err(ChandlerError::ReadFile)?
+        };
+
+        let data_uri = format!(
+            "data:{};base64,{}",
+            content_type,
+            base64::engine::general_purpose::STANDARD.encode(data)
+        );
+
+        self.size += data_uri.len() as u64;
+        self.check_size(self.size)?;
+
+        self.embedded.insert(path.to_owned(), data_uri.clone());
+
+        Ok(Some(data_uri))
+    }
+
+    /// Embed all downloaded files referenced in CSS.
+    /// `from_dir` is the directory URLs are relative to, relative to the project.
+    fn embed_css(&mut self, css: &str, from_dir: &str) -> Result<String, ChandlerError> {
+        html::replace_css_urls(css, |url| match resolve_local_path(from_dir, url) {
+            Some(path) => self.embed_file(&path),
+            None => Ok(None),
+        })
+    }
+
+    fn check_size(&self, size: u64) -> Result<(), ChandlerError> {
+        match self.max_size {
+            Some(max_size) if size > max_size => Err(ChandlerError::Other(
+                format!(
+                    "Exported HTML exceeds the maximum size of {} bytes. \
+                    Try embedding only thumbnails, or increasing the maximum size.",
+                    max_size
+                )
+                .into(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Resolve a local link relative to a directory in the project, into a path relative to the project.
+/// Returns None if the link is not a local path.
+fn resolve_local_path(from_dir: &str, link: &str) -> Option<String> {
+    // Remove any query or fragment.
+    let link = link.split(['?', '#']).next()?;
+
+    if link.is_empty() || link.starts_with('/') || link.contains(':') {
+        return None;
+    }
+
+    let mut components: Vec<&str> = from_dir.split('/').filter(|c| !c.is_empty()).collect();
+
+    for component in link.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            c => components.push(c),
+        }
+    }
+
+    Some(components.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::{test_dir, TestResponse, TestServer, TestUiHandler};
+    use crate::project::{self, test_project, ProjectFormat, UnknownSiteResolver, TEST_THREAD_HTML};
+    use crate::threadupdater::ParserType;
+
+    #[test]
+    fn can_resolve_local_path() {
+        assert_eq!(
+            resolve_local_path("", "content/a.com/1.png"),
+            Some("content/a.com/1.png".to_owned())
+        );
+        assert_eq!(
+            resolve_local_path("content/a.com/css", "../img/bg.png?v=1"),
+            Some("content/a.com/img/bg.png".to_owned())
+        );
+        assert_eq!(resolve_local_path("", "../outside.png"), None);
+        assert_eq!(resolve_local_path("", "#p1"), None);
+        assert_eq!(resolve_local_path("", "https://a.com/1.png"), None);
+    }
+
+    #[test]
+    fn can_export_single_file_html() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/b/src/2.png" => TestResponse::ok(b"file"),
+            _ => TestResponse::ok(TEST_THREAD_HTML.as_bytes()),
+        });
+
+        let path = test_dir("export_html");
+        let project = test_project(&server, &path, Some(ParserType::Tinyboard));
+
+        let export_path = path.join("export.html");
+
+        project
+            .export_html(&export_path, &HtmlExportOptions::default())
+            .unwrap();
+
+        let exported = fs::read_to_string(&export_path).unwrap();
+        assert!(exported.contains(r#"href="data:image/png;base64,ZmlsZQ==""#));
+
+        // Full-size files should link to their original URLs.
+        let options = HtmlExportOptions {
+            thumbnails_only: true,
+            ..Default::default()
+        };
+
+        project.export_html(&export_path, &options).unwrap();
+
+        let exported = fs::read_to_string(&export_path).unwrap();
+        assert!(exported.contains(&format!(r#"href="{}""#, server.url("/b/src/2.png"))));
+
+        let options = HtmlExportOptions {
+            max_size: Some(10),
+            ..Default::default()
+        };
+
+        assert!(project.export_html(&export_path, &options).is_err());
+    }
+
+    #[test]
+    fn can_export_single_file_html_from_v2_project() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/b/src/2.png" => TestResponse::ok(b"file"),
+            _ => TestResponse::ok(TEST_THREAD_HTML.as_bytes()),
+        });
+
+        let path = test_dir("export_html_v2");
+
+        // V2 projects have no manifest, so files are found on disk.
+        let mut project = project::builder()
+            .url(&server.url("/b/res/1.html"))
+            .path(Some(&path))
+            .format(Some(ProjectFormat::V2))
+            .parser(Some(ParserType::Tinyboard))
+            .site_resolver(Some(Box::new(UnknownSiteResolver)))
+            .load_or_create()
+            .unwrap();
+
+        project.update(&mut TestUiHandler::default()).unwrap();
+
+        let export_path = path.join("export.html");
+
+        project
+            .export_html(&export_path, &HtmlExportOptions::default())
+            .unwrap();
+
+        let exported = fs::read_to_string(&export_path).unwrap();
+        assert!(exported.contains(r#"href="data:image/png;base64,ZmlsZQ==""#));
+
+        let options = HtmlExportOptions {
+            thumbnails_only: true,
+            ..Default::default()
+        };
+
+        project.export_html(&export_path, &options).unwrap();
+
+        let exported = fs::read_to_string(&export_path).unwrap();
+        assert!(exported.contains(&format!(r#"href="{}""#, server.url("/b/src/2.png"))));
+    }
+}
@@ -0,0 +1,262 @@
+use std::fs;
+use std::path::Path;
+
+use serde_derive::{Deserialize, Serialize};
+use url::Url;
+
+use crate::error::*;
+use crate::project::ProjectState;
+use crate::threadparser::{Post, PostFile};
+use crate::util;
+
+/// Version of the JSON export format.
+/// It is increased whenever a change is made that existing consumers may not handle.
+pub const JSON_EXPORT_VERSION: u32 = 1;
+
+/// JSON export of a thread.
+/// Post fields are named after their equivalents in the 4chan API.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JsonExport {
+    /// Version of the export format.
+    pub version: u32,
+
+    /// URL of the thread.
+    pub url: String,
+
+    /// All posts in the thread, starting with the OP.
+    pub posts: Vec<JsonExportPost>,
+
+    /// HTML of the whole thread, if its parser cannot identify posts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JsonExportPost {
+    /// Post number.
+    pub no: u32,
+
+    /// Poster's name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Poster's tripcode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trip: Option<String>,
+
+    /// Poster ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// UNIX timestamp of the post.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<i64>,
+
+    /// Subject.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+
+    /// Comment, as HTML.
+    pub com: String,
+
+    /// Comment, as plain text.
+    pub text: String,
+
+    /// Files attached to the post.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<JsonExportFile>,
+
+    /// The post has been deleted upstream.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deleted: bool,
+
+    /// The post has been edited upstream.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub edited: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JsonExportFile {
+    /// Original filename, without the extension.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+
+    /// Extension of the original filename, including the dot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ext: Option<String>,
+
+    /// File size in bytes, as shown on the site.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fsize: Option<u64>,
+
+    /// Width.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub w: Option<u32>,
+
+    /// Height.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub h: Option<u32>,
+
+    /// URL of the file.
+    pub url: String,
+
+    /// Local path to the file, relative to the project, if it has been downloaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// URL of the thumbnail.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+
+    /// Local path to the thumbnail, relative to the project, if it has been downloaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_path: Option<String>,
+}
+
+/// Create a JSON export of the thread.
+/// If no posts can be identified, the whole thread HTML is included instead.
+pub fn create_json_export(state: &ProjectState) -> Result<JsonExport, ChandlerError> {
+    let thread_url = Url::parse(&state.thread_url)
+        .map_err(|err| ChandlerError::Other(format!("Error parsing thread URL: {}", err).into()))?;
+
+    let posts: Vec<JsonExportPost> = state
+        .thread
+        .as_ref()
+        .map_or_else(|| Ok(Vec::new()), |thread| thread.posts())?
+        .into_iter()
+        .map(|post| JsonExportPost::from_post(post, &thread_url))
+        .collect();
+
+    let document = if posts.is_empty() {
+        Some(fs::read_to_string(&state.thread_file_path).map_err(ChandlerError::ReadFile)?)
+    } else {
+        None
+    };
+
+    Ok(JsonExport {
+        version: JSON_EXPORT_VERSION,
+        url: state.thread_url.clone(),
+        posts,
+        document,
+    })
+}
+
+/// Write a JSON export of the thread to a file.
+pub fn export_json(state: &ProjectState, file_path: &Path) -> Result<(), ChandlerError> {
+    let export = create_json_export(state)?;
+
+    let file = util::create_file(file_path).map_err(ChandlerError::CreateFile)?;
+    serde_json::to_writer_pretty(file, &export)
+        .map_err(|err| ChandlerError::Other(format!("Error writing JSON export: {}", err).into()))?;
+
+    Ok(())
+}
+
+impl JsonExportPost {
+    fn from_post(post: Post, thread_url: &Url) -> Self {
+        Self {
+            no: post.number,
+            name: post.name,
+            trip: post.tripcode,
+            id: post.poster_id,
+            time: post.time.map(|time| time.timestamp()),
+            sub: post.subject,
+            com: post.comment_html,
+            text: post.comment_text,
+            files: post
+                .files
+                .into_iter()
+                .map(|file| JsonExportFile::from_post_file(file, thread_url))
+                .collect(),
+            deleted: post.is_deleted,
+            edited: post.is_edited,
+        }
+    }
+}
+
+impl JsonExportFile {
+    fn from_post_file(file: PostFile, thread_url: &Url) -> Self {
+        // URLs are made absolute, as they may be relative to the thread.
+        let absolute = |url: String| thread_url.join(&url).map_or(url, |url| url.to_string());
+
+        let (filename, ext) = match file.original_name {
+            Some(name) => match name.rsplit_once('.') {
+                Some((filename, ext)) if !filename.is_empty() => (Some(filename.to_owned()), Some(format!(".{ext}"))),
+                _ => (Some(name), None),
+            },
+            None => (None, None),
+        };
+
+        Self {
+            filename,
+            ext,
+            fsize: file.size,
+            w: file.width,
+            h: file.height,
+            url: absolute(file.url),
+            path: file.path,
+            thumbnail_url: file.thumbnail_url.map(absolute),
+            thumbnail_path: file.thumbnail_path,
+        }
+    }
+}
+
+fn is_false(v: &bool) -> bool {
+    !v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::{test_dir, TestResponse, TestServer};
+    use crate::project::{test_project, TEST_THREAD_HTML};
+    use crate::threadupdater::ParserType;
+
+    #[test]
+    fn can_export_json() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/b/src/2.png" => TestResponse::ok(b"file"),
+            _ => TestResponse::ok(TEST_THREAD_HTML.as_bytes()),
+        });
+
+        let path = test_dir("export_json");
+        let project = test_project(&server, &path, Some(ParserType::Tinyboard));
+
+        let export_path = path.join("export.json");
+        project.export_json(&export_path).unwrap();
+
+        let export: JsonExport = serde_json::from_str(&fs::read_to_string(&export_path).unwrap()).unwrap();
+        assert_eq!(export.posts.len(), 2);
+        assert!(export.document.is_none());
+
+        let op = &export.posts[0];
+        assert_eq!(op.no, 1);
+        assert_eq!(op.sub.as_deref(), Some("Cats"));
+        assert_eq!(op.time, Some(1704067200));
+        assert_eq!(op.text, "Post cats");
+
+        let file = &export.posts[1].files[0];
+        assert_eq!(file.filename.as_deref(), Some("cat"));
+        assert_eq!(file.ext.as_deref(), Some(".png"));
+        assert_eq!(file.fsize, Some(4));
+        assert_eq!((file.w, file.h), (Some(1), Some(2)));
+        assert_eq!(file.url, server.url("/b/src/2.png"));
+        assert_eq!(file.path.as_deref(), Some("content/127.0.0.1/b/src/2.png"));
+        assert!(path.join(file.path.as_deref().unwrap()).exists());
+    }
+
+    #[test]
+    fn exports_basic_thread_as_document() {
+        let server = TestServer::start(|_| TestResponse::ok(b"<html><body>Hello</body></html>"));
+
+        let path = test_dir("export_json_basic");
+        let project = test_project(&server, &path, Some(ParserType::Basic));
+
+        let export_path = path.join("export.json");
+        project.export_json(&export_path).unwrap();
+
+        let export: JsonExport = serde_json::from_str(&fs::read_to_string(&export_path).unwrap()).unwrap();
+        assert!(export.posts.is_empty());
+        assert!(export.document.unwrap().contains("Hello"));
+    }
+}
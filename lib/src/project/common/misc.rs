@@ -2,7 +2,9 @@ use std::ffi::OsStr;
 use std::io;
 use std::path::{Path, PathBuf};
 
+use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
+use url::Url;
 
 static HTML_EXTENSION: Lazy<&'static OsStr> = Lazy::new(|| OsStr::new("html"));
 
@@ -30,3 +32,28 @@ pub fn get_html_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
 
     Ok(files)
 }
+
+/// Get the latest modification time of any of the files that exist.
+pub fn latest_modified_time(paths: &[PathBuf]) -> Option<DateTime<Utc>> {
+    paths
+        .iter()
+        .filter_map(|path| path.metadata().and_then(|m| m.modified()).ok())
+        .max()
+        .map(DateTime::from)
+}
+
+/// Get the URL of a content file from its path in the form "host/path".
+/// Files from the same host as the thread use the thread's scheme and port.
+pub fn content_url(thread_url: &Url, path: &str) -> Option<String> {
+    let (host, path) = path.split_once('/')?;
+
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+
+    if thread_url.host_str() == Some(host) {
+        thread_url.join(&format!("/{path}")).ok().map(String::from)
+    } else {
+        Some(format!("{}://{host}/{path}", thread_url.scheme()))
+    }
+}
@@ -1,13 +1,23 @@
 mod download;
+mod export_html;
+mod export_json;
 mod misc;
 mod pidlock;
+mod posts;
 mod process;
 mod rebuild;
+mod thread_links;
 mod update;
+mod warc;
 
 pub use self::download::*;
+pub use self::export_html::*;
+pub use self::export_json::*;
 pub use self::misc::*;
 pub use self::pidlock::*;
+pub use self::posts::*;
 pub use self::process::*;
 pub use self::rebuild::*;
+pub use self::thread_links::*;
 pub use self::update::*;
+pub use self::warc::*;
@@ -0,0 +1,25 @@
+use std::path::Path;
+
+use crate::config::chandler::UpdateConfig;
+use crate::error::*;
+use crate::project::ProjectState;
+use crate::threadparser::Post;
+use crate::threadupdater::{CreateThreadUpdater, ParserType};
+
+/// Get all posts in the thread, starting with the OP.
+/// If the thread has not been downloaded yet, there are none.
+pub fn get_posts(state: &ProjectState) -> Result<Vec<Post>, ChandlerError> {
+    state
+        .thread
+        .as_ref()
+        .map_or_else(|| Ok(Vec::new()), |thread| thread.posts())
+}
+
+/// Read all posts in a thread file, starting with the OP, without loading its project.
+/// If the thread has not been downloaded yet, or cannot be parsed, there are none.
+pub fn read_posts(parser: &ParserType, thread_file_path: &Path) -> Result<Vec<Post>, ChandlerError> {
+    parser
+        .create_thread_updater_from(thread_file_path, UpdateConfig::default())
+        .ok()
+        .map_or_else(|| Ok(Vec::new()), |thread| thread.posts())
+}
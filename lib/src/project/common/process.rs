@@ -1,15 +1,20 @@
+use std::collections::HashMap;
 use std::path::Path;
 
 use url::Url;
 
+use crate::download::RetryState;
 use crate::error::*;
+use crate::html::{self, Link, LinkTag};
 use crate::project::ProjectState;
 use crate::threadupdater::{CreateThreadUpdater, UpdateResult};
+use crate::util;
 
 #[derive(Debug)]
 pub struct LinkInfo {
     pub url: String,
     pub path: String,
+    pub retry: RetryState,
 }
 
 #[derive(Debug)]
@@ -28,7 +33,9 @@ pub fn process_thread(state: &mut ProjectState, new_thread_file_path: &Path) ->
         // Otherwise...
 
         // Parse new thread
-        let mut new_thread = state.parser.create_thread_updater_from(new_thread_file_path)?;
+        let mut new_thread = state
+            .parser
+            .create_thread_updater_from(new_thread_file_path, state.update_config)?;
         let update_result = new_thread.perform_initial_cleanup()?;
 
         (new_thread, update_result)
@@ -44,49 +51,34 @@ pub fn process_thread(state: &mut ProjectState, new_thread_file_path: &Path) ->
 
     // Process new links.
     for link in update_result.new_links.iter_mut() {
-        let link_info = (|| {
-            if let Some(href) = link.file_link() {
-                // Make URL absolute.
-                let absolute_url = thread_url
-                    .join(&href)
-                    .map_err(|err| ChandlerError::Other(format!("Error making URL '{href}' absolute: {err}").into()))?;
-
-                // Make file URL with query and fragment removed.
-                let file_url = {
-                    let mut url = absolute_url.clone();
-                    url.set_query(None);
-                    url.set_fragment(None);
-
-                    url.to_string()
-                };
-
-                if let Some(extension) = file_url.rsplit('.').next() {
-                    if state.download_extensions.contains(extension) {
-                        if let Some(path) = state.link_path_generator.generate_path(&file_url)? {
-                            // Replace invalid filesystem characters in path.
-                            let path = replace_invalid_filesystem_characters(&path);
-
-                            link.replace(&path);
-
-                            return Ok(Some(LinkInfo {
-                                url: absolute_url.into(),
-                                path,
-                            }));
-                        } else {
-                            return Err(ChandlerError::Other(
-                                format!("Could not generate local path for url: {}", &href).into(),
-                            ));
-                        }
-                    }
-                }
-
-                Ok(None)
-            } else {
-                Ok(None)
+        // Links to posts in this thread are replaced with local anchors, and need no further processing.
+        if replace_quote_link(link, &thread_url) {
+            continue;
+        }
+
+        // Replace URLs referenced in inline CSS, and queue the files they reference.
+        if let Some(css) = link.css().filter(|_| !link.is_replaced()) {
+            let (new_css, mut css_links) = process_css(state, &css, &thread_url, Path::new(""))?;
+
+            if new_css != css {
+                link.replace(&new_css);
             }
-        })()?;
 
-        if let Some(link_info) = link_info {
+            new_links.append(&mut css_links);
+
+            continue;
+        }
+
+        // Get local paths for all URLs in the link that are files to download.
+        let mut replacements: HashMap<String, String> = HashMap::new();
+
+        for href in link.file_links() {
+            let Some(link_info) = file_link_info(state, &thread_url, &href)? else {
+                continue;
+            };
+
+            replacements.insert(href, link_info.path.clone());
+
             // If link has already been seen before, there is no need to download it again.
             if state.seen_links.contains(&link_info.url) {
                 continue;
@@ -95,6 +87,10 @@ pub fn process_thread(state: &mut ProjectState, new_thread_file_path: &Path) ->
             state.seen_links.insert(link_info.url.clone());
             new_links.push(link_info);
         }
+
+        if !replacements.is_empty() {
+            link.replace_urls(|url| replacements.get(url).cloned());
+        }
     }
 
     let new_file_count = new_links.len() as u32;
@@ -107,7 +103,208 @@ pub fn process_thread(state: &mut ProjectState, new_thread_file_path: &Path) ->
     })
 }
 
+/// Get the file to download for a URL in a link, if it has one of the extensions to download.
+fn file_link_info(state: &ProjectState, thread_url: &Url, href: &str) -> Result<Option<LinkInfo>, ChandlerError> {
+    // Make URL absolute.
+    let absolute_url = thread_url
+        .join(href)
+        .map_err(|err| ChandlerError::Other(format!("Error making URL '{href}' absolute: {err}").into()))?;
+
+    // Make file URL with query and fragment removed.
+    let file_url = {
+        let mut url = absolute_url.clone();
+        url.set_query(None);
+        url.set_fragment(None);
+
+        url.to_string()
+    };
+
+    if has_download_extension(state, &file_url) {
+        if let Some(path) = state.link_path_generator.generate_path(&file_url)? {
+            // Replace invalid filesystem characters in path.
+            let path = replace_invalid_filesystem_characters(&path);
+
+            return Ok(Some(LinkInfo {
+                url: absolute_url.into(),
+                path,
+                retry: RetryState::default(),
+            }));
+        } else {
+            return Err(ChandlerError::Other(
+                format!("Could not generate local path for url: {}", href).into(),
+            ));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Check whether a file URL, with query and fragment removed, has one of the extensions to download.
+fn has_download_extension(state: &ProjectState, file_url: &str) -> bool {
+    file_url
+        .rsplit('.')
+        .next()
+        .is_some_and(|extension| state.download_extensions.contains(extension))
+}
+
+/// Replace URLs referenced in CSS with local paths, and get the files to download that have not been seen before.
+/// Local paths are made relative to `from_dir`, which is relative to the project root.
+pub fn process_css(
+    state: &mut ProjectState,
+    css: &str,
+    base_url: &Url,
+    from_dir: &Path,
+) -> Result<(String, Vec<LinkInfo>), ChandlerError> {
+    let mut new_links: Vec<LinkInfo> = Vec::new();
+
+    let new_css = html::replace_css_urls(css, |href| {
+        // Embedded data and references to elements in the document are not files.
+        if href.is_empty() || href.starts_with('#') || href.starts_with("data:") {
+            return Ok(None);
+        }
+
+        let Ok(absolute_url) = base_url.join(href) else {
+            return Ok(None);
+        };
+
+        if !matches!(absolute_url.scheme(), "http" | "https") {
+            return Ok(None);
+        }
+
+        // Make file URL with query and fragment removed.
+        let file_url = {
+            let mut url = absolute_url.clone();
+            url.set_query(None);
+            url.set_fragment(None);
+
+            url.to_string()
+        };
+
+        // Only download the same kinds of files as are downloaded from the thread itself.
+        if !has_download_extension(state, &file_url) {
+            return Ok(None);
+        }
+
+        let Some(path) = state.link_path_generator.generate_path(&file_url)? else {
+            return Ok(None);
+        };
+
+        // Replace invalid filesystem characters in path.
+        let path = replace_invalid_filesystem_characters(&path);
+
+        let mut local_url = util::relative_link(from_dir, Path::new(&path));
+
+        // Keep the fragment, as it may refer to an element inside the file, such as an SVG sprite.
+        if let Some(fragment) = absolute_url.fragment() {
+            local_url.push('#');
+            local_url.push_str(fragment);
+        }
+
+        // If the file has already been seen before, there is no need to download it again.
+        let url: String = absolute_url.into();
+        if state.seen_links.insert(url.clone()) {
+            new_links.push(LinkInfo {
+                url,
+                path,
+                retry: RetryState::default(),
+            });
+        }
+
+        Ok(Some(local_url))
+    })?;
+
+    Ok((new_css, new_links))
+}
+
+/// Replace a link to a post in the thread itself with a local anchor.
+/// Returns whether the link was replaced.
+fn replace_quote_link(link: &mut Link, thread_url: &Url) -> bool {
+    if !matches!(link.tag(), LinkTag::A) || link.is_replaced() {
+        return false;
+    }
+
+    let Some(href) = link.link() else {
+        return false;
+    };
+
+    // Links that are already local anchors do not need to be replaced.
+    if href.starts_with('#') {
+        return false;
+    }
+
+    let Ok(url) = thread_url.join(&href) else {
+        return false;
+    };
+
+    let Some(fragment) = url.fragment().filter(|f| !f.is_empty()).map(|f| f.to_owned()) else {
+        return false;
+    };
+
+    if !is_same_thread(&url, thread_url) {
+        return false;
+    }
+
+    link.replace(&format!("#{fragment}"));
+
+    true
+}
+
+/// Check whether two URLs refer to the same thread.
+/// Only the host and path are compared, ignoring any trailing slash, as the scheme, port, query and fragment
+/// of links to a thread are not consistent across imageboards.
+fn is_same_thread(a: &Url, b: &Url) -> bool {
+    fn normalize(url: &Url) -> Option<(String, String)> {
+        // Parse the URL again to normalize it.
+        let url = Url::parse(url.as_str()).ok()?;
+        let host = url.host_str()?.to_lowercase();
+        let path = url.path().trim_end_matches('/').to_owned();
+
+        Some((host, path))
+    }
+
+    match (normalize(a), normalize(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
 /// Replace invalid filesystem characters in string.
 fn replace_invalid_filesystem_characters(s: &str) -> String {
     s.replace(':', "_").replace("//", "_")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::download::{test_dir, TestResponse, TestServer};
+    use crate::project::test_project;
+    use crate::threadupdater::ParserType;
+
+    const THREAD_LINKS_HTML: &str = r##"<html><body><div class="thread"><div class="post op" id="op_1"></div><div class="post reply" id="reply_2"><a href="#1">&gt;&gt;1</a><a href="/b/res/1.html#2">&gt;&gt;2</a><a href="/b/res/3.html#5">&gt;&gt;5</a><a href="https://example.org/">Example</a></div></div></body></html>"##;
+
+    #[test]
+    fn can_replace_quote_links() {
+        let server = TestServer::start(|_| TestResponse::ok(THREAD_LINKS_HTML.as_bytes()));
+
+        let project = test_project(&server, &test_dir("quote_links"), Some(ParserType::Tinyboard));
+
+        let html = fs::read_to_string(project.get_thread_file_path()).unwrap();
+        assert!(html.contains(r##"href="#2" data-original-href="/b/res/1.html#2""##));
+        assert!(html.contains(r##"href="/b/res/3.html#5""##));
+        assert!(html.contains(r##"href="#1">"##));
+    }
+
+    #[test]
+    fn can_compare_thread_urls() {
+        let thread_url = Url::parse("https://a.com/b/res/1.html").unwrap();
+        let same = |url: &str| is_same_thread(&Url::parse(url).unwrap(), &thread_url);
+
+        assert!(same("https://a.com/b/res/1.html#2"));
+        assert!(same("http://A.com/b/res/1.html/"));
+        assert!(same("https://a.com:8080/b/res/1.html?x=1"));
+        assert!(!same("https://a.com/b/res/2.html"));
+        assert!(!same("https://b.com/b/res/1.html"));
+    }
+}
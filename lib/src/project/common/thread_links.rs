@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use url::Url;
+
+use crate::error::*;
+use crate::html::{Link, LinkTag};
+use crate::misc::site_resolver::SiteResolver;
+use crate::project::ProjectState;
+use crate::util;
+
+/// Find links to other threads on known sites.
+/// Links that have already been replaced with local paths are skipped.
+pub fn find_thread_links(state: &ProjectState, site_resolver: &dyn SiteResolver) -> Result<Vec<String>, ChandlerError> {
+    let thread_url = parse_thread_url(&state.thread_url)?;
+
+    let mut thread_links: Vec<String> = Vec::new();
+
+    for_thread_links(state, &thread_url, |_, url| {
+        let url = url.to_string();
+
+        if url != state.thread_url && !thread_links.contains(&url) && site_resolver.resolve_site(&url)?.is_some() {
+            thread_links.push(url);
+        }
+
+        Ok(())
+    })?;
+
+    Ok(thread_links)
+}
+
+/// Replace links to other threads with relative links to their local thread files.
+/// Returns the number of links replaced.
+pub fn replace_thread_links(
+    state: &mut ProjectState,
+    thread_file_paths: &HashMap<String, PathBuf>,
+) -> Result<u32, ChandlerError> {
+    let thread_url = parse_thread_url(&state.thread_url)?;
+
+    let mut replaced_count = 0;
+
+    for_thread_links(state, &thread_url, |mut link, url| {
+        if let Some(thread_file_path) = thread_file_paths.get(url.as_str()) {
+            let mut path = util::relative_link(&state.root_path, thread_file_path);
+
+            // Keep the fragment, so that links to specific posts still work.
+            if let Some(fragment) = link.link().and_then(|l| l.split_once('#').map(|(_, f)| f.to_owned())) {
+                path.push('#');
+                path.push_str(&fragment);
+            }
+
+            link.replace(&path);
+            replaced_count += 1;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(replaced_count)
+}
+
+/// Perform an action for each <a> link in the thread that has not been replaced,
+/// along with its absolute URL with the fragment removed.
+fn for_thread_links(
+    state: &ProjectState,
+    thread_url: &Url,
+    mut action: impl FnMut(Link, Url) -> Result<(), ChandlerError>,
+) -> Result<(), ChandlerError> {
+    let Some(thread) = state.thread.as_ref() else {
+        return Ok(());
+    };
+
+    thread.for_links(&mut |link| {
+        if !matches!(link.tag(), LinkTag::A) || link.is_replaced() {
+            return Ok(());
+        }
+
+        let Some(href) = link.link() else {
+            return Ok(());
+        };
+
+        // Ignore links that cannot be made absolute, as they cannot be links to threads.
+        let Ok(mut url) = thread_url.join(&href) else {
+            return Ok(());
+        };
+
+        url.set_fragment(None);
+
+        action(link, url)
+    })
+}
+
+fn parse_thread_url(thread_url: &str) -> Result<Url, ChandlerError> {
+    Url::parse(thread_url).map_err(|err| ChandlerError::Other(format!("Error parsing thread URL: {}", err).into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::download::{test_dir, TestResponse, TestServer};
+    use crate::misc::site_resolver::SiteInfo;
+    use crate::project::test_project;
+    use crate::threadupdater::ParserType;
+
+    const THREAD_LINKS_HTML: &str = r##"<html><body><div class="thread"><div class="post op" id="op_1"></div><div class="post reply" id="reply_2"><a href="#1">&gt;&gt;1</a><a href="/b/res/1.html#2">&gt;&gt;2</a><a href="/b/res/3.html#5">&gt;&gt;5</a><a href="https://example.org/">Example</a></div></div></body></html>"##;
+
+    struct ResResolver;
+
+    impl SiteResolver for ResResolver {
+        fn resolve_site(&self, url: &str) -> Result<Option<SiteInfo>, ChandlerError> {
+            Ok(url.contains("/res/").then(|| SiteInfo {
+                name: "test".to_owned(),
+                parser: ParserType::Tinyboard,
+                path: PathBuf::new(),
+                api: None,
+            }))
+        }
+    }
+
+    #[test]
+    fn can_replace_thread_links() {
+        let server = TestServer::start(|_| TestResponse::ok(THREAD_LINKS_HTML.as_bytes()));
+
+        let path = test_dir("thread_links");
+        let mut project = test_project(&server, &path.join("1"), Some(ParserType::Tinyboard));
+
+        let thread_url = server.url("/b/res/3.html");
+        assert_eq!(
+            project.find_thread_links(&ResResolver).unwrap(),
+            vec![thread_url.clone()]
+        );
+
+        let thread_file_paths = HashMap::from([(thread_url, path.join("3").join("thread.html"))]);
+        project.replace_thread_links(&thread_file_paths).unwrap();
+
+        let html = fs::read_to_string(project.get_thread_file_path()).unwrap();
+        assert!(html.contains(r#"href="../3/thread.html#5""#));
+        assert!(html.contains(r##"href="#1""##));
+
+        // Replaced links are no longer found.
+        assert!(project.find_thread_links(&ResResolver).unwrap().is_empty());
+    }
+}
@@ -1,8 +1,13 @@
+use std::fs;
+
 use chrono::Utc;
 use tracing::info;
 
+use crate::download::*;
 use crate::error::*;
 use crate::project::ProjectState;
+use crate::threadapi::fourchan::FourchanApiThread;
+use crate::threadapi::ThreadApi;
 use crate::ui::*;
 
 use super::*;
@@ -11,6 +16,8 @@ use super::*;
 pub struct UpdateResult {
     pub was_updated: bool,
     pub new_post_count: u32,
+    pub edited_post_count: u32,
+    pub deleted_post_count: u32,
     pub new_link_count: u32,
 }
 
@@ -22,11 +29,7 @@ pub fn update_thread(
     let now = Utc::now();
     let unix_now = now.timestamp();
 
-    // Construct filename
-    let filename = format!("{}.html", unix_now);
-    let new_thread_file_path = state.originals_path.join(filename);
-
-    let result = (|| {
+    let result = {
         let url = &state.thread_url;
 
         info!("BEGIN UPDATE: {}", url);
@@ -36,52 +39,15 @@ pub fn update_thread(
             destination: state.root_path.to_path_buf(),
         });
 
-        // Download new thread HTML.
-        let result = download_file(url, &new_thread_file_path, state.last_modified, ui_handler)?;
-
-        match result {
-            DownloadResult::Success { last_modified } => {
-                // Process the new HTML.
-                let process_result = process_thread(state, &new_thread_file_path)?;
-
-                let update_result = process_result.update_result;
-
-                // If thread is archived, mark it as dead.
-                state.is_dead = update_result.is_archived;
-
-                // Update last modified date in project state.
-                state.last_modified = last_modified;
-
-                Ok(UpdateResult {
-                    was_updated: true,
-                    new_post_count: update_result.new_post_count,
-                    new_link_count: process_result.new_file_count,
-                })
+        // The API is only used once the thread has been downloaded, as the
+        // original HTML is needed as a base to render new posts into.
+        match state.api.clone() {
+            Some(ThreadApi::FourChan { url }) if state.thread.is_some() => {
+                update_from_fourchan_api(state, &url, unix_now, ui_handler)
             }
-            DownloadResult::NotModified => Ok(UpdateResult {
-                was_updated: false,
-                new_post_count: 0,
-                new_link_count: 0,
-            }),
-            DownloadResult::NotFound => {
-                // If thread returned 404, mark it as dead.
-                state.is_dead = true;
-
-                Ok(UpdateResult {
-                    was_updated: false,
-                    new_post_count: 0,
-                    new_link_count: 0,
-                })
-            }
-            DownloadResult::OtherHttpError {
-                status_code,
-                description,
-            } => Err(ChandlerError::Download(DownloadError::Http {
-                code: status_code,
-                description: description.into(),
-            })),
+            _ => update_from_html(state, unix_now, ui_handler),
         }
-    })();
+    };
 
     info!("END UPDATE");
 
@@ -89,6 +55,8 @@ pub fn update_thread(
         Ok(result) => ui_handler.event(&UiEvent::UpdateComplete {
             was_updated: result.was_updated,
             new_post_count: result.new_post_count,
+            edited_post_count: result.edited_post_count,
+            deleted_post_count: result.deleted_post_count,
             new_file_count: result.new_link_count,
         }),
         Err(err) => ui_handler.event(&UiEvent::UpdateError {
@@ -98,3 +66,218 @@ pub fn update_thread(
 
     result
 }
+
+/// Update thread by downloading its HTML.
+fn update_from_html(
+    state: &mut ProjectState,
+    unix_now: i64,
+    ui_handler: &mut dyn ChandlerUiHandler,
+) -> Result<UpdateResult, ChandlerError> {
+    // Construct filename
+    let filename = format!("{}.html", unix_now);
+    let new_thread_file_path = state.originals_path.join(filename);
+
+    // Download new thread HTML.
+    let result = state.download_engine.download_file(
+        &state.thread_url,
+        &new_thread_file_path,
+        state.last_modified,
+        ui_handler,
+    )?;
+
+    match result {
+        DownloadResult::Success { last_modified, .. } => {
+            // Process the new HTML.
+            let process_result = process_thread(state, &new_thread_file_path)?;
+
+            let update_result = process_result.update_result;
+
+            // If thread is archived, mark it as dead.
+            state.status = update_result.status;
+            state.is_dead = update_result.status.is_archived;
+
+            // Update last modified date in project state.
+            state.last_modified = last_modified;
+
+            Ok(UpdateResult {
+                was_updated: true,
+                new_post_count: update_result.new_post_count,
+                edited_post_count: update_result.edited_post_count,
+                deleted_post_count: update_result.deleted_post_count,
+                new_link_count: process_result.new_file_count,
+            })
+        }
+        result => handle_unsuccessful_download(state, result),
+    }
+}
+
+/// Update thread using the 4chan JSON API.
+///
+/// The JSON is rendered into an HTML snapshot in the originals directory,
+/// which is then processed the same way as a downloaded HTML file.
+fn update_from_fourchan_api(
+    state: &mut ProjectState,
+    api_url: &str,
+    unix_now: i64,
+    ui_handler: &mut dyn ChandlerUiHandler,
+) -> Result<UpdateResult, ChandlerError> {
+    let json_file_path = state.originals_path.join(format!("{}.json", unix_now));
+    let new_thread_file_path = state.originals_path.join(format!("{}.html", unix_now));
+
+    info!("Updating from 4chan API: {}", api_url);
+
+    // Download new thread JSON.
+    let result = state
+        .download_engine
+        .download_file(api_url, &json_file_path, state.last_modified, ui_handler)?;
+
+    match result {
+        DownloadResult::Success { last_modified, .. } => {
+            let api_thread = FourchanApiThread::from_file(&json_file_path)?;
+
+            // Render the JSON into an HTML snapshot.
+            let html = api_thread.render(api_url)?;
+            fs::write(&new_thread_file_path, html).map_err(ChandlerError::WriteFile)?;
+
+            // Process the rendered HTML.
+            // Posts that are no longer in the thread are marked as deleted while merging.
+            let process_result = process_thread(state, &new_thread_file_path)?;
+
+            // The API reports the thread's status more accurately than the rendered HTML.
+            // If thread is archived, mark it as dead.
+            state.status = api_thread.status();
+            state.is_dead = state.status.is_archived;
+
+            // Update last modified date in project state.
+            state.last_modified = last_modified;
+
+            Ok(UpdateResult {
+                was_updated: true,
+                new_post_count: process_result.update_result.new_post_count,
+                edited_post_count: process_result.update_result.edited_post_count,
+                deleted_post_count: process_result.update_result.deleted_post_count,
+                new_link_count: process_result.new_file_count,
+            })
+        }
+        result => handle_unsuccessful_download(state, result),
+    }
+}
+
+fn handle_unsuccessful_download(
+    state: &mut ProjectState,
+    result: DownloadResult,
+) -> Result<UpdateResult, ChandlerError> {
+    match result {
+        DownloadResult::Success { .. } | DownloadResult::NotModified => Ok(UpdateResult {
+            was_updated: false,
+            new_post_count: 0,
+            edited_post_count: 0,
+            deleted_post_count: 0,
+            new_link_count: 0,
+        }),
+        DownloadResult::NotFound => {
+            // If thread returned 404, mark it as dead.
+            state.is_dead = true;
+
+            Ok(UpdateResult {
+                was_updated: false,
+                new_post_count: 0,
+                edited_post_count: 0,
+                deleted_post_count: 0,
+                new_link_count: 0,
+            })
+        }
+        DownloadResult::OtherHttpError {
+            status_code,
+            description,
+            ..
+        } => Err(ChandlerError::Download(DownloadError::Http {
+            code: status_code,
+            description: description.into(),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::misc::site_resolver::{SiteInfo, SiteResolver};
+    use crate::project;
+    use crate::threadupdater::ParserType;
+
+    const THREAD_HTML: &str = r#"<html><body><div class="board"><div class="thread" id="t1"><div class="postContainer opContainer" id="pc1"></div><div class="postContainer replyContainer" id="pc2"></div></div></div></body></html>"#;
+
+    // Post 2 has been deleted, and post 3 has been added.
+    const THREAD_JSON: &str = r#"{"posts": [{"no": 1, "com": "OP"}, {"no": 3, "resto": 1, "com": "New reply"}]}"#;
+
+    const THREAD_JSON_ARCHIVED: &str =
+        r#"{"posts": [{"no": 1, "com": "OP", "archived": 1}, {"no": 3, "resto": 1, "com": "New reply"}]}"#;
+
+    struct TestSiteResolver {
+        api_url: String,
+    }
+
+    impl SiteResolver for TestSiteResolver {
+        fn resolve_site(&self, _url: &str) -> Result<Option<SiteInfo>, ChandlerError> {
+            Ok(Some(SiteInfo {
+                name: "4chan".to_owned(),
+                parser: ParserType::FourChan,
+                path: PathBuf::new(),
+                api: Some(ThreadApi::FourChan {
+                    url: self.api_url.clone(),
+                }),
+            }))
+        }
+    }
+
+    #[test]
+    fn can_update_from_fourchan_api() {
+        let json: Arc<Mutex<&'static str>> = Arc::new(Mutex::new(THREAD_JSON));
+
+        let server = TestServer::start({
+            let json = Arc::clone(&json);
+
+            move |request| {
+                if request.path.ends_with(".json") {
+                    TestResponse::ok(json.lock().unwrap().as_bytes())
+                } else {
+                    TestResponse::ok(THREAD_HTML.as_bytes())
+                }
+            }
+        });
+
+        let path = test_dir("update_fourchan_api");
+
+        let mut project = project::builder()
+            .url(&server.url("/g/thread/1"))
+            .path(Some(&path))
+            .site_resolver(Some(Box::new(TestSiteResolver {
+                api_url: server.url("/api/g/thread/1.json"),
+            })))
+            .load_or_create()
+            .unwrap();
+
+        let mut ui_handler = TestUiHandler::default();
+
+        // The first update downloads the HTML.
+        project.update(&mut ui_handler).unwrap();
+
+        // Subsequent updates use the API.
+        let result = project.update(&mut ui_handler).unwrap();
+        assert_eq!(result.new_post_count, 1);
+        assert_eq!(result.deleted_post_count, 1);
+        assert!(!result.is_dead);
+
+        let thread_html = fs::read_to_string(path.join("thread.html")).unwrap();
+        assert!(thread_html.contains(r#"<blockquote class="postMessage" id="m3">New reply</blockquote>"#));
+        assert!(thread_html.contains(r#"class="postContainer replyContainer chandler-deleted" id="pc2""#));
+
+        *json.lock().unwrap() = THREAD_JSON_ARCHIVED;
+
+        let result = project.update(&mut ui_handler).unwrap();
+        assert!(result.is_dead);
+    }
+}
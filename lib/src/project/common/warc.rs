@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::error::*;
+use crate::project::{ManifestEntry, ManifestStatus, ProjectState};
+use crate::ui::*;
+use crate::util;
+use crate::warc::{self, WarcReader, WarcRecord, WarcWriter};
+
+use super::*;
+
+/// Name of the warcinfo field containing the URL of the thread.
+const THREAD_URL_FIELD: &str = "chandler-thread-url";
+
+/// Write all original thread snapshots and downloaded content files to a WARC file,
+/// as response records with their source URLs and capture times.
+/// Snapshots rendered from an API response are written as conversion records referring to it.
+pub fn export_warc(state: &ProjectState, file_path: &Path) -> Result<(), ChandlerError> {
+    let file = util::create_file(file_path).map_err(ChandlerError::CreateFile)?;
+    let mut writer = WarcWriter::new(BufWriter::new(file));
+
+    let software = format!("Chandler/{}", env!("CARGO_PKG_VERSION"));
+
+    writer.write_record(&WarcRecord::warcinfo(
+        Utc::now(),
+        &[
+            ("software", &software),
+            ("format", "WARC File Format 1.1"),
+            (THREAD_URL_FIELD, &state.thread_url),
+        ],
+    ))?;
+
+    // Write original thread snapshots.
+    // Snapshots rendered from an API have both the API response and the rendered HTML.
+    let mut original_files: Vec<_> = fs::read_dir(&state.originals_path)
+        .map_err(ChandlerError::ReadFile)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+
+    // Write API responses before the HTML rendered from them.
+    original_files.sort_by_key(|path| {
+        (
+            path.file_stem().map(OsStr::to_owned),
+            path.extension() != Some(OsStr::new("json")),
+        )
+    });
+
+    // Record IDs of API responses, by file stem.
+    let mut api_record_ids: HashMap<String, String> = HashMap::new();
+
+    for path in original_files {
+        let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or_default();
+
+        // Originals are named after the UNIX timestamp of when they were downloaded.
+        let date = stem
+            .parse::<i64>()
+            .ok()
+            .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single())
+            .unwrap_or_else(|| modified_time(&path));
+
+        match path.extension().and_then(OsStr::to_str) {
+            Some("html") => match api_record_ids.get(stem) {
+                Some(record_id) => {
+                    let payload = fs::read(&path).map_err(ChandlerError::ReadFile)?;
+
+                    writer.write_record(&WarcRecord::conversion(
+                        &state.thread_url,
+                        date,
+                        warc::guess_content_type(&path),
+                        &payload,
+                        record_id,
+                    ))?;
+                }
+                None => {
+                    write_file_record(&mut writer, &state.thread_url, date, &path)?;
+                }
+            },
+            Some("json") => {
+                if let Some(api) = &state.api {
+                    let record_id = write_file_record(&mut writer, api.url(), date, &path)?;
+                    api_record_ids.insert(stem.to_owned(), record_id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Write downloaded content files.
+    // Files that are not in the manifest, such as in V2 projects, get their URL from their path.
+    let manifest_entries: HashMap<&str, (&str, &ManifestEntry)> = state
+        .manifest
+        .files
+        .iter()
+        .map(|(url, entry)| (entry.path.as_str(), (url.as_str(), entry)))
+        .collect();
+
+    for path in get_content_files(&state.root_path).map_err(ChandlerError::ReadFile)? {
+        let Some(relative_path) = relative_path(&state.root_path, &path) else {
+            continue;
+        };
+
+        let (url, date) = match manifest_entries.get(relative_path.as_str()) {
+            Some((url, entry)) => ((*url).to_owned(), entry.downloaded_at),
+            None => match state.link_path_generator.generate_url(&relative_path) {
+                Some(url) => (url, None),
+                None => continue,
+            },
+        };
+
+        let date = date.unwrap_or_else(|| modified_time(&path));
+
+        write_file_record(&mut writer, &url, date, &path)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Get the URL of the thread from a WARC file exported by Chandler.
+pub fn get_warc_thread_url(file_path: &Path) -> Result<String, ChandlerError> {
+    for record in open_warc(file_path)? {
+        let record = record?;
+
+        if record.record_type() != Some("warcinfo") {
+            continue;
+        }
+
+        if let Some((_, url)) = record.fields().into_iter().find(|(name, _)| name == THREAD_URL_FIELD) {
+            return Ok(url);
+        }
+    }
+
+    Err(ChandlerError::Other(
+        format!("No thread URL found in WARC file: {}", file_path.display()).into(),
+    ))
+}
+
+/// Import thread snapshots and content files from a WARC file, and rebuild the thread from them.
+/// Content files are placed where they would have been if they had been downloaded by the project.
+pub fn import_warc(
+    state: &mut ProjectState,
+    file_path: &Path,
+    ui_handler: &mut dyn ChandlerUiHandler,
+) -> Result<(), ChandlerError> {
+    for record in open_warc(file_path)? {
+        let record = record?;
+
+        let payload = record.http_payload().or_else(|| record.conversion_payload());
+
+        let (Some(url), Some(payload)) = (record.target_uri(), payload) else {
+            continue;
+        };
+
+        let date = record.date();
+        let timestamp = date.unwrap_or_else(Utc::now).timestamp();
+
+        let is_api = state.api.as_ref().is_some_and(|api| api.url() == url);
+
+        if url == state.thread_url || is_api {
+            let extension = if is_api { "json" } else { "html" };
+
+            write_file(&state.originals_path.join(format!("{timestamp}.{extension}")), payload)?;
+        } else if let Some(path) = state.link_path_generator.generate_path(url)? {
+            write_file(&state.root_path.join(&path), payload)?;
+
+            let entry = ManifestEntry::from_file(&state.root_path, &path, date)?;
+            state.manifest.files.insert(url.to_owned(), entry);
+        }
+    }
+
+    rebuild_thread(state, ui_handler)?;
+
+    // Files that were imported do not need to be downloaded.
+    let manifest = &state.manifest;
+    state
+        .new_links
+        .retain(|link| !matches!(manifest.get(&link.url), Some(e) if e.status == ManifestStatus::Downloaded));
+
+    state.write_thread()?;
+
+    Ok(())
+}
+
+fn open_warc(file_path: &Path) -> Result<WarcReader<BufReader<fs::File>>, ChandlerError> {
+    let file = util::open_file(file_path).map_err(ChandlerError::OpenFile)?;
+
+    Ok(WarcReader::new(BufReader::new(file)))
+}
+
+/// Write a file as a response record, and get its record ID.
+fn write_file_record(
+    writer: &mut WarcWriter<impl std::io::Write>,
+    url: &str,
+    date: DateTime<Utc>,
+    path: &Path,
+) -> Result<String, ChandlerError> {
+    let payload = fs::read(path).map_err(ChandlerError::ReadFile)?;
+
+    writer.write_record(&WarcRecord::response(
+        url,
+        date,
+        warc::guess_content_type(path),
+        &payload,
+    ))
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), ChandlerError> {
+    util::create_parent_dir(path).map_err(ChandlerError::CreateFile)?;
+    fs::write(path, data).map_err(ChandlerError::WriteFile)
+}
+
+/// Get all files in the project that may be downloaded content, sorted by path.
+/// Hidden files and directories, such as the project directory, and partially downloaded files are excluded.
+fn get_content_files(root_path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut dirs: Vec<PathBuf> = vec![root_path.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in dir.read_dir()? {
+            let path = entry?.path();
+
+            let is_hidden = path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .is_some_and(|name| name.starts_with('.'));
+
+            if is_hidden {
+                continue;
+            }
+
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension() != Some(OsStr::new("part")) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Get the path of a file relative to the project, with "/" as the separator.
+fn relative_path(root_path: &Path, path: &Path) -> Option<String> {
+    let components: Vec<&str> = path
+        .strip_prefix(root_path)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<_>>()?;
+
+    Some(components.join("/"))
+}
+
+/// Get the modification time of a file, or the current time if it cannot be determined.
+fn modified_time(path: &Path) -> DateTime<Utc> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_or_else(|_| Utc::now(), DateTime::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::download::{test_dir, TestResponse, TestServer, TestUiHandler};
+    use crate::misc::site_resolver::{SiteInfo, SiteResolver};
+    use crate::project::{self, test_project, ProjectFormat, UnknownSiteResolver, TEST_THREAD_HTML};
+    use crate::threadapi::ThreadApi;
+    use crate::threadupdater::ParserType;
+
+    const FOURCHAN_THREAD_HTML: &str = r#"<html><body><div class="board"><div class="thread" id="t1"><div class="postContainer opContainer" id="pc1"></div></div></div></body></html>"#;
+
+    const FOURCHAN_THREAD_JSON: &str = r#"{"posts": [{"no": 1, "com": "OP"}, {"no": 2, "resto": 1, "com": "Reply"}]}"#;
+
+    struct ApiSiteResolver {
+        api_url: String,
+    }
+
+    impl SiteResolver for ApiSiteResolver {
+        fn resolve_site(&self, _url: &str) -> Result<Option<SiteInfo>, ChandlerError> {
+            Ok(Some(SiteInfo {
+                name: "4chan".to_owned(),
+                parser: ParserType::FourChan,
+                path: PathBuf::new(),
+                api: Some(ThreadApi::FourChan {
+                    url: self.api_url.clone(),
+                }),
+            }))
+        }
+    }
+
+    fn read_records(path: &Path) -> Vec<WarcRecord> {
+        open_warc(path).unwrap().collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn can_export_and_import_warc() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/b/src/2.png" => TestResponse::ok(b"file"),
+            _ => TestResponse::ok(TEST_THREAD_HTML.as_bytes()),
+        });
+
+        let path = test_dir("export_warc");
+        let url = server.url("/b/res/1.html");
+
+        let project = test_project(&server, &path, Some(ParserType::Tinyboard));
+        project.save().unwrap();
+
+        let warc_path = path.join("export.warc");
+        project.export_warc(&warc_path).unwrap();
+
+        assert_eq!(get_warc_thread_url(&warc_path).unwrap(), url);
+
+        let import_path = test_dir("import_warc");
+
+        let mut imported_project = project::builder()
+            .url(&url)
+            .path(Some(&import_path))
+            .parser(Some(ParserType::Tinyboard))
+            .site_resolver(Some(Box::new(UnknownSiteResolver)))
+            .load_or_create()
+            .unwrap();
+
+        imported_project
+            .import_warc(&warc_path, &mut TestUiHandler::default())
+            .unwrap();
+
+        let file_path = import_path.join("content/127.0.0.1/b/src/2.png");
+        assert_eq!(fs::read(&file_path).unwrap(), b"file");
+
+        let entry = imported_project
+            .get_manifest()
+            .get(&server.url("/b/src/2.png"))
+            .unwrap();
+        assert!(entry.verify(&import_path).unwrap());
+
+        assert_eq!(
+            fs::read_to_string(imported_project.get_thread_file_path()).unwrap(),
+            fs::read_to_string(project.get_thread_file_path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn exports_content_files_without_manifest() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/b/src/2.png" => TestResponse::ok(b"file"),
+            _ => TestResponse::ok(TEST_THREAD_HTML.as_bytes()),
+        });
+
+        let path = test_dir("export_warc_v2");
+
+        // V2 projects have no manifest, so content files are found on disk.
+        let mut project = project::builder()
+            .url(&server.url("/b/res/1.html"))
+            .path(Some(&path))
+            .format(Some(ProjectFormat::V2))
+            .parser(Some(ParserType::Tinyboard))
+            .site_resolver(Some(Box::new(UnknownSiteResolver)))
+            .load_or_create()
+            .unwrap();
+
+        project.update(&mut TestUiHandler::default()).unwrap();
+
+        let warc_path = path.join("export.warc");
+        project.export_warc(&warc_path).unwrap();
+
+        let records = read_records(&warc_path);
+
+        let file_url = server.url("/b/src/2.png");
+        let file_record = records
+            .iter()
+            .find(|r| r.target_uri() == Some(file_url.as_str()))
+            .unwrap();
+        assert_eq!(file_record.http_payload(), Some(&b"file"[..]));
+
+        // The thread file itself is not content.
+        assert_eq!(
+            records.iter().filter(|r| r.record_type() == Some("response")).count(),
+            2
+        );
+    }
+
+    #[test]
+    fn exports_api_snapshots_as_conversions() {
+        let server = TestServer::start(|request| {
+            if request.path.ends_with(".json") {
+                TestResponse::ok(FOURCHAN_THREAD_JSON.as_bytes())
+            } else {
+                TestResponse::ok(FOURCHAN_THREAD_HTML.as_bytes())
+            }
+        });
+
+        let path = test_dir("export_warc_api");
+        let url = server.url("/g/thread/1");
+        let api_url = server.url("/api/g/thread/1.json");
+
+        let mut project = project::builder()
+            .url(&url)
+            .path(Some(&path))
+            .site_resolver(Some(Box::new(ApiSiteResolver {
+                api_url: api_url.clone(),
+            })))
+            .load_or_create()
+            .unwrap();
+
+        // The first update downloads the HTML, and the second uses the API.
+        let mut ui_handler = TestUiHandler::default();
+        project.update(&mut ui_handler).unwrap();
+
+        // Make sure the API snapshot gets a different timestamp.
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        project.update(&mut ui_handler).unwrap();
+
+        let warc_path = path.join("export.warc");
+        project.export_warc(&warc_path).unwrap();
+
+        let records = read_records(&warc_path);
+        let find = |record_type: &str, uri: &str| {
+            records
+                .iter()
+                .filter(|r| r.record_type() == Some(record_type) && r.target_uri() == Some(uri))
+                .collect::<Vec<_>>()
+        };
+
+        // Only the HTML that was actually downloaded is a response.
+        assert_eq!(find("response", &url).len(), 1);
+
+        let api_responses = find("response", &api_url);
+        assert_eq!(api_responses.len(), 1);
+
+        let conversions = find("conversion", &url);
+        assert_eq!(conversions.len(), 1);
+        assert_eq!(
+            conversions[0].header("WARC-Refers-To"),
+            api_responses[0].header("WARC-Record-ID")
+        );
+        assert!(String::from_utf8_lossy(conversions[0].conversion_payload().unwrap()).contains("Reply"));
+    }
+}
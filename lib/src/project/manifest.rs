@@ -0,0 +1,276 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::download::DownloadResult;
+use crate::error::*;
+use crate::util;
+
+/// Record of the content files downloaded for a project.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Manifest {
+    /// Entries for all content files, by URL.
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    /// Local path of the file, relative to the project root.
+    pub path: String,
+    pub status: ManifestStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloaded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ManifestStatus {
+    /// The file has been downloaded.
+    Downloaded,
+
+    /// The file has not been downloaded yet, but will be retried.
+    Failed,
+
+    /// The file could not be downloaded, and will not be retried.
+    Gone,
+}
+
+impl Manifest {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ChandlerError> {
+        let file = util::open_file(path).map_err(ChandlerError::OpenConfig)?;
+
+        serde_json::from_reader(file).map_err(|err| ChandlerError::ParseConfig(Cow::Owned(err.to_string())))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ChandlerError> {
+        let file = util::create_file(path).map_err(ChandlerError::CreateFile)?;
+
+        serde_json::to_writer_pretty(file, self)
+            .map_err(|err| ChandlerError::ParseConfig(Cow::Owned(err.to_string())))?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, url: &str) -> Option<&ManifestEntry> {
+        self.files.get(url)
+    }
+
+    /// Get the number of files that have been downloaded.
+    pub fn downloaded_count(&self) -> usize {
+        self.files
+            .values()
+            .filter(|entry| entry.status == ManifestStatus::Downloaded)
+            .count()
+    }
+
+    /// Record the result of a successful download.
+    ///
+    /// If the file was not modified and there is no existing entry for it,
+    /// the entry is created from the file on disk.
+    pub fn record_success(
+        &mut self,
+        root_path: &Path,
+        url: &str,
+        path: &str,
+        result: &DownloadResult,
+    ) -> Result<(), ChandlerError> {
+        let entry = match result {
+            DownloadResult::Success {
+                last_modified,
+                etag,
+                size,
+                sha256,
+            } => ManifestEntry {
+                path: path.to_owned(),
+                status: ManifestStatus::Downloaded,
+                size: Some(*size),
+                sha256: Some(sha256.clone()),
+                last_modified: *last_modified,
+                etag: etag.clone(),
+                downloaded_at: Some(Utc::now()),
+            },
+            DownloadResult::NotModified => {
+                if matches!(self.get(url), Some(e) if e.status == ManifestStatus::Downloaded) {
+                    return Ok(());
+                }
+
+                ManifestEntry::from_file(root_path, path, None)?
+            }
+            _ => return Ok(()),
+        };
+
+        self.files.insert(url.to_owned(), entry);
+
+        Ok(())
+    }
+
+    /// Record a failed download.
+    ///
+    /// A file that has previously been downloaded is still available locally,
+    /// so its existing entry is left alone.
+    pub fn record_failure(&mut self, url: &str, path: &str, permanent: bool) {
+        let status = if permanent {
+            ManifestStatus::Gone
+        } else {
+            ManifestStatus::Failed
+        };
+
+        let entry = self.files.entry(url.to_owned()).or_insert_with(|| ManifestEntry {
+            path: path.to_owned(),
+            status,
+            size: None,
+            sha256: None,
+            last_modified: None,
+            etag: None,
+            downloaded_at: None,
+        });
+
+        if entry.status != ManifestStatus::Downloaded {
+            entry.status = status;
+        }
+    }
+}
+
+impl ManifestEntry {
+    /// Create an entry for a file that is already present locally.
+    pub fn from_file(
+        root_path: &Path,
+        path: &str,
+        downloaded_at: Option<DateTime<Utc>>,
+    ) -> Result<Self, ChandlerError> {
+        let file_path = root_path.join(path);
+        let size = file_path.metadata().map_err(ChandlerError::ReadFile)?.len();
+        let sha256 = util::hash_file(&file_path).map_err(ChandlerError::OpenFile)?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            status: ManifestStatus::Downloaded,
+            size: Some(size),
+            sha256: Some(sha256),
+            last_modified: None,
+            etag: None,
+            downloaded_at,
+        })
+    }
+
+    /// Check whether the local file exists and matches the recorded size and hash.
+    pub fn verify(&self, root_path: &Path) -> Result<bool, ChandlerError> {
+        let file_path = root_path.join(&self.path);
+
+        if self.status != ManifestStatus::Downloaded || !file_path.is_file() {
+            return Ok(false);
+        }
+
+        if let Some(size) = self.size {
+            if file_path.metadata().map_err(ChandlerError::ReadFile)?.len() != size {
+                return Ok(false);
+            }
+        }
+
+        if let Some(sha256) = &self.sha256 {
+            if &util::hash_file(&file_path).map_err(ChandlerError::OpenFile)? != sha256 {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::download::test_dir;
+
+    const SHA256_HELLO: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn can_record_and_verify_download() {
+        let root_path = test_dir("manifest_verify");
+        fs::create_dir_all(root_path.join("content")).unwrap();
+        fs::write(root_path.join("content/file.txt"), "hello").unwrap();
+
+        let mut manifest = Manifest::default();
+        manifest
+            .record_success(
+                &root_path,
+                "http://example.com/file.txt",
+                "content/file.txt",
+                &DownloadResult::Success {
+                    last_modified: None,
+                    etag: Some("\"abc\"".to_owned()),
+                    size: 5,
+                    sha256: SHA256_HELLO.to_owned(),
+                },
+            )
+            .unwrap();
+
+        let entry = manifest.get("http://example.com/file.txt").unwrap();
+        assert_eq!(entry.status, ManifestStatus::Downloaded);
+        assert!(entry.verify(&root_path).unwrap());
+
+        fs::write(root_path.join("content/file.txt"), "HELLO").unwrap();
+        assert!(!entry.verify(&root_path).unwrap());
+    }
+
+    #[test]
+    fn not_modified_file_is_hashed_from_disk() {
+        let root_path = test_dir("manifest_not_modified");
+        fs::write(root_path.join("file.txt"), "hello").unwrap();
+
+        let mut manifest = Manifest::default();
+        manifest
+            .record_success(
+                &root_path,
+                "http://example.com/file.txt",
+                "file.txt",
+                &DownloadResult::NotModified,
+            )
+            .unwrap();
+
+        let entry = manifest.get("http://example.com/file.txt").unwrap();
+        assert_eq!(entry.size, Some(5));
+        assert_eq!(entry.sha256.as_deref(), Some(SHA256_HELLO));
+    }
+
+    #[test]
+    fn failure_does_not_overwrite_downloaded_entry() {
+        let root_path = test_dir("manifest_failure");
+        fs::write(root_path.join("file.txt"), "hello").unwrap();
+
+        let mut manifest = Manifest::default();
+        manifest.record_failure("http://example.com/gone.txt", "gone.txt", false);
+        manifest.record_failure("http://example.com/gone.txt", "gone.txt", true);
+        manifest
+            .record_success(
+                &root_path,
+                "http://example.com/file.txt",
+                "file.txt",
+                &DownloadResult::NotModified,
+            )
+            .unwrap();
+        manifest.record_failure("http://example.com/file.txt", "file.txt", true);
+
+        assert_eq!(
+            manifest.get("http://example.com/gone.txt").unwrap().status,
+            ManifestStatus::Gone
+        );
+        assert_eq!(
+            manifest.get("http://example.com/file.txt").unwrap().status,
+            ManifestStatus::Downloaded
+        );
+    }
+}
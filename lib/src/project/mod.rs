@@ -1,25 +1,41 @@
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
-use tracing::info;
+use tracing::{info, warn};
 use url::Url;
 
 pub mod common;
+mod manifest;
 mod v2;
 mod v3;
 
+#[cfg(test)]
+mod test_only;
+
+pub use self::manifest::*;
+
+#[cfg(test)]
+pub use self::test_only::*;
+
 use common::LinkInfo;
 
-use crate::config::chandler::ChandlerConfig;
+use crate::config::chandler::{ChandlerConfig, UpdateConfig};
 use crate::config::sites::SitesConfig;
+use crate::download::{DownloadEngine, DownloadResult};
 use crate::error::*;
 use crate::misc::site_resolver::{self, SiteResolver};
-use crate::threadupdater::{ParserType, ThreadUpdater};
+use crate::threadapi::ThreadApi;
+use crate::threadparser::{Post, ThreadStatus};
+use crate::threadupdater::{self, ParserType, ThreadUpdater};
 use crate::ui::*;
 use crate::{config, util};
 
-const DEFAULT_DOWNLOAD_EXTENSIONS: &[&str] = &["css", "gif", "ico", "jpeg", "jpg", "png", "webm"];
+const DEFAULT_DOWNLOAD_EXTENSIONS: &[&str] = &[
+    "css", "gif", "ico", "jpeg", "jpg", "mp3", "mp4", "ogg", "png", "svg", "webm", "webp", "woff", "woff2",
+];
 
 #[derive(Clone, Copy, Debug)]
 pub enum ProjectFormat {
@@ -31,10 +47,27 @@ pub enum ProjectFormat {
 pub struct ProjectUpdateResult {
     pub was_updated: bool,
     pub is_dead: bool,
+    pub status: ThreadStatus,
     pub new_post_count: u32,
+    pub edited_post_count: u32,
+    pub deleted_post_count: u32,
     pub new_file_count: u32,
 }
 
+/// Summary of a project, which can be read without loading it.
+pub struct ProjectSummary {
+    pub root_path: PathBuf,
+    pub thread_file_path: PathBuf,
+    pub thread_url: String,
+    pub is_dead: bool,
+
+    /// All posts in the thread, starting with the OP.
+    pub posts: Vec<Post>,
+
+    /// Number of files that have been downloaded, if the project keeps track of them.
+    pub downloaded_file_count: usize,
+}
+
 pub struct ProjectState {
     pub root_path: PathBuf,
     pub thread_file_path: PathBuf,
@@ -42,13 +75,21 @@ pub struct ProjectState {
     pub thread_url: String,
     pub download_extensions: BTreeSet<String>,
     pub parser: ParserType,
+
+    /// Whether the parser was automatically detected.
+    pub parser_detected: bool,
+    pub api: Option<ThreadApi>,
+    pub update_config: UpdateConfig,
     pub link_path_generator: Box<dyn LinkPathGenerator>,
+    pub download_engine: Arc<DownloadEngine>,
     pub thread: Option<Box<dyn ThreadUpdater>>,
     pub last_modified: Option<DateTime<Utc>>,
     pub is_dead: bool,
+    pub status: ThreadStatus,
     pub new_links: Vec<LinkInfo>,
     pub failed_links: Vec<LinkInfo>,
     pub seen_links: HashSet<String>,
+    pub manifest: Manifest,
 }
 
 #[derive(Default)]
@@ -84,27 +125,92 @@ pub struct CreateProjectBuilder {
     config_path: Option<PathBuf>,
 
     site_resolver: Option<Box<dyn SiteResolver>>,
+
+    /// Download engine to use. If not specified, one will be created from the configuration.
+    download_engine: Option<Arc<DownloadEngine>>,
 }
 
 pub trait LinkPathGenerator {
     fn generate_path(&self, url: &str) -> Result<Option<String>, ChandlerError>;
+
+    /// Get the URL a content file was downloaded from, from its path relative to the project.
+    /// Returns None if the path is not a content file.
+    fn generate_url(&self, path: &str) -> Option<String>;
 }
 
 pub trait Project {
     fn update(&mut self, ui_handler: &mut dyn ChandlerUiHandler) -> Result<ProjectUpdateResult, ChandlerError>;
     fn download_content(&mut self, ui_handler: &mut dyn ChandlerUiHandler) -> Result<(), ChandlerError>;
     fn rebuild(&mut self, ui_handler: &mut dyn ChandlerUiHandler) -> Result<(), ChandlerError>;
+
+    /// Write the thread's posts and the local paths of their files to a JSON file.
+    fn export_json(&self, file_path: &Path) -> Result<(), ChandlerError>;
+
+    /// Write the thread to a single HTML file, with the downloaded files it references embedded.
+    fn export_html(&self, file_path: &Path, options: &common::HtmlExportOptions) -> Result<(), ChandlerError>;
+
+    /// Write all original thread snapshots and downloaded files to a WARC file.
+    fn export_warc(&self, file_path: &Path) -> Result<(), ChandlerError>;
+
+    /// Import thread snapshots and files from a WARC file created by `export_warc`, and rebuild the thread.
+    fn import_warc(&mut self, file_path: &Path, ui_handler: &mut dyn ChandlerUiHandler) -> Result<(), ChandlerError>;
+
     fn save(&self) -> Result<(), ChandlerError>;
 
+    /// Find links to other threads on sites known to the site resolver.
+    fn find_thread_links(&self, site_resolver: &dyn SiteResolver) -> Result<Vec<String>, ChandlerError>;
+
+    /// Replace links to other threads with relative links to their local thread files, keyed by thread URL.
+    fn replace_thread_links(&mut self, thread_file_paths: &HashMap<String, PathBuf>) -> Result<(), ChandlerError>;
+
     fn get_path(&self) -> &Path;
+    fn get_url(&self) -> &str;
+    fn get_thread_file_path(&self) -> &Path;
+    fn get_manifest(&self) -> &Manifest;
+    fn is_dead(&self) -> bool;
+
+    /// Get all posts in the thread, starting with the OP.
+    fn get_posts(&self) -> Result<Vec<Post>, ChandlerError>;
 }
 
 pub trait ProjectLoader {
     type P: Project;
 
-    fn create(path: &Path, url: &str, parser: ParserType) -> Result<Self::P, ChandlerError>;
-    fn load(path: &Path) -> Result<Self::P, ChandlerError>;
+    fn create(
+        path: &Path,
+        url: &str,
+        parser: ParserType,
+        parser_detected: bool,
+        api: Option<ThreadApi>,
+        update_config: UpdateConfig,
+        download_engine: Arc<DownloadEngine>,
+    ) -> Result<Self::P, ChandlerError>;
+    fn load(
+        path: &Path,
+        update_config: UpdateConfig,
+        download_engine: Arc<DownloadEngine>,
+    ) -> Result<Self::P, ChandlerError>;
     fn exists_at(path: &Path) -> bool;
+
+    /// Get the time the thread or its state was last written, without loading the project.
+    fn modified_at(path: &Path) -> Option<DateTime<Utc>>;
+
+    /// Read a summary of the project without loading it, so that it can be read while it is being updated.
+    fn read_summary(path: &Path) -> Result<ProjectSummary, ChandlerError>;
+}
+
+impl ProjectSummary {
+    /// Get the summary of a loaded project.
+    pub fn from_project(project: &dyn Project) -> Result<Self, ChandlerError> {
+        Ok(Self {
+            root_path: project.get_path().to_path_buf(),
+            thread_file_path: project.get_thread_file_path().to_path_buf(),
+            thread_url: project.get_url().to_owned(),
+            is_dead: project.is_dead(),
+            posts: project.get_posts()?,
+            downloaded_file_count: project.get_manifest().downloaded_count(),
+        })
+    }
 }
 
 impl ProjectState {
@@ -132,18 +238,103 @@ pub fn exists_at(path: impl AsRef<Path>) -> Option<ProjectFormat> {
     }
 }
 
+/// Get the time the project at the path was last written, if there is one.
+pub fn modified_at(path: impl AsRef<Path>) -> Option<DateTime<Utc>> {
+    let path = path.as_ref();
+
+    match exists_at(path)? {
+        ProjectFormat::V3 => v3::V3Project::modified_at(path),
+        ProjectFormat::V2 => v2::V2Project::modified_at(path),
+    }
+}
+
+/// Read a summary of the project at the path, without loading it.
+pub fn read_summary(path: impl AsRef<Path>) -> Result<ProjectSummary, ChandlerError> {
+    let path = path.as_ref();
+
+    match exists_at(path) {
+        Some(ProjectFormat::V3) => v3::V3Project::read_summary(path),
+        Some(ProjectFormat::V2) => v2::V2Project::read_summary(path),
+        None => Err(ChandlerError::LoadProject("No project found".into())),
+    }
+}
+
+/// Load an existing project, downloading using the user's configuration.
+/// To use a specific configuration, use `builder` instead.
 pub fn load(path: impl AsRef<Path>) -> Result<Box<dyn Project>, ChandlerError> {
     let path = path.as_ref();
 
+    if exists_at(path).is_none() {
+        return Err(ChandlerError::LoadProject("No project found".into()));
+    }
+
+    builder()
+        .path(Some(path))
+        .use_chandler_config(true)?
+        .use_sites_config(true)?
+        .load_or_create()
+}
+
+/// Load an existing project, using an existing download engine.
+/// This can be used to share a single download engine between multiple projects.
+pub fn load_with_engine(
+    path: &Path,
+    update_config: UpdateConfig,
+    download_engine: Arc<DownloadEngine>,
+) -> Result<Box<dyn Project>, ChandlerError> {
     if v3::V3Project::exists_at(path) {
-        Ok(Box::new(v3::V3Project::load(path)?))
+        Ok(Box::new(v3::V3Project::load(path, update_config, download_engine)?))
     } else if v2::V2Project::exists_at(path) {
-        Ok(Box::new(v2::V2Project::load(path)?))
+        Ok(Box::new(v2::V2Project::load(path, update_config, download_engine)?))
     } else {
         Err(ChandlerError::LoadProject("No project found".into()))
     }
 }
 
+/// Download the thread page and detect which parser to use for it.
+fn detect_parser_from_url(url: &str, download_engine: &DownloadEngine) -> Result<ParserType, ChandlerError> {
+    struct DetectUiHandler;
+
+    impl ChandlerUiHandler for DetectUiHandler {
+        fn event(&mut self, _: &UiEvent) {}
+        fn is_cancelled(&self) -> bool {
+            false
+        }
+    }
+
+    info!("Detecting parser for unknown site: {}", url);
+
+    let file_path = util::temp_file_path("detect", "html");
+
+    let result = download_engine
+        .download_file(url, &file_path, None, &mut DetectUiHandler)
+        .and_then(|result| match result {
+            DownloadResult::Success { .. } => threadupdater::detect_parser(&file_path),
+            result => Err(ChandlerError::Other(
+                format!("Unexpected download result: {:?}", result).into(),
+            )),
+        });
+
+    // Clean up the downloaded page, as it is only needed for detection.
+    let _ = fs::remove_file(&file_path);
+
+    result
+}
+
+fn new_download_engine(
+    config: &ChandlerConfig,
+    sites_config: Option<&SitesConfig>,
+) -> Result<Arc<DownloadEngine>, ChandlerError> {
+    let mut download_engine = DownloadEngine::new(&config.download)?;
+
+    // Apply per-site rate limits.
+    if let Some(sites_config) = sites_config {
+        download_engine = download_engine.with_sites_config(sites_config);
+    }
+
+    Ok(Arc::new(download_engine))
+}
+
 pub fn builder() -> CreateProjectBuilder {
     CreateProjectBuilder::default()
 }
@@ -179,6 +370,12 @@ impl CreateProjectBuilder {
         self
     }
 
+    pub fn download_engine(mut self, download_engine: Option<Arc<DownloadEngine>>) -> Self {
+        self.download_engine = download_engine;
+
+        self
+    }
+
     pub fn config_path(mut self, path: Option<&Path>) -> Self {
         self.config_path = path.map(|p| p.to_path_buf());
 
@@ -210,10 +407,72 @@ impl CreateProjectBuilder {
         Ok(self)
     }
 
+    /// Create a download engine from the configuration.
+    /// This can be used to share a single download engine between multiple projects.
+    pub fn create_download_engine(&self) -> Result<Arc<DownloadEngine>, ChandlerError> {
+        let config_path = self.config_path.clone().or_else(config::get_default_config_path);
+
+        let config = self.load_config(config_path.as_deref())?;
+        let sites_config = self.load_sites_config(config_path.as_deref())?;
+
+        new_download_engine(&config, sites_config.as_ref())
+    }
+
+    fn load_config(&self, config_path: Option<&Path>) -> Result<ChandlerConfig, ChandlerError> {
+        let config = if let Some(config) = &self.config {
+            // If a config was explicitly specified, use it.
+            Some(config.clone())
+        } else if let Some(config_file) = &self.config_file {
+            // ... otherwise, if a specific file was specified, try to load it.
+            Some(ChandlerConfig::from_file(config_file)?)
+        } else if self.use_chandler_config {
+            // ... otherwise, if it was specified to load the user's config ...
+            if let Some(config_path) = config_path {
+                // If a config path was available, try to load the config from it.
+                Some(ChandlerConfig::from_location(config_path)?)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        Ok(config.unwrap_or_default())
+    }
+
+    fn load_sites_config(&self, config_path: Option<&Path>) -> Result<Option<SitesConfig>, ChandlerError> {
+        Ok(if let Some(sites_file) = &self.sites_file {
+            // If a specific file was specified, try to load it.
+            Some(SitesConfig::from_file(sites_file)?)
+        } else if self.use_sites_config {
+            // ... otherwise, if it was specified to load the user's sites config ...
+            if let Some(config_path) = config_path {
+                // If a config path was available, try to load the sites config from it.
+                Some(SitesConfig::from_location(config_path)?)
+            } else {
+                None
+            }
+        } else {
+            None
+        })
+    }
+
     pub fn load_or_create(self) -> Result<Box<dyn Project>, ChandlerError> {
+        // Use specified config path, or try to get the default one.
+        let config_path = self.config_path.clone().or_else(config::get_default_config_path);
+
+        let config = self.load_config(config_path.as_deref())?;
+        let sites_config = self.load_sites_config(config_path.as_deref())?;
+
+        let download_engine = if let Some(download_engine) = self.download_engine {
+            download_engine
+        } else {
+            new_download_engine(&config, sites_config.as_ref())?
+        };
+
         if let Some(path) = &self.path {
             if exists_at(path).is_some() {
-                return load(path);
+                return load_with_engine(path, config.update, download_engine);
             }
         }
 
@@ -221,78 +480,59 @@ impl CreateProjectBuilder {
             let mut path = self.path;
             let format = self.format;
             let mut parser = self.parser;
-
-            // Use specified config path, or try to get the default one.
-            let config_path = self.config_path.or_else(config::get_default_config_path);
-
-            let config = if let Some(config) = self.config {
-                // If a config was explicitly specified, use it.
-                Some(config)
-            } else if let Some(config_file) = &self.config_file {
-                // ... otherwise, if a specific file was specified, try to load it.
-                Some(ChandlerConfig::from_file(config_file)?)
-            } else if self.use_chandler_config {
-                // ... otherwise, if it was specified to load the user's config ...
-                if let Some(config_path) = &config_path {
-                    // If a config path was available, try to load the config from it.
-                    Some(ChandlerConfig::from_location(config_path)?)
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
-
-            let config = config.unwrap_or_default();
+            let mut parser_detected = false;
+            let mut api: Option<ThreadApi> = None;
 
             let site_resolver = if let Some(site_resolver) = self.site_resolver {
                 Some(site_resolver)
-            } else if let Some(sites_file) = &self.sites_file {
-                // ... otherwise, if a specific file was specified, try to load it.
-                Some(Box::new(SitesConfig::from_file(sites_file)?) as Box<dyn SiteResolver>)
-            } else if self.use_sites_config {
-                // ... otherwise, if it was specified to load the user's sites config ...
-                if let Some(config_path) = &config_path {
-                    // If a config path was available, try to load the sites config from it.
-                    Some(Box::new(SitesConfig::from_location(config_path)?) as Box<dyn SiteResolver>)
-                } else {
-                    None
-                }
             } else {
-                None
+                // ... otherwise, use the sites config if one was loaded.
+                sites_config.map(|s| Box::new(s) as Box<dyn SiteResolver>)
             };
 
             if let Some(site_resolver) = site_resolver {
-                let site_info = if let Some(site_info) = site_resolver.resolve_site(&url)? {
+                let resolved_site_info = site_resolver.resolve_site(&url)?;
+                let is_known_site = resolved_site_info.is_some();
+
+                let site_info = if let Some(site_info) = resolved_site_info {
                     site_info
                 } else {
                     site_resolver::unknown_site(&url)?
                 };
 
                 if path.is_none() {
-                    let download_path = if let Some(download_path) = config.download_path {
-                        util::normalize_path(download_path)
-                    } else if let Some(os_download_path) = dirs::download_dir() {
-                        os_download_path.join("chandler3")
-                    } else {
-                        return Err(ChandlerError::Config(
-                            "No default download directory found. A download path must be specified in the Chandler config file."
-                                .into(),
-                        ));
-                    };
+                    let download_path = config.get_download_path()?;
 
                     let new_path = download_path.join(site_info.name).join(site_info.path);
 
                     // If a project already exists at the generated path, load it.
                     if exists_at(&new_path).is_some() {
-                        return load(&new_path);
+                        return load_with_engine(&new_path, config.update, download_engine);
                     }
 
                     path = Some(new_path);
                 }
 
                 if parser.is_none() {
-                    parser = Some(site_info.parser);
+                    if is_known_site {
+                        parser = Some(site_info.parser);
+
+                        // Only use the site's API if the site's parser is used, as they go together.
+                        api = site_info.api;
+                    } else {
+                        // The site is unknown, so try to detect which parser to use from the thread page.
+                        match detect_parser_from_url(&url, &download_engine) {
+                            Ok(detected_parser) => {
+                                parser = Some(detected_parser);
+                                parser_detected = true;
+                            }
+                            Err(err) => {
+                                warn!("Parser detection failed, using basic parser: {}", err);
+
+                                parser = Some(site_info.parser);
+                            }
+                        }
+                    }
                 }
             }
 
@@ -308,8 +548,24 @@ impl CreateProjectBuilder {
             };
 
             Ok(match format {
-                ProjectFormat::V2 => Box::new(v2::V2Project::create(&path, &url, parser)?),
-                ProjectFormat::V3 => Box::new(v3::V3Project::create(&path, &url, parser)?),
+                ProjectFormat::V2 => Box::new(v2::V2Project::create(
+                    &path,
+                    &url,
+                    parser,
+                    parser_detected,
+                    api,
+                    config.update,
+                    download_engine,
+                )?),
+                ProjectFormat::V3 => Box::new(v3::V3Project::create(
+                    &path,
+                    &url,
+                    parser,
+                    parser_detected,
+                    api,
+                    config.update,
+                    download_engine,
+                )?),
             })
         } else {
             Err(ChandlerError::LoadProject(
@@ -0,0 +1,33 @@
+// This module is only used in tests.
+
+use crate::download::{TestServer, TestUiHandler};
+use crate::misc::site_resolver::SiteInfo;
+
+use super::*;
+
+/// Tinyboard thread with a subject, a timestamp and a file, for testing exports.
+pub const TEST_THREAD_HTML: &str = r#"<html><body><div class="thread"><div class="post op" id="op_1"><p class="intro"><span class="subject">Cats</span> <time datetime="2024-01-01T00:00:00Z">01/01/24</time></p><div class="body">Post cats</div></div><div class="post reply" id="reply_2"><div class="file"><p class="fileinfo">File: <a href="/b/src/2.png">2.png</a> <span class="unimportant">(4 B, 1x2, <span class="postfilename">cat.png</span>)</span></p></div><div class="body">Cat</div></div></div></body></html>"#;
+
+/// Site resolver that does not recognize any site.
+pub struct UnknownSiteResolver;
+
+impl SiteResolver for UnknownSiteResolver {
+    fn resolve_site(&self, _url: &str) -> Result<Option<SiteInfo>, ChandlerError> {
+        Ok(None)
+    }
+}
+
+/// Create a project for the thread at `/b/res/1.html` on the server, and update it once.
+pub fn test_project(server: &TestServer, path: &Path, parser: Option<ParserType>) -> Box<dyn Project> {
+    let mut project = builder()
+        .url(&server.url("/b/res/1.html"))
+        .path(Some(path))
+        .parser(parser)
+        .site_resolver(Some(Box::new(UnknownSiteResolver)))
+        .load_or_create()
+        .unwrap();
+
+    project.update(&mut TestUiHandler::default()).unwrap();
+
+    project
+}
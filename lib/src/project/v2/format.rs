@@ -67,7 +67,7 @@ impl State {
 impl From<&ProjectState> for Config {
     fn from(state: &ProjectState) -> Self {
         Self {
-            parser: state.parser.into(),
+            parser: (&state.parser).into(),
             url: state.thread_url.clone(),
             download_extensions: state.download_extensions.clone(),
         }
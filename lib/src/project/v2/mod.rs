@@ -7,6 +7,7 @@ use url::Url;
 
 mod format;
 
+use crate::download::RetryState;
 use crate::error::*;
 use crate::threadupdater::CreateThreadUpdater;
 use crate::ui::*;
@@ -46,7 +47,21 @@ impl V2LinkPathGenerator {
 impl ProjectLoader for V2Project {
     type P = V2Project;
 
-    fn create(path: &Path, url: &str, parser: ParserType) -> Result<Self::P, ChandlerError> {
+    fn create(
+        path: &Path,
+        url: &str,
+        parser: ParserType,
+        parser_detected: bool,
+        api: Option<ThreadApi>,
+        update_config: UpdateConfig,
+        download_engine: Arc<DownloadEngine>,
+    ) -> Result<Self::P, ChandlerError> {
+        if let ParserType::Custom(_) = parser {
+            return Err(ChandlerError::CreateProject(
+                "Custom parsers are not supported by the V2 project format.".into(),
+            ));
+        }
+
         let root_path = path.to_path_buf();
         let project_path = root_path.join(PROJECT_DIR_NAME);
         let originals_path = project_path.join(ORIGINALS_DIR_NAME);
@@ -80,13 +95,19 @@ impl ProjectLoader for V2Project {
             thread_url: url.to_owned(),
             download_extensions: DEFAULT_DOWNLOAD_EXTENSIONS.iter().map(|s| (*s).to_owned()).collect(),
             parser,
+            parser_detected,
+            api,
+            update_config,
             link_path_generator: Box::new(link_path_generator),
+            download_engine,
             thread: None,
             is_dead: false,
+            status: ThreadStatus::default(),
             last_modified: None,
             new_links: Vec::new(),
             failed_links: Vec::new(),
             seen_links: HashSet::new(),
+            manifest: Manifest::default(),
         };
 
         // Save initial project config and state.
@@ -104,7 +125,11 @@ impl ProjectLoader for V2Project {
         Ok(project)
     }
 
-    fn load(path: &Path) -> Result<Self::P, ChandlerError> {
+    fn load(
+        path: &Path,
+        update_config: UpdateConfig,
+        download_engine: Arc<DownloadEngine>,
+    ) -> Result<Self::P, ChandlerError> {
         let root_path = path.to_path_buf();
 
         let pidlock = if let Some(pidlock) = acquire_pidlock(&root_path, PID_FILE_NAME) {
@@ -126,12 +151,12 @@ impl ProjectLoader for V2Project {
         let config = pf::Config::load(config_file_path)?;
         let state = pf::State::load(&state_file_path)?;
 
-        let parser: ParserType = config.parser.into();
+        let parser = config.parser.parser_type(None)?;
         let link_path_generator = V2LinkPathGenerator::new(&config.url)?;
 
         // Try to load current thread.
         let thread = parser
-            .create_thread_updater_from(&root_path.join(THREAD_FILE_NAME))
+            .create_thread_updater_from(&root_path.join(THREAD_FILE_NAME), update_config)
             .ok();
 
         // Pull failed (in V2 projects, this includes unprocessed) links out of project state.
@@ -146,7 +171,11 @@ impl ProjectLoader for V2Project {
                     None
                 }
             })
-            .map(|(url, path)| LinkInfo { url, path })
+            .map(|(url, path)| LinkInfo {
+                url,
+                path,
+                retry: RetryState::default(),
+            })
             .collect();
 
         let seen_links: HashSet<String> = failed_links.iter().map(|l| l.url.clone()).collect();
@@ -158,13 +187,19 @@ impl ProjectLoader for V2Project {
             thread_url: config.url,
             download_extensions: config.download_extensions,
             parser,
+            parser_detected: false,
+            api: None,
+            update_config,
             link_path_generator: Box::new(link_path_generator),
+            download_engine,
             thread,
             is_dead: state.is_dead,
+            status: ThreadStatus::default(),
             last_modified: state.last_modified,
             new_links: Vec::new(),
             failed_links,
             seen_links,
+            manifest: Manifest::default(),
         };
 
         Ok(Self {
@@ -177,6 +212,33 @@ impl ProjectLoader for V2Project {
     fn exists_at(path: &Path) -> bool {
         path.join(PROJECT_DIR_NAME).is_dir()
     }
+
+    fn modified_at(path: &Path) -> Option<DateTime<Utc>> {
+        latest_modified_time(&[
+            path.join(THREAD_FILE_NAME),
+            path.join(PROJECT_DIR_NAME).join(STATE_FILE_NAME),
+        ])
+    }
+
+    fn read_summary(path: &Path) -> Result<ProjectSummary, ChandlerError> {
+        let project_path = path.join(PROJECT_DIR_NAME);
+        let thread_file_path = path.join(THREAD_FILE_NAME);
+
+        let config = pf::Config::load(project_path.join(CONFIG_FILE_NAME))?;
+        let state = pf::State::load(project_path.join(STATE_FILE_NAME))?;
+
+        let parser = config.parser.parser_type(None)?;
+
+        // V2 projects do not keep track of downloaded files.
+        Ok(ProjectSummary {
+            root_path: path.to_path_buf(),
+            posts: read_posts(&parser, &thread_file_path)?,
+            thread_file_path,
+            thread_url: config.url,
+            is_dead: state.is_dead,
+            downloaded_file_count: 0,
+        })
+    }
 }
 
 impl V2Project {
@@ -200,7 +262,10 @@ impl Project for V2Project {
         Ok(ProjectUpdateResult {
             was_updated: update_result.was_updated,
             is_dead: self.state.is_dead,
+            status: self.state.status,
             new_post_count: update_result.new_post_count,
+            edited_post_count: update_result.edited_post_count,
+            deleted_post_count: update_result.deleted_post_count,
             new_file_count: update_result.new_link_count,
         })
     }
@@ -223,15 +288,63 @@ impl Project for V2Project {
         Ok(())
     }
 
+    fn export_json(&self, file_path: &Path) -> Result<(), ChandlerError> {
+        export_json(&self.state, file_path)
+    }
+
+    fn export_html(&self, file_path: &Path, options: &HtmlExportOptions) -> Result<(), ChandlerError> {
+        export_html(&self.state, file_path, *options)
+    }
+
+    fn export_warc(&self, file_path: &Path) -> Result<(), ChandlerError> {
+        export_warc(&self.state, file_path)
+    }
+
+    fn import_warc(&mut self, file_path: &Path, ui_handler: &mut dyn ChandlerUiHandler) -> Result<(), ChandlerError> {
+        import_warc(&mut self.state, file_path, ui_handler)
+    }
+
     fn save(&self) -> Result<(), ChandlerError> {
         self.save_state()?;
 
         Ok(())
     }
 
+    fn find_thread_links(&self, site_resolver: &dyn SiteResolver) -> Result<Vec<String>, ChandlerError> {
+        find_thread_links(&self.state, site_resolver)
+    }
+
+    fn replace_thread_links(&mut self, thread_file_paths: &HashMap<String, PathBuf>) -> Result<(), ChandlerError> {
+        if replace_thread_links(&mut self.state, thread_file_paths)? > 0 {
+            self.state.write_thread()?;
+        }
+
+        Ok(())
+    }
+
     fn get_path(&self) -> &Path {
         &self.state.root_path
     }
+
+    fn get_url(&self) -> &str {
+        &self.state.thread_url
+    }
+
+    fn get_thread_file_path(&self) -> &Path {
+        &self.state.thread_file_path
+    }
+
+    fn get_manifest(&self) -> &Manifest {
+        &self.state.manifest
+    }
+
+    fn is_dead(&self) -> bool {
+        self.state.is_dead
+    }
+
+    fn get_posts(&self) -> Result<Vec<Post>, ChandlerError> {
+        get_posts(&self.state)
+    }
 }
 
 impl LinkPathGenerator for V2LinkPathGenerator {
@@ -248,4 +361,8 @@ impl LinkPathGenerator for V2LinkPathGenerator {
             Ok(None)
         }
     }
+
+    fn generate_url(&self, path: &str) -> Option<String> {
+        content_url(&self.thread_url, path)
+    }
 }
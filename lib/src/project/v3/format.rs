@@ -2,8 +2,10 @@ use std::collections::BTreeSet;
 
 use serde_derive::{Deserialize, Serialize};
 
-pub use crate::config::Parser;
+use crate::config::chandler::{EditPolicy, SanitizeProfile};
+pub use crate::config::{CustomParserDef, Parser};
 use crate::error::*;
+use crate::threadapi::ThreadApi;
 use crate::util;
 
 use super::*;
@@ -11,14 +13,36 @@ use super::*;
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
     pub parser: Parser,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_parser: Option<CustomParserDef>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub parser_detected: bool,
     pub url: String,
     pub download_extensions: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api: Option<ThreadApi>,
+    /// Edit policy to use for this project, instead of the one in the configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edit_policy: Option<EditPolicy>,
+    /// Sanitize profile to use for this project, instead of the one in the configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sanitize: Option<SanitizeProfile>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Link {
     pub url: String,
     pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<Retry>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Retry {
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt: Option<DateTime<Utc>>,
+    pub permanent: bool,
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -31,6 +55,9 @@ pub struct Links {
 pub struct State {
     pub last_modified: Option<DateTime<Utc>>,
     pub is_dead: bool,
+
+    #[serde(default)]
+    pub status: ThreadStatus,
     pub links: Links,
 }
 
@@ -71,9 +98,14 @@ impl State {
 impl From<&ProjectState> for Config {
     fn from(state: &ProjectState) -> Self {
         Self {
-            parser: state.parser.into(),
+            parser: (&state.parser).into(),
+            custom_parser: state.parser.custom_parser_def().cloned(),
+            parser_detected: state.parser_detected,
             url: state.thread_url.clone(),
             download_extensions: state.download_extensions.clone(),
+            api: state.api.clone(),
+            edit_policy: None,
+            sanitize: None,
         }
     }
 }
@@ -83,24 +115,47 @@ impl From<&ProjectState> for State {
         Self {
             last_modified: state.last_modified,
             is_dead: state.is_dead,
+            status: state.status,
             links: Links {
-                new: state
-                    .new_links
-                    .iter()
-                    .map(|l| Link {
-                        url: l.url.clone(),
-                        path: l.path.clone(),
-                    })
-                    .collect(),
-                failed: state
-                    .failed_links
-                    .iter()
-                    .map(|l| Link {
-                        url: l.url.clone(),
-                        path: l.path.clone(),
-                    })
-                    .collect(),
+                new: state.new_links.iter().map(Link::from).collect(),
+                failed: state.failed_links.iter().map(Link::from).collect(),
+            },
+        }
+    }
+}
+
+impl From<&LinkInfo> for Link {
+    fn from(link: &LinkInfo) -> Self {
+        Self {
+            url: link.url.clone(),
+            path: link.path.clone(),
+            retry: if link.retry.attempts > 0 {
+                Some(Retry::from(&link.retry))
+            } else {
+                None
             },
         }
     }
 }
+
+impl From<&RetryState> for Retry {
+    fn from(retry: &RetryState) -> Self {
+        Self {
+            attempts: retry.attempts,
+            last_error: retry.last_error.clone(),
+            next_attempt: retry.next_attempt,
+            permanent: retry.permanent,
+        }
+    }
+}
+
+impl From<Retry> for RetryState {
+    fn from(retry: Retry) -> Self {
+        Self {
+            attempts: retry.attempts,
+            last_error: retry.last_error,
+            next_attempt: retry.next_attempt,
+            permanent: retry.permanent,
+        }
+    }
+}
@@ -7,6 +7,7 @@ use url::Url;
 
 mod format;
 
+use crate::download::RetryState;
 use crate::error::*;
 use crate::threadupdater::CreateThreadUpdater;
 use crate::ui::*;
@@ -22,12 +23,14 @@ const CONTENT_DIR_NAME: &str = "content";
 const ORIGINALS_DIR_NAME: &str = "originals";
 const CONFIG_FILE_NAME: &str = "thread.json";
 const STATE_FILE_NAME: &str = "state.json";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
 const THREAD_FILE_NAME: &str = "thread.html";
 const PID_FILE_NAME: &str = "pid.lock";
 
 pub struct V3Project {
     state: ProjectState,
     state_file_path: PathBuf,
+    manifest_file_path: PathBuf,
     _pidlock: PidLock,
 }
 
@@ -47,7 +50,15 @@ impl V3LinkPathGenerator {
 impl ProjectLoader for V3Project {
     type P = V3Project;
 
-    fn create(path: &Path, url: &str, parser: ParserType) -> Result<Self::P, ChandlerError> {
+    fn create(
+        path: &Path,
+        url: &str,
+        parser: ParserType,
+        parser_detected: bool,
+        api: Option<ThreadApi>,
+        update_config: UpdateConfig,
+        download_engine: Arc<DownloadEngine>,
+    ) -> Result<Self::P, ChandlerError> {
         let root_path = path.to_path_buf();
         let project_path = root_path.join(PROJECT_DIR_NAME);
         let originals_path = project_path.join(ORIGINALS_DIR_NAME);
@@ -70,6 +81,7 @@ impl ProjectLoader for V3Project {
 
         let config_file_path = project_path.join(CONFIG_FILE_NAME);
         let state_file_path = project_path.join(STATE_FILE_NAME);
+        let manifest_file_path = project_path.join(MANIFEST_FILE_NAME);
         let thread_file_path = root_path.join(THREAD_FILE_NAME);
 
         let link_path_generator = V3LinkPathGenerator::new(url)?;
@@ -81,13 +93,19 @@ impl ProjectLoader for V3Project {
             thread_url: url.to_owned(),
             download_extensions: DEFAULT_DOWNLOAD_EXTENSIONS.iter().map(|s| (*s).to_owned()).collect(),
             parser,
+            parser_detected,
+            api,
+            update_config,
             link_path_generator: Box::new(link_path_generator),
+            download_engine,
             thread: None,
             is_dead: false,
+            status: ThreadStatus::default(),
             last_modified: None,
             new_links: Vec::new(),
             failed_links: Vec::new(),
             seen_links: HashSet::new(),
+            manifest: Manifest::default(),
         };
 
         // Save initial project config and state.
@@ -97,6 +115,7 @@ impl ProjectLoader for V3Project {
         let project = Self {
             state,
             state_file_path,
+            manifest_file_path,
             _pidlock: pidlock,
         };
 
@@ -105,7 +124,11 @@ impl ProjectLoader for V3Project {
         Ok(project)
     }
 
-    fn load(path: &Path) -> Result<Self::P, ChandlerError> {
+    fn load(
+        path: &Path,
+        update_config: UpdateConfig,
+        download_engine: Arc<DownloadEngine>,
+    ) -> Result<Self::P, ChandlerError> {
         let root_path = path.to_path_buf();
 
         let pidlock = if let Some(pidlock) = acquire_pidlock(&root_path, PID_FILE_NAME) {
@@ -121,18 +144,31 @@ impl ProjectLoader for V3Project {
 
         let config_file_path = project_path.join(CONFIG_FILE_NAME);
         let state_file_path = project_path.join(STATE_FILE_NAME);
+        let manifest_file_path = project_path.join(MANIFEST_FILE_NAME);
         let thread_file_path = root_path.join(THREAD_FILE_NAME);
 
         // Load project config and state.
         let config = pf::Config::load(config_file_path)?;
         let state = pf::State::load(&state_file_path)?;
 
-        let parser: ParserType = config.parser.into();
+        // Load manifest, if there is one. Projects created before it was introduced will not have one.
+        let manifest = if manifest_file_path.exists() {
+            Manifest::load(&manifest_file_path)?
+        } else {
+            Manifest::default()
+        };
+
+        let parser = config.parser.parser_type(config.custom_parser.as_ref())?;
+        // Settings specified in the project override those in the configuration.
+        let update_config = UpdateConfig {
+            edit_policy: config.edit_policy.unwrap_or(update_config.edit_policy),
+            sanitize: config.sanitize.unwrap_or(update_config.sanitize),
+        };
         let link_path_generator = V3LinkPathGenerator::new(&config.url)?;
 
         // Try to load current thread.
         let thread = parser
-            .create_thread_updater_from(&root_path.join(THREAD_FILE_NAME))
+            .create_thread_updater_from(&root_path.join(THREAD_FILE_NAME), update_config)
             .ok();
 
         let new_links: Vec<LinkInfo> = state
@@ -142,6 +178,7 @@ impl ProjectLoader for V3Project {
             .map(|l| LinkInfo {
                 url: l.url,
                 path: l.path,
+                retry: l.retry.map(Into::into).unwrap_or_default(),
             })
             .collect();
 
@@ -152,6 +189,7 @@ impl ProjectLoader for V3Project {
             .map(|l| LinkInfo {
                 url: l.url,
                 path: l.path,
+                retry: l.retry.map(Into::into).unwrap_or_default(),
             })
             .collect();
 
@@ -159,6 +197,7 @@ impl ProjectLoader for V3Project {
             .iter()
             .chain(failed_links.iter())
             .map(|l| l.url.clone())
+            .chain(manifest.files.keys().cloned())
             .collect();
 
         let state = ProjectState {
@@ -168,18 +207,25 @@ impl ProjectLoader for V3Project {
             thread_url: config.url,
             download_extensions: config.download_extensions,
             parser,
+            parser_detected: config.parser_detected,
+            api: config.api,
+            update_config,
             link_path_generator: Box::new(link_path_generator),
+            download_engine,
             thread,
             is_dead: state.is_dead,
+            status: state.status,
             last_modified: state.last_modified,
             new_links,
             failed_links,
             seen_links,
+            manifest,
         };
 
         Ok(Self {
             state,
             state_file_path,
+            manifest_file_path,
             _pidlock: pidlock,
         })
     }
@@ -187,11 +233,45 @@ impl ProjectLoader for V3Project {
     fn exists_at(path: &Path) -> bool {
         path.join(PROJECT_DIR_NAME).is_dir()
     }
+
+    fn modified_at(path: &Path) -> Option<DateTime<Utc>> {
+        latest_modified_time(&[
+            path.join(THREAD_FILE_NAME),
+            path.join(PROJECT_DIR_NAME).join(STATE_FILE_NAME),
+        ])
+    }
+
+    fn read_summary(path: &Path) -> Result<ProjectSummary, ChandlerError> {
+        let project_path = path.join(PROJECT_DIR_NAME);
+        let manifest_file_path = project_path.join(MANIFEST_FILE_NAME);
+        let thread_file_path = path.join(THREAD_FILE_NAME);
+
+        let config = pf::Config::load(project_path.join(CONFIG_FILE_NAME))?;
+        let state = pf::State::load(project_path.join(STATE_FILE_NAME))?;
+
+        let manifest = if manifest_file_path.exists() {
+            Manifest::load(&manifest_file_path)?
+        } else {
+            Manifest::default()
+        };
+
+        let parser = config.parser.parser_type(config.custom_parser.as_ref())?;
+
+        Ok(ProjectSummary {
+            root_path: path.to_path_buf(),
+            posts: read_posts(&parser, &thread_file_path)?,
+            thread_file_path,
+            thread_url: config.url,
+            is_dead: state.is_dead,
+            downloaded_file_count: manifest.downloaded_count(),
+        })
+    }
 }
 
 impl V3Project {
     pub fn save_state(&self) -> Result<(), ChandlerError> {
         pf::State::from(&self.state).save(&self.state_file_path)?;
+        self.state.manifest.save(&self.manifest_file_path)?;
 
         Ok(())
     }
@@ -210,7 +290,10 @@ impl Project for V3Project {
         Ok(ProjectUpdateResult {
             was_updated: update_result.was_updated,
             is_dead: self.state.is_dead,
+            status: self.state.status,
             new_post_count: update_result.new_post_count,
+            edited_post_count: update_result.edited_post_count,
+            deleted_post_count: update_result.deleted_post_count,
             new_file_count: update_result.new_link_count,
         })
     }
@@ -233,15 +316,63 @@ impl Project for V3Project {
         Ok(())
     }
 
+    fn export_json(&self, file_path: &Path) -> Result<(), ChandlerError> {
+        export_json(&self.state, file_path)
+    }
+
+    fn export_html(&self, file_path: &Path, options: &HtmlExportOptions) -> Result<(), ChandlerError> {
+        export_html(&self.state, file_path, *options)
+    }
+
+    fn export_warc(&self, file_path: &Path) -> Result<(), ChandlerError> {
+        export_warc(&self.state, file_path)
+    }
+
+    fn import_warc(&mut self, file_path: &Path, ui_handler: &mut dyn ChandlerUiHandler) -> Result<(), ChandlerError> {
+        import_warc(&mut self.state, file_path, ui_handler)
+    }
+
     fn save(&self) -> Result<(), ChandlerError> {
         self.save_state()?;
 
         Ok(())
     }
 
+    fn find_thread_links(&self, site_resolver: &dyn SiteResolver) -> Result<Vec<String>, ChandlerError> {
+        find_thread_links(&self.state, site_resolver)
+    }
+
+    fn replace_thread_links(&mut self, thread_file_paths: &HashMap<String, PathBuf>) -> Result<(), ChandlerError> {
+        if replace_thread_links(&mut self.state, thread_file_paths)? > 0 {
+            self.state.write_thread()?;
+        }
+
+        Ok(())
+    }
+
     fn get_path(&self) -> &Path {
         &self.state.root_path
     }
+
+    fn get_url(&self) -> &str {
+        &self.state.thread_url
+    }
+
+    fn get_thread_file_path(&self) -> &Path {
+        &self.state.thread_file_path
+    }
+
+    fn get_manifest(&self) -> &Manifest {
+        &self.state.manifest
+    }
+
+    fn is_dead(&self) -> bool {
+        self.state.is_dead
+    }
+
+    fn get_posts(&self) -> Result<Vec<Post>, ChandlerError> {
+        get_posts(&self.state)
+    }
 }
 
 impl LinkPathGenerator for V3LinkPathGenerator {
@@ -258,4 +389,59 @@ impl LinkPathGenerator for V3LinkPathGenerator {
             Ok(None)
         }
     }
+
+    fn generate_url(&self, path: &str) -> Option<String> {
+        content_url(
+            &self.thread_url,
+            path.strip_prefix(CONTENT_DIR_NAME)?.strip_prefix('/')?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::chandler::{EditPolicy, SanitizeProfile};
+    use crate::download::test_dir;
+
+    use super::*;
+
+    #[test]
+    fn resolves_update_config_when_loading() {
+        let path = test_dir("v3_update_config");
+        let download_engine = builder().create_download_engine().unwrap();
+
+        let project = V3Project::create(
+            &path,
+            "http://127.0.0.1/b/res/1.html",
+            ParserType::Basic,
+            false,
+            None,
+            UpdateConfig::default(),
+            Arc::clone(&download_engine),
+        )
+        .unwrap();
+        drop(project);
+
+        let update_config = UpdateConfig {
+            edit_policy: EditPolicy::Replace,
+            sanitize: SanitizeProfile::Strict,
+        };
+
+        // The configuration should be used, as the project does not specify its own.
+        let project = V3Project::load(&path, update_config, Arc::clone(&download_engine)).unwrap();
+        assert_eq!(project.state.update_config.edit_policy, EditPolicy::Replace);
+        assert_eq!(project.state.update_config.sanitize, SanitizeProfile::Strict);
+        drop(project);
+
+        // Settings in the project should override the configuration.
+        let config_file_path = path.join(PROJECT_DIR_NAME).join(CONFIG_FILE_NAME);
+        let mut config = pf::Config::load(&config_file_path).unwrap();
+        config.edit_policy = Some(EditPolicy::KeepBoth);
+        config.sanitize = Some(SanitizeProfile::Paranoid);
+        config.save(&config_file_path).unwrap();
+
+        let project = V3Project::load(&path, update_config, download_engine).unwrap();
+        assert_eq!(project.state.update_config.edit_policy, EditPolicy::KeepBoth);
+        assert_eq!(project.state.update_config.sanitize, SanitizeProfile::Paranoid);
+    }
 }
@@ -0,0 +1,358 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::path::Path;
+
+use serde_derive::Deserialize;
+use url::Url;
+
+use crate::error::*;
+use crate::html::escape;
+use crate::threadparser::ThreadStatus;
+use crate::util;
+
+/// Default base URL of the 4chan API.
+pub const DEFAULT_API_URL: &str = "https://a.4cdn.org";
+
+/// Base URL of uploaded files.
+const MEDIA_URL: &str = "//i.4cdn.org";
+
+/// Base URL of static site files.
+const STATIC_URL: &str = "//s.4cdn.org";
+
+#[derive(Debug, Deserialize)]
+pub struct FourchanApiThread {
+    pub posts: Vec<FourchanApiPost>,
+}
+
+/// A post, as returned by the 4chan API.
+///
+/// Only the fields used by Chandler are included.
+/// Flags are represented as integers, where 1 means true.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FourchanApiPost {
+    pub no: u32,
+    pub resto: u32,
+    pub sticky: u8,
+    pub closed: u8,
+    pub archived: u8,
+    pub archived_on: Option<i64>,
+    pub bumplimit: u8,
+    pub imagelimit: u8,
+    pub now: String,
+    pub time: i64,
+    pub name: Option<String>,
+    pub trip: Option<String>,
+    pub id: Option<String>,
+    pub capcode: Option<String>,
+    pub sub: Option<String>,
+    pub com: Option<String>,
+    pub tim: Option<u64>,
+    pub filename: Option<String>,
+    pub ext: Option<String>,
+    pub fsize: u64,
+    pub md5: Option<String>,
+    pub w: u32,
+    pub h: u32,
+    pub tn_w: u32,
+    pub tn_h: u32,
+    pub filedeleted: u8,
+    pub spoiler: u8,
+}
+
+/// Get the API URL for a thread, given the API base URL and the thread's HTML URL.
+///
+/// Thread URLs are on the form "https://boards.4chan.org/{board}/thread/{id}[/{slug}]".
+pub fn get_thread_api_url(api_url: &str, thread_url: &str) -> Option<String> {
+    let thread_url = Url::parse(thread_url).ok()?;
+    let mut segments = thread_url.path_segments()?;
+
+    let board = segments.next()?;
+    if segments.next()? != "thread" {
+        return None;
+    }
+
+    let id: u32 = segments.next()?.parse().ok()?;
+
+    Some(format!(
+        "{}/{}/thread/{}.json",
+        api_url.trim_end_matches('/'),
+        board,
+        id
+    ))
+}
+
+/// Get the board name from a thread API URL.
+fn get_board(api_url: &str) -> Option<String> {
+    let api_url = Url::parse(api_url).ok()?;
+    let segments: Vec<&str> = api_url.path_segments()?.collect();
+
+    let thread_index = segments.iter().rposition(|s| *s == "thread")?;
+    if thread_index == 0 {
+        return None;
+    }
+
+    Some(segments[thread_index - 1].to_owned())
+}
+
+impl FourchanApiThread {
+    pub fn from_file(path: &Path) -> Result<Self, ChandlerError> {
+        let file = util::open_file(path).map_err(ChandlerError::OpenFile)?;
+
+        serde_json::from_reader(file).map_err(|err| {
+            ChandlerError::Other(Cow::Owned(format!(
+                "Error parsing 4chan API thread '{}': {}",
+                path.display(),
+                err
+            )))
+        })
+    }
+
+    pub fn op(&self) -> Option<&FourchanApiPost> {
+        self.posts.first()
+    }
+
+    /// Get the thread's status from the flags of the OP.
+    pub fn status(&self) -> ThreadStatus {
+        self.op().map_or_else(ThreadStatus::default, |op| ThreadStatus {
+            is_locked: op.closed == 1,
+            is_archived: op.archived == 1,
+            bump_limit_reached: op.bumplimit == 1,
+            image_limit_reached: op.imagelimit == 1,
+        })
+    }
+
+    /// Render the thread as an HTML document using 4chan's markup,
+    /// so that it can be merged by the 4chan thread parser.
+    pub fn render(&self, api_url: &str) -> Result<String, ChandlerError> {
+        let op = self
+            .op()
+            .ok_or_else(|| ChandlerError::Other("No posts found in 4chan API thread!".into()))?;
+
+        let board = get_board(api_url)
+            .ok_or_else(|| ChandlerError::Other(format!("Could not get board from API URL: {api_url}").into()))?;
+
+        let title = op
+            .sub
+            .as_deref()
+            .map_or_else(|| format!("/{board}/ - {}", op.no), |s| format!("/{board}/ - {s}"));
+
+        let mut html = String::new();
+
+        html.push_str("<!DOCTYPE html><html><head><meta charset=\"utf-8\">");
+        let _ = write!(html, "<title>{}</title>", escape(&title));
+        html.push_str("</head><body><div class=\"board\">");
+        let _ = write!(html, "<div class=\"thread\" id=\"t{}\">", op.no);
+
+        for post in self.posts.iter() {
+            render_post(&mut html, post, &board, post.no == op.no);
+        }
+
+        html.push_str("</div></div></body></html>");
+
+        Ok(html)
+    }
+}
+
+fn render_post(html: &mut String, post: &FourchanApiPost, board: &str, is_op: bool) {
+    let no = post.no;
+
+    if is_op {
+        let _ = write!(html, "<div class=\"postContainer opContainer\" id=\"pc{no}\">");
+        let _ = write!(html, "<div id=\"p{no}\" class=\"post op\">");
+
+        // On 4chan, the OP's file comes before the post info.
+        render_file(html, post, board);
+        render_post_info(html, post);
+    } else {
+        let _ = write!(html, "<div class=\"postContainer replyContainer\" id=\"pc{no}\">");
+        let _ = write!(html, "<div class=\"sideArrows\" id=\"sa{no}\">&gt;&gt;</div>");
+        let _ = write!(html, "<div id=\"p{no}\" class=\"post reply\">");
+
+        render_post_info(html, post);
+        render_file(html, post, board);
+    }
+
+    // The comment is already HTML.
+    let _ = write!(
+        html,
+        "<blockquote class=\"postMessage\" id=\"m{no}\">{}</blockquote>",
+        post.com.as_deref().unwrap_or_default()
+    );
+
+    html.push_str("</div></div>");
+}
+
+fn render_post_info(html: &mut String, post: &FourchanApiPost) {
+    let no = post.no;
+
+    let _ = write!(html, "<div class=\"postInfo desktop\" id=\"pi{no}\">");
+    let _ = write!(html, "<input type=\"checkbox\" name=\"{no}\" value=\"delete\"> ");
+
+    if let Some(sub) = &post.sub {
+        let _ = write!(html, "<span class=\"subject\">{}</span> ", escape(sub));
+    }
+
+    html.push_str("<span class=\"nameBlock\">");
+    let _ = write!(
+        html,
+        "<span class=\"name\">{}</span>",
+        escape(post.name.as_deref().unwrap_or("Anonymous"))
+    );
+
+    if let Some(trip) = &post.trip {
+        let _ = write!(html, " <span class=\"postertrip\">{}</span>", escape(trip));
+    }
+
+    if let Some(capcode) = &post.capcode {
+        let _ = write!(html, " <strong class=\"capcode\">## {}</strong>", escape(capcode));
+    }
+
+    if let Some(id) = &post.id {
+        let _ = write!(
+            html,
+            " <span class=\"posteruid\">(ID: <span class=\"hand\">{}</span>)</span>",
+            escape(id)
+        );
+    }
+
+    html.push_str("</span> ");
+
+    let _ = write!(
+        html,
+        "<span class=\"dateTime\" data-utc=\"{}\">{}</span> ",
+        post.time,
+        escape(&post.now)
+    );
+
+    let _ = write!(
+        html,
+        "<span class=\"postNum desktop\"><a href=\"#p{no}\" title=\"Link to this post\">No.</a><a href=\"#q{no}\" title=\"Reply to this post\">{no}</a>"
+    );
+
+    if post.sticky == 1 {
+        let _ = write!(
+            html,
+            " <img src=\"{STATIC_URL}/image/sticky.gif\" alt=\"Sticky\" title=\"Sticky\" class=\"stickyIcon retina\">"
+        );
+    }
+
+    if post.closed == 1 && post.archived != 1 {
+        let _ = write!(
+            html,
+            " <img src=\"{STATIC_URL}/image/closed.gif\" alt=\"Closed\" title=\"Closed\" class=\"closedIcon retina\">"
+        );
+    }
+
+    if post.archived == 1 {
+        let _ = write!(
+            html,
+            " <img src=\"{STATIC_URL}/image/archived.gif\" alt=\"Archived\" title=\"Archived\" class=\"archivedIcon retina\">"
+        );
+    }
+
+    html.push_str("</span></div>");
+}
+
+fn render_file(html: &mut String, post: &FourchanApiPost, board: &str) {
+    let no = post.no;
+
+    if post.filedeleted == 1 {
+        let _ = write!(
+            html,
+            "<div class=\"file\" id=\"f{no}\"><span class=\"fileThumb\"><img src=\"{STATIC_URL}/image/filedeleted-res.gif\" alt=\"File deleted.\" class=\"fileDeletedRes retina\"></span></div>"
+        );
+
+        return;
+    }
+
+    let (Some(tim), Some(ext)) = (post.tim, &post.ext) else {
+        return;
+    };
+
+    let file_url = format!("{MEDIA_URL}/{board}/{tim}{ext}");
+    let thumb_url = format!("{MEDIA_URL}/{board}/{tim}s.jpg");
+    let filename = format!("{}{}", post.filename.as_deref().unwrap_or_default(), ext);
+    let size = format_size(post.fsize);
+
+    let _ = write!(html, "<div class=\"file\" id=\"f{no}\">");
+    let _ = write!(
+        html,
+        "<div class=\"fileText\" id=\"fT{no}\">File: <a href=\"{file_url}\" target=\"_blank\">{}</a> ({size}, {}x{})</div>",
+        escape(&filename),
+        post.w,
+        post.h
+    );
+    let _ = write!(
+        html,
+        "<a class=\"fileThumb\" href=\"{file_url}\" target=\"_blank\"><img src=\"{thumb_url}\" alt=\"{size}\" data-md5=\"{}\" style=\"height: {}px; width: {}px;\" loading=\"lazy\"></a>",
+        escape(post.md5.as_deref().unwrap_or_default()),
+        post.tn_h,
+        post.tn_w
+    );
+    html.push_str("</div>");
+}
+
+/// Format a file size the same way 4chan does.
+fn format_size(size: u64) -> String {
+    if size >= 1024 * 1024 {
+        format!("{:.2} MB", size as f64 / (1024.0 * 1024.0))
+    } else if size >= 1024 {
+        format!("{} KB", size / 1024)
+    } else {
+        format!("{} B", size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html;
+    use crate::threadparser::fourchan::FourchanThread;
+    use crate::threadparser::{HtmlDocument, MergeableImageboardThread};
+
+    use super::*;
+
+    const THREAD_JSON: &str = r##"{"posts": [
+        {"no": 1, "resto": 0, "now": "01/01/24(Mon)00:00:00", "time": 1704067200, "name": "Anonymous",
+         "sub": "Test <thread>", "com": "OP text", "tim": 1704067200000, "filename": "image", "ext": ".png",
+         "fsize": 2048, "md5": "abc==", "w": 800, "h": 600, "tn_w": 250, "tn_h": 187, "archived": 1},
+        {"no": 3, "resto": 1, "now": "01/01/24(Mon)00:01:00", "time": 1704067260, "name": "Anonymous",
+         "com": "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br>Reply"}
+    ]}"##;
+
+    const API_URL: &str = "https://a.4cdn.org/g/thread/1.json";
+
+    #[test]
+    fn can_get_thread_api_url() {
+        assert_eq!(
+            get_thread_api_url(DEFAULT_API_URL, "https://boards.4chan.org/g/thread/12345/some-thread").as_deref(),
+            Some("https://a.4cdn.org/g/thread/12345.json")
+        );
+        assert_eq!(
+            get_thread_api_url("http://127.0.0.1:1234/api/", "https://boards.4chan.org/g/thread/12345").as_deref(),
+            Some("http://127.0.0.1:1234/api/g/thread/12345.json")
+        );
+        assert_eq!(get_thread_api_url(DEFAULT_API_URL, "https://boards.4chan.org/g/"), None);
+    }
+
+    #[test]
+    fn can_render_thread() {
+        let thread: FourchanApiThread = serde_json::from_str(THREAD_JSON).unwrap();
+
+        let rendered = thread.render(API_URL).unwrap();
+
+        assert!(rendered.contains("<title>/g/ - Test &lt;thread&gt;</title>"));
+        assert!(rendered.contains("href=\"//i.4cdn.org/g/1704067200000.png\""));
+        assert!(rendered.contains("src=\"//i.4cdn.org/g/1704067200000s.jpg\""));
+        assert!(rendered.contains("(2 KB, 800x600)"));
+        assert!(rendered.contains("<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a><br>Reply"));
+
+        // The rendered thread must be understood by the 4chan parser.
+        let parsed = FourchanThread::from_document(html::parse_string(&rendered));
+
+        let reply_ids: Vec<u32> = parsed.get_all_replies().unwrap().map(|r| r.id).collect();
+        assert_eq!(reply_ids, vec![3]);
+        assert_eq!(thread.status(), parsed.status().unwrap());
+        assert!(parsed.status().unwrap().is_archived);
+    }
+}
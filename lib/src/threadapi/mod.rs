@@ -0,0 +1,23 @@
+use serde_derive::{Deserialize, Serialize};
+
+pub mod fourchan;
+
+/// JSON API used to update a thread instead of scraping its HTML.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum ThreadApi {
+    /// 4chan's read-only JSON API.
+    #[serde(rename = "4chan")]
+    FourChan {
+        /// URL of the thread's JSON file.
+        url: String,
+    },
+}
+
+impl ThreadApi {
+    pub fn url(&self) -> &str {
+        match self {
+            Self::FourChan { url } => url,
+        }
+    }
+}
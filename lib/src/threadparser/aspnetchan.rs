@@ -3,7 +3,9 @@ use std::collections::VecDeque;
 
 use html5ever::local_name;
 use kuchikiki::*;
+use once_cell::sync::Lazy;
 
+use crate::config::PostSelectorsDef;
 use crate::error::ChandlerError;
 use crate::html;
 use crate::util;
@@ -12,6 +14,35 @@ use super::*;
 
 const REPLY_ID_ATTRIBUTE_NAME: &str = "data-post-no";
 
+static POST_SELECTORS: Lazy<PostSelectors> = Lazy::new(|| {
+    PostSelectors::new(&PostSelectorsDef {
+        subject_selector: Some(".post-subject".to_owned()),
+        name_selector: Some(".post-name".to_owned()),
+        tripcode_selector: Some(".post-tripcode".to_owned()),
+        poster_id_selector: Some(".post-poster-id".to_owned()),
+        time_selector: Some("time".to_owned()),
+        comment_selector: Some(".post-message".to_owned()),
+        file_selector: Some(".post-file".to_owned()),
+        file_link_selector: Some("a".to_owned()),
+        file_thumbnail_selector: Some("img".to_owned()),
+        file_name_selector: Some(".post-file-name".to_owned()),
+        file_info_selector: Some(".post-file-info".to_owned()),
+        file_deleted_selector: Some(".post-file-deleted".to_owned()),
+        ..Default::default()
+    })
+    .unwrap()
+});
+
+static STATUS_SELECTORS: Lazy<StatusSelectors> = Lazy::new(|| {
+    StatusSelectors::new(
+        ".post-op .thread-locked",
+        ".post-op .thread-archived",
+        ".post-op .thread-bump-limit",
+        ".post-op .thread-image-limit",
+    )
+    .unwrap()
+});
+
 pub struct AspNetChanThread {
     pub root: NodeRef,
 }
@@ -22,6 +53,16 @@ pub struct AspNetChanReply {
     pub node: NodeRef,
 }
 
+impl ImageboardReply for AspNetChanReply {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn node(&self) -> &NodeRef {
+        &self.node
+    }
+}
+
 impl AspNetChanReply {
     pub fn from_node(node: NodeRef) -> Option<Self> {
         // Try to get reply ID from node.
@@ -96,8 +137,8 @@ impl HtmlDocument for AspNetChanThread {
         Ok(())
     }
 
-    fn purge_scripts(&self) -> Result<(), ChandlerError> {
-        html::purge_scripts(self.root.clone());
+    fn sanitize(&self, profile: SanitizeProfile) -> Result<(), ChandlerError> {
+        html::sanitize(self.root.clone(), profile);
 
         Ok(())
     }
@@ -115,6 +156,16 @@ impl MergeableImageboardThread for AspNetChanThread {
         Ok(Box::new(GetReplies { replies }))
     }
 
+    fn get_op(&self) -> Option<Self::Reply> {
+        html::find_elements_with_classes(self.root.clone(), local_name!("div"), &["post-op"])
+            .next()
+            .and_then(AspNetChanReply::from_node)
+    }
+
+    fn get_post(&self, reply: &Self::Reply) -> Result<Post, ChandlerError> {
+        Ok(POST_SELECTORS.get_post(reply.id, &reply.node))
+    }
+
     fn merge_replies_from(&mut self, new: Self) -> Result<Vec<Self::Reply>, ChandlerError> {
         // Create temporary insert marker node.
         let insert_marker_node = NodeRef::new_comment("INSERT");
@@ -169,14 +220,27 @@ impl MergeableImageboardThread for AspNetChanThread {
         Ok(())
     }
 
-    fn is_archived(&self) -> Result<bool, ChandlerError> {
-        Ok(false)
+    fn status(&self) -> Result<ThreadStatus, ChandlerError> {
+        Ok(STATUS_SELECTORS.get_status(&self.root))
+    }
+
+    fn has_insertion_point(&self) -> bool {
+        html::find_elements_with_classes(self.root.clone(), local_name!("div"), &["thread"])
+            .next()
+            .is_some()
+    }
+
+    fn reply_text(&self, reply: &Self::Reply) -> String {
+        // Only the message is compared, as backlinks and the rest of the post's markup
+        // can change without the post being edited.
+        POST_SELECTORS.get_edit_text(&reply.node)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::html;
+    use crate::threadupdater;
 
     use super::*;
 
@@ -214,4 +278,43 @@ mod tests {
 
         assert_eq!(result_html, expected_html);
     }
+
+    #[test]
+    fn update_marks_deleted_replies() {
+        let result =
+            threadupdater::update_from_html(AspNetChanThread::from_document(html::parse_string(THREAD2)), THREAD3);
+
+        assert_eq!(result.new_post_count, 1);
+        assert_eq!(result.deleted_post_count, 1);
+    }
+
+    #[test]
+    fn update_from_non_thread_does_not_mark_deleted_replies() {
+        for page in threadupdater::NOT_THREAD_PAGES {
+            let result =
+                threadupdater::update_from_html(AspNetChanThread::from_document(html::parse_string(THREAD2)), page);
+
+            assert_eq!(result.deleted_post_count, 0);
+        }
+    }
+
+    // Thread with all status markers in the OP
+    const THREAD_STATUS: &str = r#"<div class="thread" id="thread-id-1"><div class="post-container post-op" id="post1" data-post-no="1"><span class="thread-locked"></span><span class="thread-archived"></span><span class="thread-bump-limit"></span><span class="thread-image-limit"></span></div><div class="post-container" id="post2" data-post-no="2"></div></div>"#;
+
+    // Thread with status markers in a reply, which do not apply to the thread
+    const THREAD_STATUS_IN_REPLY: &str = r#"<div class="thread" id="thread-id-1"><div class="post-container post-op" id="post1" data-post-no="1"></div><div class="post-container" id="post2" data-post-no="2"><span class="thread-locked"></span><span class="thread-bump-limit"></span></div></div>"#;
+
+    #[test]
+    fn can_get_status_from_markers() {
+        let thread = AspNetChanThread::from_document(html::parse_string(THREAD_STATUS));
+        let reply_thread = AspNetChanThread::from_document(html::parse_string(THREAD_STATUS_IN_REPLY));
+
+        let status = thread.status().unwrap();
+        assert!(status.is_locked);
+        assert!(status.is_archived);
+        assert!(status.bump_limit_reached);
+        assert!(status.image_limit_reached);
+
+        assert!(reply_thread.status().unwrap().is_open());
+    }
 }
@@ -47,8 +47,8 @@ impl HtmlDocument for BasicThread {
         Ok(())
     }
 
-    fn purge_scripts(&self) -> Result<(), ChandlerError> {
-        html::purge_scripts(self.root.clone());
+    fn sanitize(&self, profile: SanitizeProfile) -> Result<(), ChandlerError> {
+        html::sanitize(self.root.clone(), profile);
 
         Ok(())
     }
@@ -0,0 +1,417 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+use html5ever::local_name;
+use kuchikiki::iter::NodeIterator;
+use kuchikiki::*;
+use regex::Regex;
+
+use crate::config::CustomParserDef;
+use crate::error::ChandlerError;
+use crate::html;
+use crate::util;
+
+use super::*;
+
+/// Parser driven by a custom parser definition, using CSS selectors to locate posts.
+pub struct CustomParser {
+    pub def: CustomParserDef,
+    reply_selector: Selectors,
+    op_selector: Selectors,
+    insert_selector: Option<Selectors>,
+    reply_id_regex: Regex,
+    op_id_regex: Regex,
+    locked_selector: Option<Selectors>,
+    archived_selector: Option<Selectors>,
+    post_selectors: PostSelectors,
+}
+
+pub struct CustomThread {
+    pub root: NodeRef,
+
+    /// Parser to use. Documents created without one cannot be merged.
+    pub parser: Option<Arc<CustomParser>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CustomReply {
+    pub id: u32,
+    pub node: NodeRef,
+}
+
+impl ImageboardReply for CustomReply {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn node(&self) -> &NodeRef {
+        &self.node
+    }
+}
+
+impl CustomParser {
+    pub fn new(def: CustomParserDef) -> Result<Self, ChandlerError> {
+        fn compile(selector: &str) -> Result<Selectors, ChandlerError> {
+            Selectors::compile(selector)
+                .map_err(|_| ChandlerError::Config(format!("Invalid CSS selector: {selector}").into()))
+        }
+
+        let reply_id_regex = Regex::new(&def.reply_id_regex)
+            .map_err(|err| ChandlerError::Config(format!("Invalid reply ID regex: {err}").into()))?;
+
+        let op_id_regex = Regex::new(def.op_id_regex.as_deref().unwrap_or(r"(\d+)"))
+            .map_err(|err| ChandlerError::Config(format!("Invalid OP ID regex: {err}").into()))?;
+
+        Ok(Self {
+            reply_selector: compile(&def.reply_selector)?,
+            op_selector: compile(&def.op_selector)?,
+            insert_selector: def.insert_selector.as_deref().map(compile).transpose()?,
+            reply_id_regex,
+            op_id_regex,
+            locked_selector: def.locked_selector.as_deref().map(compile).transpose()?,
+            archived_selector: def.archived_selector.as_deref().map(compile).transpose()?,
+            post_selectors: def
+                .post
+                .as_ref()
+                .map(PostSelectors::new)
+                .transpose()?
+                .unwrap_or_default(),
+            def,
+        })
+    }
+
+    /// Get the first element matching the selector.
+    fn select_first(&self, root: &NodeRef, selector: &Selectors) -> Option<NodeRef> {
+        selector
+            .filter(root.inclusive_descendants().elements())
+            .next()
+            .map(|e| e.as_node().clone())
+    }
+
+    fn reply_from_node(&self, node: NodeRef) -> Option<CustomReply> {
+        Self::post_from_node(node, &self.reply_id_regex)
+    }
+
+    fn op_from_node(&self, node: NodeRef) -> Option<CustomReply> {
+        Self::post_from_node(node, &self.op_id_regex)
+    }
+
+    fn post_from_node(node: NodeRef, id_regex: &Regex) -> Option<CustomReply> {
+        // Try to get post ID from the node's "id" attribute.
+        let id = {
+            let data = node.as_element()?;
+            let attrs = data.attributes.borrow();
+            let id_attr = attrs.get(local_name!("id"))?;
+
+            id_regex.captures(id_attr)?.get(1)?.as_str().parse::<u32>().ok()?
+        };
+
+        Some(CustomReply { id, node })
+    }
+}
+
+impl fmt::Debug for CustomParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomParser").field("def", &self.def).finish()
+    }
+}
+
+impl CustomThread {
+    pub fn from_file_with_parser(file_path: &Path, parser: Arc<CustomParser>) -> Result<Self, ChandlerError> {
+        Ok(Self {
+            root: html::parse_file(file_path)?,
+            parser: Some(parser),
+        })
+    }
+
+    fn parser(&self) -> Result<&CustomParser, ChandlerError> {
+        self.parser
+            .as_deref()
+            .ok_or_else(|| ChandlerError::Other("No custom parser definition was specified!".into()))
+    }
+}
+
+impl HtmlDocument for CustomThread {
+    type Document = NodeRef;
+
+    fn from_document(document: Self::Document) -> Self {
+        Self {
+            root: document,
+            parser: None,
+        }
+    }
+
+    fn into_document(self) -> Self::Document {
+        self.root
+    }
+
+    fn from_file(file_path: &Path) -> Result<Self, ChandlerError> {
+        let node = html::parse_file(file_path)?;
+
+        Ok(Self::from_document(node))
+    }
+
+    fn load_file_like(&self, file_path: &Path) -> Result<Self, ChandlerError> {
+        Ok(Self {
+            root: html::parse_file(file_path)?,
+            parser: self.parser.clone(),
+        })
+    }
+
+    fn write_file(&self, file_path: &Path) -> Result<(), ChandlerError> {
+        let mut file = util::create_file(file_path).map_err(ChandlerError::CreateFile)?;
+
+        html5ever::serialize(&mut file, &self.root, Default::default())
+            .map_err(|err| ChandlerError::Other(Cow::Owned(format!("Serialization error: {}", err))))?;
+
+        Ok(())
+    }
+
+    fn for_links(&self, mut action: impl FnMut(html::Link) -> Result<(), ChandlerError>) -> Result<(), ChandlerError> {
+        let links = html::find_links(self.root.clone());
+
+        for link in links.into_iter() {
+            action(link)?;
+        }
+
+        Ok(())
+    }
+
+    fn sanitize(&self, profile: SanitizeProfile) -> Result<(), ChandlerError> {
+        html::sanitize(self.root.clone(), profile);
+
+        Ok(())
+    }
+}
+
+impl MergeableImageboardThread for CustomThread {
+    type Reply = CustomReply;
+
+    fn get_all_replies(&self) -> Result<Box<dyn Iterator<Item = Self::Reply>>, ChandlerError> {
+        let parser = self.parser()?;
+
+        let replies: VecDeque<CustomReply> = parser
+            .reply_selector
+            .filter(self.root.inclusive_descendants().elements())
+            .filter_map(|e| parser.reply_from_node(e.as_node().clone()))
+            .collect();
+
+        Ok(Box::new(replies.into_iter()))
+    }
+
+    fn get_op(&self) -> Option<Self::Reply> {
+        let parser = self.parser().ok()?;
+
+        parser
+            .select_first(&self.root, &parser.op_selector)
+            .and_then(|node| parser.op_from_node(node))
+    }
+
+    fn get_post(&self, reply: &Self::Reply) -> Result<Post, ChandlerError> {
+        Ok(self.parser()?.post_selectors.get_post(reply.id, &reply.node))
+    }
+
+    fn merge_replies_from(&mut self, new: Self) -> Result<Vec<Self::Reply>, ChandlerError> {
+        let parser = self.parser()?;
+
+        // Create temporary insert marker node.
+        let insert_marker_node = NodeRef::new_comment("INSERT");
+
+        let last_reply_id = if let Some(last_original_reply) = self.get_all_replies()?.last() {
+            last_original_reply.node.insert_after(insert_marker_node.clone());
+
+            last_original_reply.id
+        } else if let Some(insert_selector) = &parser.insert_selector {
+            // Get original insertion point element.
+            let insert_element = parser
+                .select_first(&self.root, insert_selector)
+                .ok_or_else(|| ChandlerError::Other("No insertion point element found in original thread!".into()))?;
+
+            // Append the insert marker node to the insertion point element.
+            insert_element.append(insert_marker_node.clone());
+
+            0
+        } else {
+            // Get original OP element.
+            let original_op_element = parser
+                .select_first(&self.root, &parser.op_selector)
+                .ok_or_else(|| ChandlerError::Other("No OP element found in original thread!".into()))?;
+
+            // Insert the insert marker node after the original OP element.
+            original_op_element.insert_after(insert_marker_node.clone());
+
+            0
+        };
+
+        let mut new_replies: Vec<Self::Reply> = Vec::new();
+
+        for new_reply in new.get_all_replies()? {
+            if new_reply.id <= last_reply_id {
+                continue;
+            }
+
+            // Append the reply to the original thread.
+            insert_marker_node.insert_before(new_reply.node.clone());
+
+            new_replies.push(new_reply);
+        }
+
+        // Remove temporary insert marker node.
+        insert_marker_node.detach();
+
+        Ok(new_replies)
+    }
+
+    fn for_reply_links(
+        &self,
+        reply: &Self::Reply,
+        mut action: impl FnMut(html::Link) -> Result<(), ChandlerError>,
+    ) -> Result<(), ChandlerError> {
+        let links = html::find_links(reply.node.clone());
+
+        for link in links.into_iter() {
+            action(link)?;
+        }
+
+        Ok(())
+    }
+
+    fn status(&self) -> Result<ThreadStatus, ChandlerError> {
+        let parser = self.parser()?;
+
+        let matches = |selector: &Option<Selectors>| {
+            selector
+                .as_ref()
+                .is_some_and(|selector| parser.select_first(&self.root, selector).is_some())
+        };
+
+        Ok(ThreadStatus {
+            is_locked: matches(&parser.locked_selector),
+            is_archived: matches(&parser.archived_selector),
+            ..Default::default()
+        })
+    }
+
+    fn has_insertion_point(&self) -> bool {
+        self.parser.as_deref().is_some_and(|parser| {
+            let selector = parser.insert_selector.as_ref().unwrap_or(&parser.op_selector);
+
+            parser.select_first(&self.root, selector).is_some()
+        })
+    }
+
+    fn reply_text(&self, reply: &Self::Reply) -> String {
+        // Only the message is compared, if the parser knows where it is.
+        self.parser()
+            .map(|parser| parser.post_selectors.get_edit_text(&reply.node))
+            .unwrap_or_else(|_| html::post_text(&reply.node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html;
+    use crate::threadupdater;
+
+    use super::*;
+
+    // Original thread with OP only
+    const THREAD1: &str = r#"<div class="thread"><div class="op" id="op_1"></div><div class="replies"></div></div>"#;
+
+    // Thread with 2 posts
+    const THREAD2: &str = r#"<div class="thread"><div class="op" id="op_1"></div><div class="replies"><div class="post" id="post_2"></div></div></div>"#;
+
+    // Thread with post 2 deleted and a new post 3 added
+    const THREAD3: &str = r#"<div class="thread"><div class="op" id="op_1"></div><div class="replies"><div class="post" id="post_3"></div></div><img class="locked"></div>"#;
+
+    // Merged thread with all 3 posts
+    const THREAD_MERGED: &str = r#"<div class="thread"><div class="op" id="op_1"></div><div class="replies"><div class="post" id="post_2"></div><div class="post" id="post_3"></div></div></div>"#;
+
+    fn parser() -> Arc<CustomParser> {
+        Arc::new(
+            CustomParser::new(CustomParserDef {
+                reply_selector: "div.replies > div.post".to_owned(),
+                op_selector: "div.op".to_owned(),
+                insert_selector: Some("div.replies".to_owned()),
+                reply_id_regex: r"^post_(\d+)".to_owned(),
+                op_id_regex: None,
+                locked_selector: None,
+                archived_selector: Some("img.locked".to_owned()),
+                post: None,
+            })
+            .unwrap(),
+        )
+    }
+
+    fn thread(html_str: &str) -> CustomThread {
+        CustomThread {
+            root: html::parse_string(html_str),
+            parser: Some(parser()),
+        }
+    }
+
+    #[test]
+    fn can_merge_threads() {
+        let merged_node = html::parse_string(THREAD_MERGED);
+
+        let expected_html = html::to_string(merged_node);
+
+        let mut thread1 = thread(THREAD1);
+        let thread2 = thread(THREAD2);
+        let thread3 = thread(THREAD3);
+
+        assert!(!thread2.status().unwrap().is_archived);
+        assert!(thread3.status().unwrap().is_archived);
+
+        thread1.merge_replies_from(thread2).unwrap();
+        thread1.merge_replies_from(thread3).unwrap();
+
+        let node1 = thread1.into_document();
+
+        let result_html = html::to_string(node1);
+
+        assert_eq!(result_html, expected_html);
+    }
+
+    #[test]
+    fn can_get_op() {
+        let posts = thread(THREAD2).get_all_posts().unwrap();
+
+        assert_eq!(posts.iter().map(|p| p.number).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn invalid_definition_is_rejected() {
+        let result = CustomParser::new(CustomParserDef {
+            reply_selector: "div..post".to_owned(),
+            op_selector: "div.op".to_owned(),
+            insert_selector: None,
+            reply_id_regex: r"^post_(\d+)".to_owned(),
+            op_id_regex: None,
+            locked_selector: None,
+            archived_selector: None,
+            post: None,
+        });
+
+        assert!(matches!(result, Err(ChandlerError::Config(_))));
+    }
+
+    #[test]
+    fn update_marks_deleted_replies() {
+        let result = threadupdater::update_from_html(thread(THREAD2), THREAD3);
+
+        assert_eq!(result.new_post_count, 1);
+        assert_eq!(result.deleted_post_count, 1);
+    }
+
+    #[test]
+    fn update_from_non_thread_does_not_mark_deleted_replies() {
+        for page in threadupdater::NOT_THREAD_PAGES {
+            let result = threadupdater::update_from_html(thread(THREAD2), page);
+
+            assert_eq!(result.deleted_post_count, 0);
+        }
+    }
+}
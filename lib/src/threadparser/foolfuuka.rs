@@ -3,13 +3,44 @@ use std::collections::VecDeque;
 
 use html5ever::local_name;
 use kuchikiki::*;
+use once_cell::sync::Lazy;
 
+use crate::config::PostSelectorsDef;
 use crate::error::ChandlerError;
 use crate::html;
 use crate::util;
 
 use super::*;
 
+static POST_SELECTORS: Lazy<PostSelectors> = Lazy::new(|| {
+    PostSelectors::new(&PostSelectorsDef {
+        subject_selector: Some(".post_title".to_owned()),
+        name_selector: Some(".post_author".to_owned()),
+        tripcode_selector: Some(".post_tripcode".to_owned()),
+        poster_id_selector: Some(".poster_hash".to_owned()),
+        time_selector: Some("time".to_owned()),
+        comment_selector: Some(".text".to_owned()),
+        file_selector: Some(".post_file".to_owned()),
+        file_link_selector: Some(".post_file_filename".to_owned()),
+        file_name_selector: Some(".post_file_filename".to_owned()),
+        file_info_selector: Some(".post_file_metadata".to_owned()),
+        file_deleted_selector: Some(".thread_image_box .icon-ban-circle".to_owned()),
+        exclude_selector: Some(".posts".to_owned()),
+        ..Default::default()
+    })
+    .unwrap()
+});
+
+static STATUS_SELECTORS: Lazy<StatusSelectors> = Lazy::new(|| {
+    StatusSelectors::new(
+        ".post_is_op > header .icon-lock",
+        ".post_is_op > header .icon-archive",
+        ".post_is_op > header .icon-anchor",
+        ".post_is_op > header .icon-picture",
+    )
+    .unwrap()
+});
+
 pub struct FoolFuukaThread {
     pub root: NodeRef,
 }
@@ -20,6 +51,16 @@ pub struct FoolFuukaReply {
     pub node: NodeRef,
 }
 
+impl ImageboardReply for FoolFuukaReply {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn node(&self) -> &NodeRef {
+        &self.node
+    }
+}
+
 impl FoolFuukaReply {
     pub fn from_node(node: NodeRef) -> Option<Self> {
         // Try to get reply ID from node.
@@ -94,8 +135,8 @@ impl HtmlDocument for FoolFuukaThread {
         Ok(())
     }
 
-    fn purge_scripts(&self) -> Result<(), ChandlerError> {
-        html::purge_scripts(self.root.clone());
+    fn sanitize(&self, profile: SanitizeProfile) -> Result<(), ChandlerError> {
+        html::sanitize(self.root.clone(), profile);
 
         Ok(())
     }
@@ -111,6 +152,16 @@ impl MergeableImageboardThread for FoolFuukaThread {
         Ok(Box::new(GetReplies { replies }))
     }
 
+    fn get_op(&self) -> Option<Self::Reply> {
+        html::find_elements_with_classes(self.root.clone(), local_name!("article"), &["post_is_op"])
+            .next()
+            .and_then(FoolFuukaReply::from_node)
+    }
+
+    fn get_post(&self, reply: &Self::Reply) -> Result<Post, ChandlerError> {
+        Ok(POST_SELECTORS.get_post(reply.id, &reply.node))
+    }
+
     fn merge_replies_from(&mut self, new: Self) -> Result<Vec<Self::Reply>, ChandlerError> {
         // Create temporary insert marker node.
         let insert_marker_node = NodeRef::new_comment("INSERT");
@@ -165,14 +216,28 @@ impl MergeableImageboardThread for FoolFuukaThread {
         Ok(())
     }
 
-    fn is_archived(&self) -> Result<bool, ChandlerError> {
-        Ok(false)
+    fn status(&self) -> Result<ThreadStatus, ChandlerError> {
+        Ok(STATUS_SELECTORS.get_status(&self.root))
+    }
+
+    fn has_insertion_point(&self) -> bool {
+        html::find_elements_with_classes(self.root.clone(), local_name!("aside"), &["posts"])
+            .next()
+            .is_some()
+    }
+
+    fn reply_text(&self, reply: &Self::Reply) -> String {
+        // Only the message is compared, as backlinks and the rest of the post's markup
+        // can change without the post being edited.
+        POST_SELECTORS.get_edit_text(&reply.node)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::config::chandler::EditPolicy;
     use crate::html;
+    use crate::threadupdater;
 
     use super::*;
 
@@ -211,4 +276,70 @@ mod tests {
 
         assert_eq!(result_html, expected_html);
     }
+
+    #[test]
+    fn update_marks_deleted_replies() {
+        let result =
+            threadupdater::update_from_html(FoolFuukaThread::from_document(html::parse_string(THREAD2)), THREAD3);
+
+        assert_eq!(result.new_post_count, 1);
+        assert_eq!(result.deleted_post_count, 1);
+    }
+
+    #[test]
+    fn update_from_non_thread_does_not_mark_deleted_replies() {
+        for page in threadupdater::NOT_THREAD_PAGES {
+            let result =
+                threadupdater::update_from_html(FoolFuukaThread::from_document(html::parse_string(THREAD2)), page);
+
+            assert_eq!(result.deleted_post_count, 0);
+        }
+    }
+
+    // Thread with post 2, as originally downloaded
+    const THREAD_EDIT1: &str = r#"<article id="1" class="thread post_is_op"><div class="text">Hello</div><aside class="posts"><article class="post" id="2"><header></header><div class="text">Reply</div></article></aside></article>"#;
+
+    // Thread with post 2 quoted by a new post
+    const THREAD_EDIT2: &str = r#"<article id="1" class="thread post_is_op"><div class="text">Hello</div><aside class="posts"><article class="post" id="2"><header><div class="backlink_list">Quoted By: <a class="backlink">&gt;&gt;3</a></div></header><div class="text">Reply</div></article><article class="post" id="3"><div class="text">&gt;&gt;2</div></article></aside></article>"#;
+
+    // Thread with post 2 banned
+    const THREAD_EDIT3: &str = r#"<article id="1" class="thread post_is_op"><div class="text">Hello</div><aside class="posts"><article class="post" id="2"><header></header><div class="text">Reply<br><strong>(USER WAS BANNED FOR THIS POST)</strong></div></article></aside></article>"#;
+
+    #[test]
+    fn can_merge_edits() {
+        let thread1 = FoolFuukaThread::from_document(html::parse_string(THREAD_EDIT1));
+        let thread2 = FoolFuukaThread::from_document(html::parse_string(THREAD_EDIT2));
+        let thread3 = FoolFuukaThread::from_document(html::parse_string(THREAD_EDIT3));
+
+        // New backlinks are not edits.
+        let edited = thread1.merge_edits_from(&thread2, EditPolicy::Replace).unwrap();
+        assert!(edited.is_empty());
+
+        let edited = thread1.merge_edits_from(&thread3, EditPolicy::Replace).unwrap();
+        assert_eq!(edited.len(), 1);
+        assert_eq!(edited[0].id, 2);
+
+        let result_html = html::to_string(thread1.into_document());
+        assert!(result_html.contains("(USER WAS BANNED FOR THIS POST)"));
+    }
+
+    // Thread with all status markers in the OP
+    const THREAD_STATUS: &str = r#"<article id="1" class="thread post_is_op"><header><i class="icon-lock"></i><i class="icon-archive"></i><i class="icon-anchor"></i><i class="icon-picture"></i></header><aside class="posts"><article class="post" id="2"></article></aside></article>"#;
+
+    // Thread with status markers in a reply, which do not apply to the thread
+    const THREAD_STATUS_IN_REPLY: &str = r#"<article id="1" class="thread post_is_op"><header></header><aside class="posts"><article class="post" id="2"><header><i class="icon-lock"></i><i class="icon-anchor"></i></header></article></aside></article>"#;
+
+    #[test]
+    fn can_get_status_from_markers() {
+        let thread = FoolFuukaThread::from_document(html::parse_string(THREAD_STATUS));
+        let reply_thread = FoolFuukaThread::from_document(html::parse_string(THREAD_STATUS_IN_REPLY));
+
+        let status = thread.status().unwrap();
+        assert!(status.is_locked);
+        assert!(status.is_archived);
+        assert!(status.bump_limit_reached);
+        assert!(status.image_limit_reached);
+
+        assert!(reply_thread.status().unwrap().is_open());
+    }
 }
@@ -8,6 +8,28 @@ use crate::util;
 
 use html5ever::local_name;
 use kuchikiki::*;
+use once_cell::sync::Lazy;
+
+use crate::config::PostSelectorsDef;
+
+static POST_SELECTORS: Lazy<PostSelectors> = Lazy::new(|| {
+    PostSelectors::new(&PostSelectorsDef {
+        subject_selector: Some(".postInfo .subject".to_owned()),
+        name_selector: Some(".postInfo .name".to_owned()),
+        tripcode_selector: Some(".postInfo .postertrip".to_owned()),
+        poster_id_selector: Some(".postInfo .posteruid .hand".to_owned()),
+        time_selector: Some(".postInfo .dateTime".to_owned()),
+        comment_selector: Some(".postMessage".to_owned()),
+        file_selector: Some(".file".to_owned()),
+        file_link_selector: Some(".fileText a".to_owned()),
+        file_thumbnail_selector: Some(".fileThumb img".to_owned()),
+        file_name_selector: Some(".fileText a".to_owned()),
+        file_info_selector: Some(".fileText".to_owned()),
+        file_deleted_selector: Some(".fileDeleted, .fileDeletedRes".to_owned()),
+        ..Default::default()
+    })
+    .unwrap()
+});
 
 pub struct FourchanThread {
     pub root: NodeRef,
@@ -19,6 +41,16 @@ pub struct FourchanReply {
     pub node: NodeRef,
 }
 
+impl ImageboardReply for FourchanReply {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn node(&self) -> &NodeRef {
+        &self.node
+    }
+}
+
 impl FourchanReply {
     pub fn from_node(node: NodeRef) -> Option<Self> {
         // Try to get reply ID from node.
@@ -27,7 +59,7 @@ impl FourchanReply {
                 // Try to locate "id" attribute.
                 if let Some(id_attr) = data.attributes.borrow().get(local_name!("id")) {
                     // Try to parse it as an integer, skipping the "pc" prefix.
-                    if let Ok(id) = id_attr[2..].parse::<u32>() {
+                    if let Some(id) = id_attr.strip_prefix("pc").and_then(|s| s.parse::<u32>().ok()) {
                         return Some(id);
                     }
                 }
@@ -95,8 +127,8 @@ impl HtmlDocument for FourchanThread {
         Ok(())
     }
 
-    fn purge_scripts(&self) -> Result<(), ChandlerError> {
-        html::purge_scripts(self.root.clone());
+    fn sanitize(&self, profile: SanitizeProfile) -> Result<(), ChandlerError> {
+        html::sanitize(self.root.clone(), profile);
 
         Ok(())
     }
@@ -115,6 +147,17 @@ impl MergeableImageboardThread for FourchanThread {
         Ok(Box::new(GetReplies { replies }))
     }
 
+    fn get_op(&self) -> Option<Self::Reply> {
+        html::find_elements_with_classes(self.root.clone(), local_name!("div"), &["thread"])
+            .next()?
+            .children()
+            .find_map(FourchanReply::from_node)
+    }
+
+    fn get_post(&self, reply: &Self::Reply) -> Result<Post, ChandlerError> {
+        Ok(POST_SELECTORS.get_post(reply.id, &reply.node))
+    }
+
     fn merge_replies_from(&mut self, new: Self) -> Result<Vec<Self::Reply>, ChandlerError> {
         // Create temporary insert marker node.
         let insert_marker_node = NodeRef::new_comment("INSERT");
@@ -169,14 +212,38 @@ impl MergeableImageboardThread for FourchanThread {
         Ok(())
     }
 
-    fn is_archived(&self) -> Result<bool, ChandlerError> {
-        Ok(html::find_elements_with_classes(self.root.clone(), local_name!("img"), &["archivedIcon"]).any(|_| true))
+    fn status(&self) -> Result<ThreadStatus, ChandlerError> {
+        let has_icon = |class_name: &str| {
+            html::find_elements_with_classes(self.root.clone(), local_name!("img"), &[class_name])
+                .next()
+                .is_some()
+        };
+
+        Ok(ThreadStatus {
+            is_locked: has_icon("closedIcon"),
+            is_archived: has_icon("archivedIcon"),
+            ..Default::default()
+        })
+    }
+
+    fn has_insertion_point(&self) -> bool {
+        html::find_elements_with_classes(self.root.clone(), local_name!("div"), &["thread"])
+            .next()
+            .is_some()
+    }
+
+    fn reply_text(&self, reply: &Self::Reply) -> String {
+        // Only the message is compared, as the rest of the post's markup
+        // differs between the HTML and API versions of the thread.
+        POST_SELECTORS.get_edit_text(&reply.node)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::config::chandler::EditPolicy;
     use crate::html;
+    use crate::threadupdater;
 
     use super::*;
 
@@ -214,4 +281,92 @@ mod tests {
 
         assert_eq!(result_html, expected_html);
     }
+
+    // Thread with post 2, as originally downloaded
+    const THREAD_EDIT1: &str = r#"<div class="thread" id="t1"><div class="opContainer" id="pc1"></div><div class="replyContainer" id="pc2"><div class="postInfo">Anonymous 01/01/24</div><blockquote class="postMessage" id="m2">Hello</blockquote></div></div>"#;
+
+    // Thread with post 2 banned, and different post info markup
+    const THREAD_EDIT2: &str = r#"<div class="thread" id="t1"><div class="opContainer" id="pc1"></div><div class="replyContainer" id="pc2"><div class="postInfo">Anonymous (Mon) 01/01/24</div><blockquote class="postMessage" id="m2">Hello<br><strong class="banMessage">(USER WAS BANNED FOR THIS POST)</strong></blockquote></div></div>"#;
+
+    #[test]
+    fn can_merge_edits() {
+        let thread1 = FourchanThread::from_document(html::parse_string(THREAD_EDIT1));
+        let thread2 = FourchanThread::from_document(html::parse_string(THREAD_EDIT2));
+
+        // Identical messages are not edits, even if the rest of the markup differs.
+        let unchanged = FourchanThread::from_document(html::parse_string(THREAD_EDIT1));
+        let edited = thread1.merge_edits_from(&unchanged, EditPolicy::Replace).unwrap();
+        assert!(edited.is_empty());
+
+        let edited = thread1.merge_edits_from(&thread2, EditPolicy::Replace).unwrap();
+        assert_eq!(edited.len(), 1);
+        assert_eq!(edited[0].id, 2);
+
+        let result_html = html::to_string(thread1.into_document());
+        assert!(result_html.contains("(USER WAS BANNED FOR THIS POST)"));
+        assert!(result_html.contains(r#"class="replyContainer chandler-edited""#));
+    }
+
+    // Thread with a file, as rendered from the API
+    const THREAD_POSTS: &str = r##"<div class="thread" id="t1"><div class="postContainer opContainer" id="pc1"><div id="p1" class="post op"><div class="file" id="f1"><div class="fileText" id="fT1">File: <a href="https://i.4cdn.org/g/1700000000000.png" target="_blank">wallpaper 1920x1080.png</a> (1.50 MB, 1280x720)</div><a class="fileThumb" href="https://i.4cdn.org/g/1700000000000.png" target="_blank"><img src="https://i.4cdn.org/g/1700000000000s.jpg" alt="1.50 MB"></a></div><div class="postInfo desktop" id="pi1"><span class="subject">Thread</span> <span class="nameBlock"><span class="name">Anonymous</span> <span class="postertrip">!Trip</span> <span class="posteruid">(ID: <span class="hand">abc123</span>)</span></span> <span class="dateTime" data-utc="1704067200">01/01/24(Mon)00:00:00</span></div><blockquote class="postMessage" id="m1">Hello</blockquote></div></div><div class="postContainer replyContainer" id="pc2"><div id="p2" class="post reply"><div class="postInfo desktop" id="pi2"><span class="nameBlock"><span class="name">Anonymous</span></span></div><blockquote class="postMessage" id="m2"><a href="#p1" class="quotelink">&gt;&gt;1</a><br>Reply</blockquote></div></div></div>"##;
+
+    #[test]
+    fn can_get_posts() {
+        let thread = FourchanThread::from_document(html::parse_string(THREAD_POSTS));
+
+        let posts = thread.get_all_posts().unwrap();
+        assert_eq!(posts.len(), 2);
+
+        let op = &posts[0];
+        assert_eq!(op.number, 1);
+        assert_eq!(op.subject.as_deref(), Some("Thread"));
+        assert_eq!(op.name.as_deref(), Some("Anonymous"));
+        assert_eq!(op.tripcode.as_deref(), Some("!Trip"));
+        assert_eq!(op.poster_id.as_deref(), Some("abc123"));
+        assert_eq!(op.time.map(|t| t.timestamp()), Some(1704067200));
+        assert_eq!(op.comment_text, "Hello");
+
+        assert_eq!(
+            op.files,
+            vec![PostFile {
+                url: "https://i.4cdn.org/g/1700000000000.png".to_owned(),
+                path: None,
+                thumbnail_url: Some("https://i.4cdn.org/g/1700000000000s.jpg".to_owned()),
+                thumbnail_path: None,
+                original_name: Some("wallpaper 1920x1080.png".to_owned()),
+                size: Some(1572864),
+                width: Some(1280),
+                height: Some(720),
+            }]
+        );
+
+        let reply = &posts[1];
+        assert_eq!(reply.number, 2);
+        assert_eq!(reply.subject, None);
+        assert_eq!(
+            reply.comment_html,
+            r##"<a href="#p1" class="quotelink">&gt;&gt;1</a><br>Reply"##
+        );
+        assert_eq!(reply.comment_text, ">>1\nReply");
+        assert!(reply.files.is_empty());
+    }
+
+    #[test]
+    fn update_marks_deleted_replies() {
+        let result =
+            threadupdater::update_from_html(FourchanThread::from_document(html::parse_string(THREAD2)), THREAD3);
+
+        assert_eq!(result.new_post_count, 1);
+        assert_eq!(result.deleted_post_count, 1);
+    }
+
+    #[test]
+    fn update_from_non_thread_does_not_mark_deleted_replies() {
+        for page in threadupdater::NOT_THREAD_PAGES {
+            let result =
+                threadupdater::update_from_html(FourchanThread::from_document(html::parse_string(THREAD2)), page);
+
+            assert_eq!(result.deleted_post_count, 0);
+        }
+    }
 }
@@ -6,6 +6,7 @@ use kuchikiki::*;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::config::PostSelectorsDef;
 use crate::error::ChandlerError;
 use crate::html;
 use crate::util;
@@ -14,6 +15,30 @@ use super::*;
 
 static REGEX_GET_REPLY_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"^reply_(\d+)").unwrap());
 
+static POST_SELECTORS: Lazy<PostSelectors> = Lazy::new(|| {
+    PostSelectors::new(&PostSelectorsDef {
+        subject_selector: Some(".filetitle".to_owned()),
+        name_selector: Some(".postername".to_owned()),
+        tripcode_selector: Some(".postertrip".to_owned()),
+        comment_selector: Some("blockquote".to_owned()),
+        file_selector: Some(".filesize".to_owned()),
+        file_link_selector: Some("a".to_owned()),
+        file_deleted_selector: Some(".nothumb".to_owned()),
+        ..Default::default()
+    })
+    .unwrap()
+});
+
+static STATUS_SELECTORS: Lazy<StatusSelectors> = Lazy::new(|| {
+    StatusSelectors::new(
+        r#".post img[src$="/locked.gif"]"#,
+        r#".post img[src$="/archived.gif"]"#,
+        r#".post img[src$="/bumplimit.gif"]"#,
+        r#".post img[src$="/imagelimit.gif"]"#,
+    )
+    .unwrap()
+});
+
 pub struct KusabaxThread {
     pub root: NodeRef,
 }
@@ -24,6 +49,16 @@ pub struct KusabaxReply {
     pub node: NodeRef,
 }
 
+impl ImageboardReply for KusabaxReply {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn node(&self) -> &NodeRef {
+        &self.node
+    }
+}
+
 impl KusabaxReply {
     pub fn from_node(node: NodeRef) -> Option<Self> {
         // Try to get post ID from node.
@@ -100,8 +135,8 @@ impl HtmlDocument for KusabaxThread {
         Ok(())
     }
 
-    fn purge_scripts(&self) -> Result<(), ChandlerError> {
-        html::purge_scripts(self.root.clone());
+    fn sanitize(&self, profile: SanitizeProfile) -> Result<(), ChandlerError> {
+        html::sanitize(self.root.clone(), profile);
 
         Ok(())
     }
@@ -117,6 +152,25 @@ impl MergeableImageboardThread for KusabaxThread {
         Ok(Box::new(GetReplies { replies }))
     }
 
+    fn get_op(&self) -> Option<Self::Reply> {
+        let node = html::find_elements_with_classes(self.root.clone(), local_name!("div"), &["post"]).next()?;
+
+        // The OP's "id" attribute is just the post number.
+        let id = node
+            .as_element()?
+            .attributes
+            .borrow()
+            .get(local_name!("id"))?
+            .parse::<u32>()
+            .ok()?;
+
+        Some(KusabaxReply { id, node })
+    }
+
+    fn get_post(&self, reply: &Self::Reply) -> Result<Post, ChandlerError> {
+        Ok(POST_SELECTORS.get_post(reply.id, &reply.node))
+    }
+
     fn merge_replies_from(&mut self, new: Self) -> Result<Vec<Self::Reply>, ChandlerError> {
         // Create temporary insert marker node.
         let insert_marker_node = NodeRef::new_comment("INSERT");
@@ -171,14 +225,27 @@ impl MergeableImageboardThread for KusabaxThread {
         Ok(())
     }
 
-    fn is_archived(&self) -> Result<bool, ChandlerError> {
-        Ok(false)
+    fn status(&self) -> Result<ThreadStatus, ChandlerError> {
+        Ok(STATUS_SELECTORS.get_status(&self.root))
+    }
+
+    fn has_insertion_point(&self) -> bool {
+        html::find_elements_with_classes(self.root.clone(), local_name!("div"), &["replies"])
+            .next()
+            .is_some()
+    }
+
+    fn reply_text(&self, reply: &Self::Reply) -> String {
+        // Only the message is compared, as backlinks and the rest of the post's markup
+        // can change without the post being edited.
+        POST_SELECTORS.get_edit_text(&reply.node)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::html;
+    use crate::threadupdater;
 
     use super::*;
 
@@ -217,4 +284,43 @@ mod tests {
 
         assert_eq!(result_html, expected_html);
     }
+
+    #[test]
+    fn update_marks_deleted_replies() {
+        let result =
+            threadupdater::update_from_html(KusabaxThread::from_document(html::parse_string(THREAD2)), THREAD3);
+
+        assert_eq!(result.new_post_count, 1);
+        assert_eq!(result.deleted_post_count, 1);
+    }
+
+    #[test]
+    fn update_from_non_thread_does_not_mark_deleted_replies() {
+        for page in threadupdater::NOT_THREAD_PAGES {
+            let result =
+                threadupdater::update_from_html(KusabaxThread::from_document(html::parse_string(THREAD2)), page);
+
+            assert_eq!(result.deleted_post_count, 0);
+        }
+    }
+
+    // Thread with all status markers in the OP
+    const THREAD_STATUS: &str = r#"<div id="thread_1_a"><div class="post" id="1"><img src="/css/locked.gif"><img src="/css/archived.gif"><img src="/css/bumplimit.gif"><img src="/css/imagelimit.gif"></div><div id="replies_1_a" class="replies"><div class="reply" id="reply_2"></div></div></div>"#;
+
+    // Thread with status markers in a reply, which do not apply to the thread
+    const THREAD_STATUS_IN_REPLY: &str = r#"<div id="thread_1_a"><div class="post" id="1"></div><div id="replies_1_a" class="replies"><div class="reply" id="reply_2"><img src="/css/locked.gif"><img src="/css/bumplimit.gif"></div></div></div>"#;
+
+    #[test]
+    fn can_get_status_from_markers() {
+        let thread = KusabaxThread::from_document(html::parse_string(THREAD_STATUS));
+        let reply_thread = KusabaxThread::from_document(html::parse_string(THREAD_STATUS_IN_REPLY));
+
+        let status = thread.status().unwrap();
+        assert!(status.is_locked);
+        assert!(status.is_archived);
+        assert!(status.bump_limit_reached);
+        assert!(status.image_limit_reached);
+
+        assert!(reply_thread.status().unwrap().is_open());
+    }
 }
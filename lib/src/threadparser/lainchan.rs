@@ -7,6 +7,7 @@ use kuchikiki::*;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::config::PostSelectorsDef;
 use crate::error::ChandlerError;
 use crate::html;
 use crate::util;
@@ -14,6 +15,36 @@ use crate::util;
 use super::*;
 
 static REGEX_GET_REPLY_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"^pc(\d+)").unwrap());
+static REGEX_GET_OP_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"^op_(\d+)").unwrap());
+
+static POST_SELECTORS: Lazy<PostSelectors> = Lazy::new(|| {
+    PostSelectors::new(&PostSelectorsDef {
+        subject_selector: Some(".intro .subject".to_owned()),
+        name_selector: Some(".intro .name".to_owned()),
+        tripcode_selector: Some(".intro .trip".to_owned()),
+        poster_id_selector: Some(".intro .poster_id".to_owned()),
+        time_selector: Some(".intro time".to_owned()),
+        comment_selector: Some(".body".to_owned()),
+        file_selector: Some(".file".to_owned()),
+        file_link_selector: Some(".fileinfo a".to_owned()),
+        file_thumbnail_selector: Some(".post-image".to_owned()),
+        file_name_selector: Some(".postfilename".to_owned()),
+        file_info_selector: Some(".fileinfo".to_owned()),
+        file_deleted_selector: Some("img.deleted".to_owned()),
+        ..Default::default()
+    })
+    .unwrap()
+});
+
+static STATUS_SELECTORS: Lazy<StatusSelectors> = Lazy::new(|| {
+    StatusSelectors::new(
+        ".op .intro .fa-lock",
+        ".op .intro .fa-archive",
+        ".op .intro .fa-anchor",
+        ".op .intro .fa-image",
+    )
+    .unwrap()
+});
 
 pub struct LainchanThread {
     pub root: NodeRef,
@@ -25,6 +56,16 @@ pub struct LainchanReply {
     pub node: NodeRef,
 }
 
+impl ImageboardReply for LainchanReply {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn node(&self) -> &NodeRef {
+        &self.node
+    }
+}
+
 impl LainchanReply {
     pub fn from_node(node: NodeRef) -> Option<Self> {
         // Try to get reply ID from node.
@@ -101,8 +142,8 @@ impl HtmlDocument for LainchanThread {
         Ok(())
     }
 
-    fn purge_scripts(&self) -> Result<(), ChandlerError> {
-        html::purge_scripts(self.root.clone());
+    fn sanitize(&self, profile: SanitizeProfile) -> Result<(), ChandlerError> {
+        html::sanitize(self.root.clone(), profile);
 
         Ok(())
     }
@@ -118,6 +159,44 @@ impl MergeableImageboardThread for LainchanThread {
         Ok(Box::new(GetReplies { replies }))
     }
 
+    fn get_op(&self) -> Option<Self::Reply> {
+        let node = html::find_elements_with_classes(self.root.clone(), local_name!("div"), &["op"]).next()?;
+
+        let id = {
+            let data = node.as_element()?;
+            let attrs = data.attributes.borrow();
+
+            REGEX_GET_OP_ID.captures(attrs.get(local_name!("id"))?)?[1]
+                .parse::<u32>()
+                .ok()?
+        };
+
+        Some(LainchanReply { id, node })
+    }
+
+    fn get_post(&self, reply: &Self::Reply) -> Result<Post, ChandlerError> {
+        let mut post = POST_SELECTORS.get_post(reply.id, &reply.node);
+
+        // The OP's files are in a separate element before it.
+        let is_op = reply
+            .node
+            .as_element()
+            .is_some_and(|data| html::has_classes(data, &["op"]));
+
+        if is_op && post.files.is_empty() {
+            let files = reply.node.preceding_siblings().find(|node| {
+                node.as_element()
+                    .is_some_and(|data| html::has_classes(data, &["files"]))
+            });
+
+            if let Some(files) = files {
+                post.files = POST_SELECTORS.get_files(&files);
+            }
+        }
+
+        Ok(post)
+    }
+
     fn merge_replies_from(&mut self, new: Self) -> Result<Vec<Self::Reply>, ChandlerError> {
         // Create temporary insert marker node.
         let insert_marker_node = NodeRef::new_comment("INSERT");
@@ -182,14 +261,27 @@ impl MergeableImageboardThread for LainchanThread {
         Ok(())
     }
 
-    fn is_archived(&self) -> Result<bool, ChandlerError> {
-        Ok(false)
+    fn status(&self) -> Result<ThreadStatus, ChandlerError> {
+        Ok(STATUS_SELECTORS.get_status(&self.root))
+    }
+
+    fn has_insertion_point(&self) -> bool {
+        html::find_elements_with_classes(self.root.clone(), local_name!("div"), &["op"])
+            .next()
+            .is_some()
+    }
+
+    fn reply_text(&self, reply: &Self::Reply) -> String {
+        // Only the message is compared, as backlinks and the rest of the post's markup
+        // can change without the post being edited.
+        POST_SELECTORS.get_edit_text(&reply.node)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::html;
+    use crate::threadupdater;
 
     use super::*;
 
@@ -227,4 +319,43 @@ mod tests {
 
         assert_eq!(result_html, expected_html);
     }
+
+    #[test]
+    fn update_marks_deleted_replies() {
+        let result =
+            threadupdater::update_from_html(LainchanThread::from_document(html::parse_string(THREAD2)), THREAD3);
+
+        assert_eq!(result.new_post_count, 1);
+        assert_eq!(result.deleted_post_count, 1);
+    }
+
+    #[test]
+    fn update_from_non_thread_does_not_mark_deleted_replies() {
+        for page in threadupdater::NOT_THREAD_PAGES {
+            let result =
+                threadupdater::update_from_html(LainchanThread::from_document(html::parse_string(THREAD2)), page);
+
+            assert_eq!(result.deleted_post_count, 0);
+        }
+    }
+
+    // Thread with all status markers in the OP
+    const THREAD_STATUS: &str = r#"<div class="thread" id="thread_1"><div class="post op" id="op_1"><p class="intro"><i class="fa fa-lock"></i> <i class="fa fa-archive"></i> <i class="fa fa-anchor"></i> <i class="fa fa-image"></i></p></div><div class="postcontainer" id="pc2"></div></div>"#;
+
+    // Thread with status markers in a reply, which do not apply to the thread
+    const THREAD_STATUS_IN_REPLY: &str = r#"<div class="thread" id="thread_1"><div class="post op" id="op_1"></div><div class="postcontainer" id="pc2"><div class="post reply" id="reply_2"><p class="intro"><i class="fa fa-lock"></i> <i class="fa fa-anchor"></i></p></div></div></div>"#;
+
+    #[test]
+    fn can_get_status_from_markers() {
+        let thread = LainchanThread::from_document(html::parse_string(THREAD_STATUS));
+        let reply_thread = LainchanThread::from_document(html::parse_string(THREAD_STATUS_IN_REPLY));
+
+        let status = thread.status().unwrap();
+        assert!(status.is_locked);
+        assert!(status.is_archived);
+        assert!(status.bump_limit_reached);
+        assert!(status.image_limit_reached);
+
+        assert!(reply_thread.status().unwrap().is_open());
+    }
 }
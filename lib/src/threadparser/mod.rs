@@ -1,10 +1,16 @@
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
+use chrono::Utc;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::config::chandler::{EditPolicy, SanitizeProfile};
 use crate::error::*;
 use crate::html;
 
 pub mod aspnetchan;
 pub mod basic;
+pub mod custom;
 pub mod foolfuuka;
 pub mod fourchan;
 pub mod kusabax;
@@ -12,6 +18,34 @@ pub mod lainchan;
 pub mod ponychan;
 pub mod tinyboard;
 
+mod post;
+mod status;
+
+pub use self::post::*;
+pub use self::status::*;
+
+/// Status of a thread, as indicated by the site.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ThreadStatus {
+    /// The thread is locked, and cannot be replied to.
+    pub is_locked: bool,
+
+    /// The thread has been archived.
+    pub is_archived: bool,
+
+    /// The thread has reached the bump limit.
+    pub bump_limit_reached: bool,
+
+    /// The thread has reached the image limit.
+    pub image_limit_reached: bool,
+}
+
+impl ThreadStatus {
+    pub fn is_open(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 pub trait HtmlDocument: Sized {
     type Document;
 
@@ -19,19 +53,51 @@ pub trait HtmlDocument: Sized {
     fn into_document(self) -> Self::Document;
 
     fn from_file(file_path: &Path) -> Result<Self, ChandlerError>;
+
+    /// Load another document from file, using the same configuration as this one.
+    fn load_file_like(&self, file_path: &Path) -> Result<Self, ChandlerError> {
+        Self::from_file(file_path)
+    }
+
     fn write_file(&self, file_path: &Path) -> Result<(), ChandlerError>;
 
     fn for_links(&self, action: impl FnMut(html::Link) -> Result<(), ChandlerError>) -> Result<(), ChandlerError>;
 
-    /// Purge all script tags
-    fn purge_scripts(&self) -> Result<(), ChandlerError>;
+    /// Sanitize the document according to the profile.
+    fn sanitize(&self, profile: SanitizeProfile) -> Result<(), ChandlerError>;
+}
+
+pub trait ImageboardReply {
+    fn id(&self) -> u32;
+    fn node(&self) -> &kuchikiki::NodeRef;
 }
 
 pub trait MergeableImageboardThread: HtmlDocument {
-    type Reply;
+    type Reply: ImageboardReply;
 
     fn get_all_replies(&self) -> Result<Box<dyn Iterator<Item = Self::Reply>>, ChandlerError>;
 
+    /// Get the OP, if it can be found.
+    fn get_op(&self) -> Option<Self::Reply>;
+
+    /// Extract post data from a reply or the OP.
+    fn get_post(&self, reply: &Self::Reply) -> Result<Post, ChandlerError>;
+
+    /// Extract post data from all posts in the thread, starting with the OP.
+    fn get_all_posts(&self) -> Result<Vec<Post>, ChandlerError> {
+        let mut posts: Vec<Post> = Vec::new();
+
+        if let Some(op) = self.get_op() {
+            posts.push(self.get_post(&op)?);
+        }
+
+        for reply in self.get_all_replies()? {
+            posts.push(self.get_post(&reply)?);
+        }
+
+        Ok(posts)
+    }
+
     fn merge_replies_from(&mut self, new: Self) -> Result<Vec<Self::Reply>, ChandlerError>;
 
     fn for_reply_links(
@@ -40,5 +106,106 @@ pub trait MergeableImageboardThread: HtmlDocument {
         action: impl FnMut(html::Link) -> Result<(), ChandlerError>,
     ) -> Result<(), ChandlerError>;
 
-    fn is_archived(&self) -> Result<bool, ChandlerError>;
+    fn status(&self) -> Result<ThreadStatus, ChandlerError>;
+
+    /// Check whether the element that new replies are inserted relative to
+    /// (usually the OP) is present in the thread.
+    fn has_insertion_point(&self) -> bool;
+
+    /// Check whether the document was parsed as an actual thread, rather than for example an error page
+    /// or a page with a layout the parser does not recognize.
+    fn is_thread(&self) -> bool {
+        self.has_insertion_point() && self.get_op().is_some()
+    }
+
+    /// Mark all replies whose IDs are not in the specified set as deleted.
+    /// Returns the number of replies that were newly marked.
+    fn mark_deleted_replies(&self, live_ids: &HashSet<u32>) -> Result<u32, ChandlerError> {
+        let now = Utc::now();
+        let mut count: u32 = 0;
+
+        for reply in self.get_all_replies()? {
+            if live_ids.contains(&reply.id()) {
+                continue;
+            }
+
+            if html::mark_deleted(reply.node(), now) {
+                // Make sure the thread has the style for deleted posts.
+                if count == 0 {
+                    html::inject_style(reply.node());
+                }
+
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Get the text of a reply, used to detect whether it has been edited.
+    fn reply_text(&self, reply: &Self::Reply) -> String {
+        html::post_text(reply.node())
+    }
+
+    /// Apply changes to replies that are present in both this thread and the new one,
+    /// according to the edit policy.
+    /// Returns the replies that were newly found to be edited, as they are now in this thread.
+    fn merge_edits_from(&self, new: &Self, policy: EditPolicy) -> Result<Vec<Self::Reply>, ChandlerError> {
+        let mut new_replies: HashMap<u32, Self::Reply> = new.get_all_replies()?.map(|r| (r.id(), r)).collect();
+
+        let now = Utc::now();
+        let mut edited_replies: Vec<Self::Reply> = Vec::new();
+
+        let mut replies: Vec<Self::Reply> = self.get_all_replies()?.collect();
+
+        // The OP is compared too, unless its element also contains the replies,
+        // as it then cannot be replaced on its own.
+        if let (Some(op), Some(new_op)) = (self.get_op(), new.get_op()) {
+            if !contains_replies(self, &op)? && !contains_replies(new, &new_op)? {
+                new_replies.insert(new_op.id(), new_op);
+                replies.insert(0, op);
+            }
+        }
+
+        for reply in replies {
+            let Some(new_reply) = new_replies.remove(&reply.id()) else {
+                continue;
+            };
+
+            if self.reply_text(&reply) == new.reply_text(&new_reply) {
+                continue;
+            }
+
+            let edited_reply = match policy {
+                EditPolicy::KeepOriginal => {
+                    if !html::mark_edited(reply.node(), now) {
+                        continue;
+                    }
+
+                    reply
+                }
+                EditPolicy::Replace | EditPolicy::KeepBoth => {
+                    html::replace_edited(reply.node(), new_reply.node(), now, policy == EditPolicy::KeepBoth);
+
+                    new_reply
+                }
+            };
+
+            // Make sure the thread has the style for edited posts.
+            if edited_replies.is_empty() {
+                html::inject_style(edited_reply.node());
+            }
+
+            edited_replies.push(edited_reply);
+        }
+
+        Ok(edited_replies)
+    }
+}
+
+/// Check whether any of the thread's replies are inside the element of a post.
+fn contains_replies<T: MergeableImageboardThread>(thread: &T, post: &T::Reply) -> Result<bool, ChandlerError> {
+    Ok(thread
+        .get_all_replies()?
+        .any(|reply| reply.node().ancestors().any(|ancestor| &ancestor == post.node())))
 }
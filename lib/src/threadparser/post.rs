@@ -0,0 +1,431 @@
+use chrono::{DateTime, TimeZone, Utc};
+use html5ever::{local_name, LocalName};
+use kuchikiki::iter::NodeIterator;
+use kuchikiki::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::config::PostSelectorsDef;
+use crate::error::ChandlerError;
+use crate::html;
+
+static REGEX_FILE_SIZE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\b(\d+(?:\.\d+)?)\s*(B|KB|KiB|MB|MiB|GB|GiB)\b").unwrap());
+static REGEX_FILE_DIMENSIONS: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(\d+)\s*[x×]\s*(\d+)\b").unwrap());
+
+/// A post in a thread, extracted from its HTML.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Post {
+    /// Post number.
+    pub number: u32,
+
+    /// Poster's name.
+    pub name: Option<String>,
+
+    /// Poster's tripcode.
+    pub tripcode: Option<String>,
+
+    /// Poster ID, on boards that use them.
+    pub poster_id: Option<String>,
+
+    /// Time the post was made.
+    pub time: Option<DateTime<Utc>>,
+
+    /// Subject of the post.
+    pub subject: Option<String>,
+
+    /// Comment, as HTML.
+    pub comment_html: String,
+
+    /// Comment, as plain text with line breaks preserved.
+    pub comment_text: String,
+
+    /// Files attached to the post.
+    pub files: Vec<PostFile>,
+
+    /// The post has been deleted upstream.
+    pub is_deleted: bool,
+
+    /// The post has been edited upstream.
+    pub is_edited: bool,
+}
+
+/// A file attached to a post.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PostFile {
+    /// Link to the file, as it was on the site.
+    pub url: String,
+
+    /// Local path to the file, if it has been downloaded.
+    pub path: Option<String>,
+
+    /// Link to the thumbnail, as it was on the site.
+    pub thumbnail_url: Option<String>,
+
+    /// Local path to the thumbnail, if it has been downloaded.
+    pub thumbnail_path: Option<String>,
+
+    /// Original filename, as uploaded.
+    pub original_name: Option<String>,
+
+    /// File size in bytes, as shown on the site.
+    /// It is usually rounded.
+    pub size: Option<u64>,
+
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// CSS selectors used to extract post data from post elements.
+#[derive(Default)]
+pub struct PostSelectors {
+    subject: Option<Selectors>,
+    name: Option<Selectors>,
+    tripcode: Option<Selectors>,
+    poster_id: Option<Selectors>,
+    time: Option<Selectors>,
+    comment: Option<Selectors>,
+    file: Option<Selectors>,
+    file_link: Option<Selectors>,
+    file_thumbnail: Option<Selectors>,
+    file_name: Option<Selectors>,
+    file_info: Option<Selectors>,
+    file_deleted: Option<Selectors>,
+    exclude: Option<Selectors>,
+}
+
+impl PostSelectors {
+    pub fn new(def: &PostSelectorsDef) -> Result<Self, ChandlerError> {
+        fn compile(selector: &Option<String>) -> Result<Option<Selectors>, ChandlerError> {
+            selector
+                .as_deref()
+                .map(|selector| {
+                    Selectors::compile(selector)
+                        .map_err(|_| ChandlerError::Config(format!("Invalid CSS selector: {selector}").into()))
+                })
+                .transpose()
+        }
+
+        Ok(Self {
+            subject: compile(&def.subject_selector)?,
+            name: compile(&def.name_selector)?,
+            tripcode: compile(&def.tripcode_selector)?,
+            poster_id: compile(&def.poster_id_selector)?,
+            time: compile(&def.time_selector)?,
+            comment: compile(&def.comment_selector)?,
+            file: compile(&def.file_selector)?,
+            file_link: compile(&def.file_link_selector)?,
+            file_thumbnail: compile(&def.file_thumbnail_selector)?,
+            file_name: compile(&def.file_name_selector)?,
+            file_info: compile(&def.file_info_selector)?,
+            file_deleted: compile(&def.file_deleted_selector)?,
+            exclude: compile(&def.exclude_selector)?,
+        })
+    }
+
+    /// Extract post data from a post element.
+    pub fn get_post(&self, number: u32, node: &NodeRef) -> Post {
+        let text = |selector: &Option<Selectors>| {
+            selector
+                .as_ref()
+                .and_then(|selector| self.select_first(node, selector))
+                .and_then(|element| non_empty(html::post_text(&element)))
+        };
+
+        let comment = match &self.comment {
+            Some(selector) => self.select_first(node, selector),
+            None => Some(node.clone()),
+        };
+
+        let (comment_html, comment_text) = comment
+            .map(|comment| (inner_html(&comment), comment_text(&comment)))
+            .unwrap_or_default();
+
+        let (is_deleted, is_edited) = node.as_element().map_or((false, false), |data| {
+            (
+                html::has_classes(data, &[html::DELETED_CLASS]),
+                html::has_classes(data, &[html::EDITED_CLASS]),
+            )
+        });
+
+        Post {
+            number,
+            name: text(&self.name),
+            tripcode: text(&self.tripcode),
+            poster_id: text(&self.poster_id),
+            time: self
+                .time
+                .as_ref()
+                .and_then(|selector| self.select_first(node, selector))
+                .and_then(|element| parse_time(&element)),
+            subject: text(&self.subject),
+            comment_html,
+            comment_text,
+            files: self.get_files(node),
+            is_deleted,
+            is_edited,
+        }
+    }
+
+    /// Extract all files from an element.
+    pub fn get_files(&self, node: &NodeRef) -> Vec<PostFile> {
+        let Some(file_selector) = &self.file else {
+            return Vec::new();
+        };
+
+        self.select(node, file_selector)
+            .filter_map(|file| self.get_file(&file))
+            .collect()
+    }
+
+    fn get_file(&self, node: &NodeRef) -> Option<PostFile> {
+        let select_first =
+            |selector: &Option<Selectors>| selector.as_ref().and_then(|selector| self.select_first(node, selector));
+
+        let (url, path) = select_first(&self.file_link).and_then(|link| get_link(&link, "href"))?;
+
+        let (thumbnail_url, thumbnail_path) = select_first(&self.file_thumbnail)
+            .and_then(|thumbnail| get_link(&thumbnail, "src").or_else(|| get_link(&thumbnail, "href")))
+            .map_or((None, None), |(url, path)| (Some(url), path));
+
+        let name_element = select_first(&self.file_name);
+        let original_name = name_element
+            .as_ref()
+            .and_then(|name| get_attribute(name, local_name!("title")).or_else(|| non_empty(html::post_text(name))));
+
+        let mut info = select_first(&self.file_info)
+            .map(|info| html::post_text(&info))
+            .unwrap_or_else(|| html::post_text(node));
+
+        // Remove the filename, so that sizes or dimensions in it are not mistaken for the file's.
+        for name in name_element.iter().map(html::post_text).chain(original_name.clone()) {
+            if !name.is_empty() {
+                info = info.replace(&name, "");
+            }
+        }
+
+        let size = REGEX_FILE_SIZE.captures(&info).and_then(|caps| {
+            let value = caps[1].parse::<f64>().ok()?;
+            let multiplier: u64 = match caps[2].to_ascii_uppercase().as_str() {
+                "KB" | "KIB" => 1024,
+                "MB" | "MIB" => 1024 * 1024,
+                "GB" | "GIB" => 1024 * 1024 * 1024,
+                _ => 1,
+            };
+
+            Some((value * multiplier as f64).round() as u64)
+        });
+
+        let (width, height) = REGEX_FILE_DIMENSIONS
+            .captures(&info)
+            .map_or((None, None), |caps| (caps[1].parse().ok(), caps[2].parse().ok()));
+
+        Some(PostFile {
+            url,
+            path,
+            thumbnail_url,
+            thumbnail_path,
+            original_name,
+            size,
+            width,
+            height,
+        })
+    }
+
+    /// Get the text of a post used to detect whether it has been edited:
+    /// its comment, and whether its file has been deleted.
+    /// Versions kept from previous edits are ignored.
+    pub fn get_edit_text(&self, node: &NodeRef) -> String {
+        let is_current = |element: &NodeRef| {
+            !element
+                .ancestors()
+                .take_while(|ancestor| ancestor != node)
+                .elements()
+                .any(|ancestor| html::has_classes(&ancestor, &[html::EDIT_ORIGINAL_CLASS]))
+        };
+
+        let mut text = match &self.comment {
+            Some(selector) => self
+                .select(node, selector)
+                .find(is_current)
+                .map(|comment| html::post_text(&comment))
+                .unwrap_or_default(),
+            None => html::post_text(node),
+        };
+
+        if let Some(selector) = &self.file_deleted {
+            if self.select(node, selector).any(|element| is_current(&element)) {
+                text.push_str(" [File deleted]");
+            }
+        }
+
+        text
+    }
+
+    /// Get all elements inside the node matching the selector, in document order,
+    /// skipping those inside excluded elements.
+    fn select<'a>(&'a self, node: &'a NodeRef, selector: &'a Selectors) -> impl Iterator<Item = NodeRef> + 'a {
+        selector
+            .filter(node.descendants().elements())
+            .map(|element| element.as_node().clone())
+            .filter(move |element| !self.is_excluded(node, element))
+    }
+
+    fn select_first(&self, node: &NodeRef, selector: &Selectors) -> Option<NodeRef> {
+        self.select(node, selector).next()
+    }
+
+    /// Check whether an element is inside an excluded element within the node.
+    fn is_excluded(&self, node: &NodeRef, element: &NodeRef) -> bool {
+        let Some(exclude) = &self.exclude else {
+            return false;
+        };
+
+        element
+            .ancestors()
+            .take_while(|ancestor| ancestor != node)
+            .elements()
+            .any(|ancestor| exclude.matches(&ancestor))
+    }
+}
+
+fn get_attribute(node: &NodeRef, name: LocalName) -> Option<String> {
+    node.as_element()
+        .and_then(|data| data.attributes.borrow().get(name).map(str::to_owned))
+        .and_then(non_empty)
+}
+
+/// Get the original URL of a link, and its local path if it has been replaced.
+fn get_link(node: &NodeRef, attr_name: &str) -> Option<(String, Option<String>)> {
+    let link = get_attribute(node, LocalName::from(attr_name))?;
+
+    Some(
+        match get_attribute(node, LocalName::from(format!("data-original-{attr_name}"))) {
+            Some(original) => (original, Some(link)),
+            None => (link, None),
+        },
+    )
+}
+
+fn non_empty(s: String) -> Option<String> {
+    let s = s.trim();
+
+    (!s.is_empty()).then(|| s.to_owned())
+}
+
+/// Get the time from a "datetime", "data-utc" or "data-time" attribute.
+fn parse_time(node: &NodeRef) -> Option<DateTime<Utc>> {
+    if let Some(time) = get_attribute(node, local_name!("datetime")) {
+        return DateTime::parse_from_rfc3339(&time)
+            .ok()
+            .map(|time| time.with_timezone(&Utc));
+    }
+
+    ["data-utc", "data-time"]
+        .into_iter()
+        .find_map(|name| get_attribute(node, LocalName::from(name)))
+        .and_then(|time| time.parse::<i64>().ok())
+        .and_then(|time| Utc.timestamp_opt(time, 0).single())
+}
+
+/// Serialize the children of a node.
+fn inner_html(node: &NodeRef) -> String {
+    node.children().map(|child| child.to_string()).collect()
+}
+
+/// Get the text of a comment, with line breaks preserved.
+fn comment_text(node: &NodeRef) -> String {
+    fn collect_text(node: &NodeRef, text: &mut String) {
+        for child in node.children() {
+            match child.data() {
+                NodeData::Text(t) => text.push_str(&t.borrow()),
+                NodeData::Element(data) => match data.name.local {
+                    local_name!("br") => text.push('\n'),
+                    local_name!("script") | local_name!("style") => {}
+                    _ if html::has_classes(data, &[html::EDIT_ORIGINAL_CLASS]) => {}
+                    _ => collect_text(&child, text),
+                },
+                _ => {}
+            }
+        }
+    }
+
+    let mut text = String::new();
+    collect_text(node, &mut text);
+
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POST: &str = r#"<div class="post" id="p2"><span class="name">Anonymous</span> <span class="trip">!Trip</span> <time datetime="2024-01-01T00:00:00Z">01/01/24</time><div class="file"><a href="content/123.png" data-original-href="src/123.png">123.png</a> (1.5 MB, 1920x1080, <span class="postfilename" title="1x1 wallpaper.png">1x1 wallpaper.png</span>)<img class="thumb" src="thumb/123s.jpg"></div><blockquote>Hello<br>  world <b>!</b></blockquote><div class="replies"><div class="post"><span class="name">Nested</span></div></div></div>"#;
+
+    #[test]
+    fn can_get_post() {
+        let document = html::parse_string(POST);
+        let node = html::find_elements(document, |data| data.name.local == local_name!("div"))
+            .next()
+            .unwrap();
+
+        let selectors = PostSelectors::new(&PostSelectorsDef {
+            name_selector: Some(".name".to_owned()),
+            tripcode_selector: Some(".trip".to_owned()),
+            poster_id_selector: Some(".poster_id".to_owned()),
+            time_selector: Some("time".to_owned()),
+            comment_selector: Some("blockquote".to_owned()),
+            file_selector: Some(".file".to_owned()),
+            file_link_selector: Some("a".to_owned()),
+            file_thumbnail_selector: Some(".thumb".to_owned()),
+            file_name_selector: Some(".postfilename".to_owned()),
+            exclude_selector: Some(".replies".to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let post = selectors.get_post(2, &node);
+
+        assert_eq!(
+            post,
+            Post {
+                number: 2,
+                name: Some("Anonymous".to_owned()),
+                tripcode: Some("!Trip".to_owned()),
+                time: Some(DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().into()),
+                comment_html: "Hello<br>  world <b>!</b>".to_owned(),
+                comment_text: "Hello\nworld !".to_owned(),
+                files: vec![PostFile {
+                    url: "src/123.png".to_owned(),
+                    path: Some("content/123.png".to_owned()),
+                    thumbnail_url: Some("thumb/123s.jpg".to_owned()),
+                    thumbnail_path: None,
+                    original_name: Some("1x1 wallpaper.png".to_owned()),
+                    size: Some(1572864),
+                    width: Some(1920),
+                    height: Some(1080),
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn can_parse_unix_time() {
+        let document = html::parse_string(r#"<span class="dateTime" data-utc="1704067200">01/01/24</span>"#);
+        let node = html::find_elements(document, |data| data.name.local == local_name!("span"))
+            .next()
+            .unwrap();
+
+        assert_eq!(
+            parse_time(&node),
+            Some(DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().into())
+        );
+    }
+}
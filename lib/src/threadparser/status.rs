@@ -0,0 +1,48 @@
+use kuchikiki::iter::NodeIterator;
+use kuchikiki::*;
+
+use crate::error::ChandlerError;
+
+use super::ThreadStatus;
+
+/// CSS selectors for the elements an imageboard engine uses to show the status of a thread,
+/// relative to the thread's root element.
+pub struct StatusSelectors {
+    locked: Selectors,
+    archived: Selectors,
+    bump_limit: Selectors,
+    image_limit: Selectors,
+}
+
+impl StatusSelectors {
+    pub fn new(locked: &str, archived: &str, bump_limit: &str, image_limit: &str) -> Result<Self, ChandlerError> {
+        fn compile(selector: &str) -> Result<Selectors, ChandlerError> {
+            Selectors::compile(selector)
+                .map_err(|_| ChandlerError::Config(format!("Invalid CSS selector: {selector}").into()))
+        }
+
+        Ok(Self {
+            locked: compile(locked)?,
+            archived: compile(archived)?,
+            bump_limit: compile(bump_limit)?,
+            image_limit: compile(image_limit)?,
+        })
+    }
+
+    /// Get thread status from the elements in the thread matching the selectors.
+    pub fn get_status(&self, root: &NodeRef) -> ThreadStatus {
+        let matches = |selector: &Selectors| {
+            selector
+                .filter(root.inclusive_descendants().elements())
+                .next()
+                .is_some()
+        };
+
+        ThreadStatus {
+            is_locked: matches(&self.locked),
+            is_archived: matches(&self.archived),
+            bump_limit_reached: matches(&self.bump_limit),
+            image_limit_reached: matches(&self.image_limit),
+        }
+    }
+}
@@ -7,6 +7,7 @@ use kuchikiki::*;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::config::PostSelectorsDef;
 use crate::error::ChandlerError;
 use crate::html;
 use crate::util;
@@ -14,6 +15,36 @@ use crate::util;
 use super::*;
 
 static REGEX_GET_REPLY_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"^reply_(\d+)").unwrap());
+static REGEX_GET_OP_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"^op_(\d+)").unwrap());
+
+static POST_SELECTORS: Lazy<PostSelectors> = Lazy::new(|| {
+    PostSelectors::new(&PostSelectorsDef {
+        subject_selector: Some(".intro .subject".to_owned()),
+        name_selector: Some(".intro .name".to_owned()),
+        tripcode_selector: Some(".intro .trip".to_owned()),
+        poster_id_selector: Some(".intro .poster_id".to_owned()),
+        time_selector: Some(".intro time".to_owned()),
+        comment_selector: Some(".body".to_owned()),
+        file_selector: Some(".file".to_owned()),
+        file_link_selector: Some(".fileinfo a".to_owned()),
+        file_thumbnail_selector: Some(".post-image".to_owned()),
+        file_name_selector: Some(".postfilename".to_owned()),
+        file_info_selector: Some(".fileinfo".to_owned()),
+        file_deleted_selector: Some("img.deleted".to_owned()),
+        ..Default::default()
+    })
+    .unwrap()
+});
+
+static STATUS_SELECTORS: Lazy<StatusSelectors> = Lazy::new(|| {
+    StatusSelectors::new(
+        ".op .intro .fa-lock",
+        ".op .intro .fa-archive",
+        ".op .intro .fa-anchor",
+        ".op .intro .fa-image",
+    )
+    .unwrap()
+});
 
 pub struct TinyboardThread {
     pub root: NodeRef,
@@ -25,6 +56,16 @@ pub struct TinyboardReply {
     pub node: NodeRef,
 }
 
+impl ImageboardReply for TinyboardReply {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn node(&self) -> &NodeRef {
+        &self.node
+    }
+}
+
 impl TinyboardReply {
     pub fn from_node(node: NodeRef) -> Option<Self> {
         // Try to get reply ID from node.
@@ -101,8 +142,8 @@ impl HtmlDocument for TinyboardThread {
         Ok(())
     }
 
-    fn purge_scripts(&self) -> Result<(), ChandlerError> {
-        html::purge_scripts(self.root.clone());
+    fn sanitize(&self, profile: SanitizeProfile) -> Result<(), ChandlerError> {
+        html::sanitize(self.root.clone(), profile);
 
         Ok(())
     }
@@ -118,6 +159,44 @@ impl MergeableImageboardThread for TinyboardThread {
         Ok(Box::new(GetReplies { replies }))
     }
 
+    fn get_op(&self) -> Option<Self::Reply> {
+        let node = html::find_elements_with_classes(self.root.clone(), local_name!("div"), &["op"]).next()?;
+
+        let id = {
+            let data = node.as_element()?;
+            let attrs = data.attributes.borrow();
+
+            REGEX_GET_OP_ID.captures(attrs.get(local_name!("id"))?)?[1]
+                .parse::<u32>()
+                .ok()?
+        };
+
+        Some(TinyboardReply { id, node })
+    }
+
+    fn get_post(&self, reply: &Self::Reply) -> Result<Post, ChandlerError> {
+        let mut post = POST_SELECTORS.get_post(reply.id, &reply.node);
+
+        // The OP's files are in a separate element before it.
+        let is_op = reply
+            .node
+            .as_element()
+            .is_some_and(|data| html::has_classes(data, &["op"]));
+
+        if is_op && post.files.is_empty() {
+            let files = reply.node.preceding_siblings().find(|node| {
+                node.as_element()
+                    .is_some_and(|data| html::has_classes(data, &["files"]))
+            });
+
+            if let Some(files) = files {
+                post.files = POST_SELECTORS.get_files(&files);
+            }
+        }
+
+        Ok(post)
+    }
+
     fn merge_replies_from(&mut self, new: Self) -> Result<Vec<Self::Reply>, ChandlerError> {
         // Create temporary insert marker node.
         let insert_marker_node = NodeRef::new_comment("INSERT");
@@ -182,14 +261,28 @@ impl MergeableImageboardThread for TinyboardThread {
         Ok(())
     }
 
-    fn is_archived(&self) -> Result<bool, ChandlerError> {
-        Ok(false)
+    fn status(&self) -> Result<ThreadStatus, ChandlerError> {
+        Ok(STATUS_SELECTORS.get_status(&self.root))
+    }
+
+    fn has_insertion_point(&self) -> bool {
+        html::find_elements_with_classes(self.root.clone(), local_name!("div"), &["op"])
+            .next()
+            .is_some()
+    }
+
+    fn reply_text(&self, reply: &Self::Reply) -> String {
+        // Only the message is compared, as backlinks and the rest of the post's markup
+        // can change without the post being edited.
+        POST_SELECTORS.get_edit_text(&reply.node)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::config::chandler::EditPolicy;
     use crate::html;
+    use crate::threadupdater;
 
     use super::*;
 
@@ -205,6 +298,9 @@ mod tests {
     // Merged thread with all 3 posts
     const THREAD_MERGED: &'static str = r#"<div class="thread" id="thread_1"><div class="post op" id="op_1"></div><div class="post reply" id="reply_2"></div><br><div class="post reply" id="reply_3"></div></div>"#;
 
+    // Locked thread, with a reply that happens to contain an archive icon
+    const THREAD_LOCKED: &str = r#"<div class="thread" id="thread_1"><div class="post op" id="op_1"><p class="intro"><i class="fa fa-lock" title="Locked"></i></p></div><div class="post reply" id="reply_2"><img alt="Archived"></div></div>"#;
+
     #[test]
     fn can_merge_threads() {
         let node1 = html::parse_string(THREAD1);
@@ -227,4 +323,102 @@ mod tests {
 
         assert_eq!(result_html, expected_html);
     }
+
+    #[test]
+    fn can_get_status() {
+        let open_thread = TinyboardThread::from_document(html::parse_string(THREAD2));
+        let locked_thread = TinyboardThread::from_document(html::parse_string(THREAD_LOCKED));
+
+        assert!(open_thread.status().unwrap().is_open());
+
+        let status = locked_thread.status().unwrap();
+        assert!(status.is_locked);
+        assert!(!status.is_archived);
+    }
+
+    // Thread with a file in the OP, which is outside the OP element
+    const THREAD_POSTS: &str = r#"<div class="thread" id="thread_1"><div class="files"><div class="file"><p class="fileinfo">File: <a href="/b/src/1.png">1.png</a> <span class="unimportant">(45.5 KB, 800x600, <span class="postfilename">cat.png</span>)</span></p><img class="post-image" src="/b/thumb/1.png"></div></div><div class="post op" id="op_1"><p class="intro"><span class="subject">Cats</span> <span class="name">Anonymous</span> <time datetime="2024-01-01T00:00:00Z">01/01/24</time></p><div class="body">Post cats</div></div><div class="post reply" id="reply_2"><p class="intro"><span class="name">Anonymous</span> <span class="trip">!Trip</span> <span class="poster_id">abc123</span></p><div class="body">No</div></div></div>"#;
+
+    #[test]
+    fn can_get_posts() {
+        let thread = TinyboardThread::from_document(html::parse_string(THREAD_POSTS));
+
+        let posts = thread.get_all_posts().unwrap();
+        assert_eq!(posts.len(), 2);
+
+        let op = &posts[0];
+        assert_eq!(op.number, 1);
+        assert_eq!(op.subject.as_deref(), Some("Cats"));
+        assert_eq!(op.time.map(|t| t.timestamp()), Some(1704067200));
+        assert_eq!(op.comment_text, "Post cats");
+        assert_eq!(op.files.len(), 1);
+        assert_eq!(op.files[0].url, "/b/src/1.png");
+        assert_eq!(op.files[0].original_name.as_deref(), Some("cat.png"));
+        assert_eq!(op.files[0].size, Some(46592));
+        assert_eq!(op.files[0].width, Some(800));
+
+        let reply = &posts[1];
+        assert_eq!(reply.number, 2);
+        assert_eq!(reply.tripcode.as_deref(), Some("!Trip"));
+        assert_eq!(reply.poster_id.as_deref(), Some("abc123"));
+        assert!(reply.files.is_empty());
+    }
+
+    #[test]
+    fn update_marks_deleted_replies() {
+        let result =
+            threadupdater::update_from_html(TinyboardThread::from_document(html::parse_string(THREAD2)), THREAD3);
+
+        assert_eq!(result.new_post_count, 1);
+        assert_eq!(result.deleted_post_count, 1);
+    }
+
+    #[test]
+    fn update_from_non_thread_does_not_mark_deleted_replies() {
+        for page in threadupdater::NOT_THREAD_PAGES {
+            let result =
+                threadupdater::update_from_html(TinyboardThread::from_document(html::parse_string(THREAD2)), page);
+
+            assert_eq!(result.deleted_post_count, 0);
+        }
+    }
+
+    // Thread with a file in post 2, as originally downloaded
+    const THREAD_EDIT1: &str = r#"<div class="thread" id="thread_1"><div class="post op" id="op_1"><div class="body">Hello</div></div><div class="post reply" id="reply_2"><div class="file"><img class="post-image" src="/b/thumb/2.png"></div><div class="body">Reply</div></div></div>"#;
+
+    // Thread with the OP banned and the file in post 2 deleted
+    const THREAD_EDIT2: &str = r#"<div class="thread" id="thread_1"><div class="post op" id="op_1"><div class="body">Hello<br><span class="public_ban">(USER WAS BANNED FOR THIS POST)</span></div></div><div class="post reply" id="reply_2"><div class="file"><img class="post-image deleted" src="/static/deleted.png"></div><div class="body">Reply</div></div></div>"#;
+
+    #[test]
+    fn can_merge_edits() {
+        let thread1 = TinyboardThread::from_document(html::parse_string(THREAD_EDIT1));
+        let thread2 = TinyboardThread::from_document(html::parse_string(THREAD_EDIT2));
+
+        let edited = thread1.merge_edits_from(&thread2, EditPolicy::Replace).unwrap();
+        assert_eq!(edited.iter().map(|reply| reply.id).collect::<Vec<_>>(), [1, 2]);
+
+        let result_html = html::to_string(thread1.into_document());
+        assert!(result_html.contains("(USER WAS BANNED FOR THIS POST)"));
+        assert!(result_html.contains("/static/deleted.png"));
+    }
+
+    // Thread with all status markers in the OP
+    const THREAD_STATUS: &str = r#"<div class="thread" id="thread_1"><div class="post op" id="op_1"><p class="intro"><i class="fa fa-lock"></i> <i class="fa fa-archive"></i> <i class="fa fa-anchor"></i> <i class="fa fa-image"></i></p></div><div class="post reply" id="reply_2"></div></div>"#;
+
+    // Thread with status markers in a reply, which do not apply to the thread
+    const THREAD_STATUS_IN_REPLY: &str = r#"<div class="thread" id="thread_1"><div class="post op" id="op_1"></div><div class="post reply" id="reply_2"><p class="intro"><i class="fa fa-lock"></i> <i class="fa fa-anchor"></i></p></div></div>"#;
+
+    #[test]
+    fn can_get_status_from_markers() {
+        let thread = TinyboardThread::from_document(html::parse_string(THREAD_STATUS));
+        let reply_thread = TinyboardThread::from_document(html::parse_string(THREAD_STATUS_IN_REPLY));
+
+        let status = thread.status().unwrap();
+        assert!(status.is_locked);
+        assert!(status.is_archived);
+        assert!(status.bump_limit_reached);
+        assert!(status.image_limit_reached);
+
+        assert!(reply_thread.status().unwrap().is_open());
+    }
 }
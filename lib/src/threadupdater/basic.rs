@@ -1,24 +1,27 @@
+use crate::config::chandler::SanitizeProfile;
 use crate::threadparser::basic::BasicThread;
-use crate::threadparser::HtmlDocument;
+use crate::threadparser::{HtmlDocument, Post};
 
 use super::*;
 
 pub struct BasicThreadUpdater {
     thread: BasicThread,
+    sanitize: SanitizeProfile,
 }
 
 impl BasicThreadUpdater {
-    pub fn from_file(file_path: &Path) -> Result<Self, ChandlerError> {
+    pub fn from_file(file_path: &Path, sanitize: SanitizeProfile) -> Result<Self, ChandlerError> {
         Ok(Self {
             thread: BasicThread::from_file(file_path)?,
+            sanitize,
         })
     }
 }
 
 impl ThreadUpdater for BasicThreadUpdater {
     fn perform_initial_cleanup(&mut self) -> Result<UpdateResult, ChandlerError> {
-        // Purge all script tags from the thread HTML.
-        self.thread.purge_scripts()?;
+        // Sanitize the thread HTML.
+        self.thread.sanitize(self.sanitize)?;
 
         let mut new_links: Vec<html::Link> = Vec::new();
 
@@ -30,8 +33,10 @@ impl ThreadUpdater for BasicThreadUpdater {
         })?;
 
         Ok(UpdateResult {
-            is_archived: false,
+            status: ThreadStatus::default(),
             new_post_count: 0,
+            edited_post_count: 0,
+            deleted_post_count: 0,
             new_links,
         })
     }
@@ -46,4 +51,12 @@ impl ThreadUpdater for BasicThreadUpdater {
     fn write_file(&self, file_path: &Path) -> Result<(), ChandlerError> {
         self.thread.write_file(file_path)
     }
+
+    fn for_links(&self, action: &mut dyn FnMut(html::Link) -> Result<(), ChandlerError>) -> Result<(), ChandlerError> {
+        self.thread.for_links(action)
+    }
+
+    fn posts(&self) -> Result<Vec<Post>, ChandlerError> {
+        Ok(Vec::new())
+    }
 }
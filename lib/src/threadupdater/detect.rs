@@ -0,0 +1,110 @@
+use kuchikiki::NodeRef;
+use tracing::info;
+
+use crate::threadparser::MergeableImageboardThread;
+
+use super::*;
+
+/// Detect the parser that best matches a thread page.
+///
+/// Each parser is run against the page, and the parser that finds the OP
+/// and the most replies, with their post IDs, is selected.
+/// If no parser recognizes the page, the basic parser is used.
+pub fn detect_parser(file_path: &Path) -> Result<ParserType, ChandlerError> {
+    let document = html::parse_file(file_path)?;
+
+    detect_parser_from_document(&document)
+}
+
+pub fn detect_parser_from_document(document: &NodeRef) -> Result<ParserType, ChandlerError> {
+    // Parsers are listed in order of preference, in case of a tie.
+    let probes = [
+        (ParserType::FourChan, probe::<FourchanThread>(document)?),
+        (ParserType::Tinyboard, probe::<TinyboardThread>(document)?),
+        (ParserType::Kusabax, probe::<KusabaxThread>(document)?),
+        (ParserType::Lainchan, probe::<LainchanThread>(document)?),
+        (ParserType::Ponychan, probe::<PonychanThread>(document)?),
+        (ParserType::FoolFuuka, probe::<FoolFuukaThread>(document)?),
+        (ParserType::AspNetChan, probe::<AspNetChanThread>(document)?),
+    ];
+
+    let mut best: Option<(ParserType, u32)> = None;
+
+    for (parser, reply_count) in probes {
+        let Some(reply_count) = reply_count else {
+            continue;
+        };
+
+        if best.as_ref().is_none_or(|(_, best_count)| reply_count > *best_count) {
+            best = Some((parser, reply_count));
+        }
+    }
+
+    Ok(if let Some((parser, reply_count)) = best {
+        info!("Detected parser: {:?} ({} replies matched)", parser, reply_count);
+
+        parser
+    } else {
+        info!("No parser recognized the thread, using basic parser.");
+
+        ParserType::Basic
+    })
+}
+
+/// Probe the document using the specified parser.
+/// Returns the number of replies whose ID was found, or None if the parser does not find the OP.
+fn probe<TP: MergeableImageboardThread<Document = NodeRef>>(document: &NodeRef) -> Result<Option<u32>, ChandlerError> {
+    let thread = TP::from_document(document.clone());
+
+    if !thread.is_thread() {
+        return Ok(None);
+    }
+
+    Ok(Some(thread.get_all_replies()?.count() as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::download::{test_dir, TestResponse, TestServer};
+    use crate::project::test_project;
+
+    const FOURCHAN_THREAD: &str = r#"<div class="thread" id="t1"><div class="opContainer" id="pc1"></div><div class="replyContainer" id="pc2"></div><div class="replyContainer" id="pc3"></div></div>"#;
+
+    const TINYBOARD_THREAD: &str =
+        r#"<div class="thread"><div class="post op" id="op_1"></div><div class="post reply" id="reply_2"></div></div>"#;
+
+    const TINYBOARD_OP_ONLY_THREAD: &str = r#"<div class="thread"><div class="post op" id="op_1"></div></div>"#;
+
+    const KUSABAX_THREAD: &str = r#"<div id="thread_1_a"><div class="post" id="1"></div><div id="replies_1_a" class="replies"><div class="reply" id="reply_2"></div></div></div>"#;
+
+    const UNKNOWN_THREAD: &str = r#"<div class="topic"><p>Hello</p></div>"#;
+
+    fn detect(html_str: &str) -> ParserType {
+        detect_parser_from_document(&html::parse_string(html_str)).unwrap()
+    }
+
+    #[test]
+    fn can_detect_parser() {
+        assert!(matches!(detect(FOURCHAN_THREAD), ParserType::FourChan));
+        assert!(matches!(detect(TINYBOARD_THREAD), ParserType::Tinyboard));
+        assert!(matches!(detect(TINYBOARD_OP_ONLY_THREAD), ParserType::Tinyboard));
+        assert!(matches!(detect(KUSABAX_THREAD), ParserType::Kusabax));
+        assert!(matches!(detect(UNKNOWN_THREAD), ParserType::Basic));
+    }
+
+    #[test]
+    fn detects_parser_for_unknown_site() {
+        let server =
+            TestServer::start(|_| TestResponse::ok(format!("<html><body>{TINYBOARD_THREAD}</body></html>").as_bytes()));
+
+        let path = test_dir("detect_parser");
+        test_project(&server, &path, None);
+
+        let config = fs::read_to_string(path.join(".chandler3").join("thread.json")).unwrap();
+        assert!(config.contains(r#""parser": "tinyboard""#));
+        assert!(config.contains(r#""parser_detected": true"#));
+    }
+}
@@ -1,23 +1,31 @@
-use crate::threadparser::MergeableImageboardThread;
+use std::collections::HashSet;
+
+use tracing::warn;
+
+use crate::config::chandler::{EditPolicy, UpdateConfig};
+use crate::threadparser::{ImageboardReply, MergeableImageboardThread, Post};
 
 use super::*;
 
 pub struct MergingThreadUpdater<TP: MergeableImageboardThread> {
     thread: TP,
+    config: UpdateConfig,
 }
 
 impl<TP: MergeableImageboardThread> MergingThreadUpdater<TP> {
-    pub fn from_file(file_path: &Path) -> Result<Self, ChandlerError> {
-        Ok(Self {
-            thread: TP::from_file(file_path)?,
-        })
+    pub fn new(thread: TP, config: UpdateConfig) -> Self {
+        Self { thread, config }
+    }
+
+    pub fn from_file(file_path: &Path, config: UpdateConfig) -> Result<Self, ChandlerError> {
+        Ok(Self::new(TP::from_file(file_path)?, config))
     }
 }
 
 impl<TP: MergeableImageboardThread> ThreadUpdater for MergingThreadUpdater<TP> {
     fn perform_initial_cleanup(&mut self) -> Result<UpdateResult, ChandlerError> {
-        // Purge all script tags from the thread HTML.
-        self.thread.purge_scripts()?;
+        // Sanitize the thread HTML.
+        self.thread.sanitize(self.config.sanitize)?;
 
         let new_post_count = (1 + self.thread.get_all_replies().map_or(0, |iter| iter.count())) as u32;
 
@@ -30,11 +38,13 @@ impl<TP: MergeableImageboardThread> ThreadUpdater for MergingThreadUpdater<TP> {
             Ok(())
         })?;
 
-        let is_archived = self.thread.is_archived()?;
+        let status = self.thread.status()?;
 
         Ok(UpdateResult {
-            is_archived,
+            status,
             new_post_count,
+            edited_post_count: 0,
+            deleted_post_count: 0,
             new_links,
         })
     }
@@ -43,18 +53,53 @@ impl<TP: MergeableImageboardThread> ThreadUpdater for MergingThreadUpdater<TP> {
         let thread = &mut self.thread;
 
         // Parse new thread.
-        let new_thread = TP::from_file(path)?;
+        let new_thread = thread.load_file_like(path)?;
+
+        // Sanitize the new thread, so that edits are compared against sanitized posts
+        // and new posts are sanitized before being merged.
+        new_thread.sanitize(self.config.sanitize)?;
+
+        // If the new thread could not be parsed, for example because an error page was served instead,
+        // there is nothing to merge, and posts being missing does not mean they were deleted.
+        if !new_thread.is_thread() {
+            warn!("No thread found in {}, ignoring it.", path.display());
+
+            return Ok(UpdateResult {
+                status: thread.status()?,
+                new_post_count: 0,
+                edited_post_count: 0,
+                deleted_post_count: 0,
+                new_links: Vec::new(),
+            });
+        }
+
+        let status = new_thread.status()?;
 
-        let is_archived = new_thread.is_archived()?;
+        // Get the IDs of all replies still present in the new thread.
+        let live_ids: HashSet<u32> = new_thread.get_all_replies()?.map(|r| r.id()).collect();
+
+        // Apply changes to posts that are already in the main thread.
+        let edited_replies = thread.merge_edits_from(&new_thread, self.config.edit_policy)?;
+        let edited_post_count = edited_replies.len() as u32;
 
         // Merge posts from new thread into the main thread.
         let new_replies = thread.merge_replies_from(new_thread)?;
         let new_post_count = new_replies.len() as u32;
 
+        // Mark posts that are no longer present in the new thread as deleted.
+        let deleted_post_count = thread.mark_deleted_replies(&live_ids)?;
+
         let mut new_links: Vec<html::Link> = Vec::new();
 
         // Process links for all new replies.
-        for reply in new_replies.iter() {
+        // Replies that were replaced by a new version may also contain new links.
+        let replaced_replies = if self.config.edit_policy == EditPolicy::KeepOriginal {
+            &[]
+        } else {
+            edited_replies.as_slice()
+        };
+
+        for reply in new_replies.iter().chain(replaced_replies) {
             thread.for_reply_links(reply, |link| {
                 new_links.push(link);
 
@@ -63,8 +108,10 @@ impl<TP: MergeableImageboardThread> ThreadUpdater for MergingThreadUpdater<TP> {
         }
 
         Ok(UpdateResult {
-            is_archived,
+            status,
             new_post_count,
+            edited_post_count,
+            deleted_post_count,
             new_links,
         })
     }
@@ -72,4 +119,12 @@ impl<TP: MergeableImageboardThread> ThreadUpdater for MergingThreadUpdater<TP> {
     fn write_file(&self, file_path: &Path) -> Result<(), ChandlerError> {
         self.thread.write_file(file_path)
     }
+
+    fn for_links(&self, action: &mut dyn FnMut(html::Link) -> Result<(), ChandlerError>) -> Result<(), ChandlerError> {
+        self.thread.for_links(action)
+    }
+
+    fn posts(&self) -> Result<Vec<Post>, ChandlerError> {
+        self.thread.get_all_posts()
+    }
 }
@@ -1,22 +1,35 @@
 use std::path::Path;
+use std::sync::Arc;
 
+use crate::config::chandler::UpdateConfig;
+use crate::config::CustomParserDef;
 use crate::error::ChandlerError;
 use crate::html;
 use crate::threadparser::aspnetchan::AspNetChanThread;
+use crate::threadparser::custom::{CustomParser, CustomThread};
 use crate::threadparser::foolfuuka::FoolFuukaThread;
 use crate::threadparser::fourchan::FourchanThread;
 use crate::threadparser::kusabax::KusabaxThread;
 use crate::threadparser::lainchan::LainchanThread;
 use crate::threadparser::ponychan::PonychanThread;
 use crate::threadparser::tinyboard::TinyboardThread;
+use crate::threadparser::{Post, ThreadStatus};
 
 mod basic;
+mod detect;
 mod merging;
 
+#[cfg(test)]
+mod test_only;
+
 pub use self::basic::*;
+pub use self::detect::*;
 pub use self::merging::*;
 
-#[derive(Clone, Copy, Debug)]
+#[cfg(test)]
+pub use self::test_only::*;
+
+#[derive(Clone, Debug)]
 pub enum ParserType {
     Basic,
     FourChan,
@@ -26,36 +39,66 @@ pub enum ParserType {
     FoolFuuka,
     Ponychan,
     Lainchan,
+    Custom(Arc<CustomParser>),
 }
 
 pub trait CreateThreadUpdater {
-    fn create_thread_updater_from(&self, path: &Path) -> Result<Box<dyn ThreadUpdater>, ChandlerError>;
+    fn create_thread_updater_from(
+        &self,
+        path: &Path,
+        config: UpdateConfig,
+    ) -> Result<Box<dyn ThreadUpdater>, ChandlerError>;
 }
 
 pub trait ThreadUpdater {
     fn perform_initial_cleanup(&mut self) -> Result<UpdateResult, ChandlerError>;
     fn update_from(&mut self, path: &Path) -> Result<UpdateResult, ChandlerError>;
     fn write_file(&self, file_path: &Path) -> Result<(), ChandlerError>;
+    fn for_links(&self, action: &mut dyn FnMut(html::Link) -> Result<(), ChandlerError>) -> Result<(), ChandlerError>;
+
+    /// Get all posts in the thread, starting with the OP.
+    /// Threads whose parser cannot identify posts have none.
+    fn posts(&self) -> Result<Vec<Post>, ChandlerError>;
 }
 
 #[derive(Debug)]
 pub struct UpdateResult {
-    pub is_archived: bool,
+    pub status: ThreadStatus,
     pub new_post_count: u32,
+    pub edited_post_count: u32,
+    pub deleted_post_count: u32,
     pub new_links: Vec<html::Link>,
 }
 
+impl ParserType {
+    /// Get the custom parser definition, if this is a custom parser.
+    pub fn custom_parser_def(&self) -> Option<&CustomParserDef> {
+        match self {
+            Self::Custom(parser) => Some(&parser.def),
+            _ => None,
+        }
+    }
+}
+
 impl CreateThreadUpdater for ParserType {
-    fn create_thread_updater_from(&self, path: &Path) -> Result<Box<dyn ThreadUpdater>, ChandlerError> {
+    fn create_thread_updater_from(
+        &self,
+        path: &Path,
+        config: UpdateConfig,
+    ) -> Result<Box<dyn ThreadUpdater>, ChandlerError> {
         Ok(match self {
-            Self::Basic => Box::new(BasicThreadUpdater::from_file(path)?),
-            Self::FourChan => Box::new(MergingThreadUpdater::<FourchanThread>::from_file(path)?),
-            Self::Tinyboard => Box::new(MergingThreadUpdater::<TinyboardThread>::from_file(path)?),
-            Self::AspNetChan => Box::new(MergingThreadUpdater::<AspNetChanThread>::from_file(path)?),
-            Self::Kusabax => Box::new(MergingThreadUpdater::<KusabaxThread>::from_file(path)?),
-            Self::FoolFuuka => Box::new(MergingThreadUpdater::<FoolFuukaThread>::from_file(path)?),
-            Self::Ponychan => Box::new(MergingThreadUpdater::<PonychanThread>::from_file(path)?),
-            Self::Lainchan => Box::new(MergingThreadUpdater::<LainchanThread>::from_file(path)?),
+            Self::Basic => Box::new(BasicThreadUpdater::from_file(path, config.sanitize)?),
+            Self::FourChan => Box::new(MergingThreadUpdater::<FourchanThread>::from_file(path, config)?),
+            Self::Tinyboard => Box::new(MergingThreadUpdater::<TinyboardThread>::from_file(path, config)?),
+            Self::AspNetChan => Box::new(MergingThreadUpdater::<AspNetChanThread>::from_file(path, config)?),
+            Self::Kusabax => Box::new(MergingThreadUpdater::<KusabaxThread>::from_file(path, config)?),
+            Self::FoolFuuka => Box::new(MergingThreadUpdater::<FoolFuukaThread>::from_file(path, config)?),
+            Self::Ponychan => Box::new(MergingThreadUpdater::<PonychanThread>::from_file(path, config)?),
+            Self::Lainchan => Box::new(MergingThreadUpdater::<LainchanThread>::from_file(path, config)?),
+            Self::Custom(parser) => Box::new(MergingThreadUpdater::new(
+                CustomThread::from_file_with_parser(path, parser.clone())?,
+                config,
+            )),
         })
     }
 }
@@ -0,0 +1,24 @@
+// This module is only used in tests.
+
+use std::fs;
+
+use crate::download::test_dir;
+use crate::threadparser::MergeableImageboardThread;
+
+use super::*;
+
+/// Pages that are not threads, such as error pages served instead of the thread.
+pub const NOT_THREAD_PAGES: &[&str] = &[
+    "",
+    r#"<html><head><title>Just a moment...</title></head><body><div id="challenge">Checking your browser</div></body></html>"#,
+];
+
+/// Update a thread from a new version of it, as if it had been downloaded.
+pub fn update_from_html<TP: MergeableImageboardThread>(thread: TP, html_str: &str) -> UpdateResult {
+    let path = test_dir("update_from").join("thread.html");
+    fs::write(&path, html_str).unwrap();
+
+    let mut updater = MergingThreadUpdater::new(thread, UpdateConfig::default());
+
+    updater.update_from(&path).unwrap()
+}
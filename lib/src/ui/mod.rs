@@ -45,6 +45,8 @@ pub enum UiEvent {
     UpdateComplete {
         was_updated: bool,
         new_post_count: u32,
+        edited_post_count: u32,
+        deleted_post_count: u32,
         new_file_count: u32,
     },
 
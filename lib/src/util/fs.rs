@@ -1,5 +1,6 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use anyhow::Context;
 
@@ -15,6 +16,15 @@ pub fn open_file(path: impl AsRef<Path>) -> Result<fs::File, anyhow::Error> {
     fs::File::open(path).with_context(|| format!("Error opening file: {}", path.display()))
 }
 
+pub fn append_file(path: impl AsRef<Path>) -> Result<fs::File, anyhow::Error> {
+    let path = path.as_ref();
+
+    fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Error opening file for appending: {}", path.display()))
+}
+
 pub fn create_parent_dir(path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
     if let Some(parent_dir_path) = path.as_ref().parent() {
         fs::create_dir_all(parent_dir_path)
@@ -23,3 +33,33 @@ pub fn create_parent_dir(path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+/// Write a file by writing to a temporary file next to it and renaming it into place,
+/// so that the file is never left partially written.
+pub fn write_file_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<(), anyhow::Error> {
+    let path = path.as_ref();
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    fs::write(&temp_path, contents).with_context(|| format!("Error writing file: {}", temp_path.display()))?;
+
+    if let Err(err) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err).with_context(|| format!("Error replacing file: {}", path.display()));
+    }
+
+    Ok(())
+}
+
+/// Get a unique path for a temporary file.
+pub fn temp_file_path(name: &str, extension: &str) -> PathBuf {
+    static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    std::env::temp_dir().join(format!(
+        "chandler3-{}-{}-{}.{}",
+        name,
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed),
+        extension
+    ))
+}
@@ -0,0 +1,27 @@
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+
+use super::*;
+
+/// Compute the SHA-256 hash of a file's content.
+pub fn hash_file(path: impl AsRef<Path>) -> Result<String, anyhow::Error> {
+    let path = path.as_ref();
+
+    let mut file = open_file(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).with_context(|| format!("Error reading file: {}", path.display()))?;
+
+    Ok(finish_hash(hasher))
+}
+
+/// Get the final hash from a hasher as a lowercase hex string.
+pub fn finish_hash(hasher: Sha256) -> String {
+    hasher.finalize().iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
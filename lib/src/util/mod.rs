@@ -1,8 +1,10 @@
 mod fs;
+mod hash;
 mod path;
 pub mod pid;
 mod slice;
 
 pub use self::fs::*;
+pub use self::hash::*;
 pub use self::path::*;
 pub use self::slice::*;
@@ -27,3 +27,38 @@ pub fn normalize_path<P: AsRef<Path>>(path: P) -> PathBuf {
 
     new_path
 }
+
+/// Get a relative link from a directory to a file, using forward slashes as in URLs.
+pub fn relative_link(from_dir: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = to.components().collect();
+
+    // Get the number of leading components the two paths have in common.
+    let common = from.iter().zip(to.iter()).take_while(|(a, b)| a == b).count();
+
+    let parents = std::iter::repeat_n("..".into(), from.len() - common);
+    let rest = to[common..].iter().map(|c| c.as_os_str().to_string_lossy());
+
+    parents.chain(rest).collect::<Vec<Cow<str>>>().join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_get_relative_link() {
+        assert_eq!(
+            relative_link(Path::new("/dl/4chan/g/100"), Path::new("/dl/4chan/g/200/thread.html")),
+            "../200/thread.html"
+        );
+        assert_eq!(
+            relative_link(Path::new("/dl/4chan/g/100"), Path::new("/dl/4chan/v/300/thread.html")),
+            "../../v/300/thread.html"
+        );
+        assert_eq!(
+            relative_link(Path::new("/dl/4chan/g/100"), Path::new("/dl/4chan/g/100/thread.html")),
+            "thread.html"
+        );
+    }
+}
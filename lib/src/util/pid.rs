@@ -1,6 +1,8 @@
 use std::fs;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use sysinfo::{Pid, ProcessRefreshKind};
 use tracing::{debug, error};
@@ -52,6 +54,25 @@ impl PidLock {
 
         Some(Self { path })
     }
+
+    /// Try to acquire a PID lock, waiting for up to the specified time for it to be released.
+    /// This is intended for locks that are only held briefly, such as while writing a file.
+    pub fn acquire_wait(path: impl AsRef<Path>, timeout: Duration) -> Option<Self> {
+        let path = path.as_ref();
+        let start = Instant::now();
+
+        loop {
+            if let Some(lock) = Self::acquire(path) {
+                return Some(lock);
+            }
+
+            if start.elapsed() >= timeout {
+                return None;
+            }
+
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
 }
 
 impl Drop for PidLock {
@@ -0,0 +1,217 @@
+use std::path::Path;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+mod reader;
+mod writer;
+
+pub use self::reader::*;
+pub use self::writer::*;
+
+const WARC_VERSION: &str = "WARC/1.1";
+
+/// Content type of response records, which contain a full HTTP response.
+const HTTP_RESPONSE_CONTENT_TYPE: &str = "application/http;msgtype=response";
+
+/// A record in a WARC file.
+#[derive(Clone, Debug, Default)]
+pub struct WarcRecord {
+    /// Named fields, in the order they appear in the record.
+    /// Content-Length is not included, as it is determined by the block.
+    pub headers: Vec<(String, String)>,
+
+    /// Content of the record.
+    pub block: Vec<u8>,
+}
+
+impl WarcRecord {
+    pub fn new(record_type: &str, date: DateTime<Utc>) -> Self {
+        Self {
+            headers: vec![
+                ("WARC-Type".to_owned(), record_type.to_owned()),
+                ("WARC-Date".to_owned(), date.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            ],
+            block: Vec::new(),
+        }
+    }
+
+    /// Create a warcinfo record describing the file.
+    pub fn warcinfo(date: DateTime<Utc>, fields: &[(&str, &str)]) -> Self {
+        let mut record = Self::new("warcinfo", date);
+        record.set_header("Content-Type", "application/warc-fields");
+
+        for (name, value) in fields {
+            record.block.extend(format!("{name}: {value}\r\n").as_bytes());
+        }
+
+        record
+    }
+
+    /// Create a response record for a successful HTTP response.
+    /// The original response headers are not available, so only the content type is included.
+    pub fn response(url: &str, date: DateTime<Utc>, content_type: &str, payload: &[u8]) -> Self {
+        let mut record = Self::new("response", date);
+        record.set_header("WARC-Target-URI", url);
+        record.set_header("Content-Type", HTTP_RESPONSE_CONTENT_TYPE);
+
+        record.block = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\r\n",
+            payload.len()
+        )
+        .into_bytes();
+        record.block.extend_from_slice(payload);
+
+        record
+    }
+
+    /// Create a conversion record, for content that was derived from another record rather than downloaded.
+    /// `refers_to` is the ID of the record it was derived from.
+    pub fn conversion(url: &str, date: DateTime<Utc>, content_type: &str, payload: &[u8], refers_to: &str) -> Self {
+        let mut record = Self::new("conversion", date);
+        record.set_header("WARC-Target-URI", url);
+        record.set_header("WARC-Refers-To", refers_to);
+        record.set_header("Content-Type", content_type);
+
+        record.block = payload.to_vec();
+
+        record
+    }
+
+    /// Get the value of a field. Names are not case sensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Set the value of a field, replacing any existing value.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self.headers.iter_mut().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            Some((_, v)) => *v = value.to_owned(),
+            None => self.headers.push((name.to_owned(), value.to_owned())),
+        }
+    }
+
+    pub fn record_type(&self) -> Option<&str> {
+        self.header("WARC-Type")
+    }
+
+    pub fn target_uri(&self) -> Option<&str> {
+        self.header("WARC-Target-URI")
+    }
+
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        self.header("WARC-Date")
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+            .map(|date| date.with_timezone(&Utc))
+    }
+
+    /// Get the fields of a warcinfo or metadata record.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        String::from_utf8_lossy(&self.block)
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_owned(), value.trim().to_owned()))
+            .collect()
+    }
+
+    /// Get the content of a conversion record.
+    pub fn conversion_payload(&self) -> Option<&[u8]> {
+        (self.record_type() == Some("conversion")).then_some(self.block.as_slice())
+    }
+
+    /// Get the payload of a response record, without the HTTP headers.
+    /// Returns None if this is not a successful HTTP response.
+    pub fn http_payload(&self) -> Option<&[u8]> {
+        if self.record_type() != Some("response") {
+            return None;
+        }
+
+        let header_end = self.block.windows(4).position(|w| w == b"\r\n\r\n")?;
+        let status_line = self.block[..header_end].split(|b| *b == b'\n').next()?;
+
+        // Only successful responses contain the actual file.
+        if !String::from_utf8_lossy(status_line)
+            .split_whitespace()
+            .nth(1)
+            .is_some_and(|code| code.starts_with('2'))
+        {
+            return None;
+        }
+
+        Some(&self.block[header_end + 4..])
+    }
+}
+
+/// Guess the content type of a file from its extension.
+pub fn guess_content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "css" => "text/css",
+        "gif" => "image/gif",
+        "htm" | "html" => "text/html",
+        "ico" => "image/x-icon",
+        "jpeg" | "jpg" => "image/jpeg",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "ogg" => "audio/ogg",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        "webm" => "video/webm",
+        "webp" => "image/webp",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn can_write_and_read_records() {
+        let date: DateTime<Utc> = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().into();
+
+        let mut writer = WarcWriter::new(Vec::new());
+        writer
+            .write_record(&WarcRecord::warcinfo(date, &[("software", "Chandler")]))
+            .unwrap();
+        writer
+            .write_record(&WarcRecord::response(
+                "http://example.com/1.html",
+                date,
+                "text/html",
+                b"<html>\r\n\r\n</html>",
+            ))
+            .unwrap();
+
+        let data = writer.into_inner();
+        let records = WarcReader::new(Cursor::new(data))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(records[0].record_type(), Some("warcinfo"));
+        assert_eq!(
+            records[0].fields(),
+            vec![("software".to_owned(), "Chandler".to_owned())]
+        );
+
+        assert_eq!(records[1].record_type(), Some("response"));
+        assert_eq!(records[1].target_uri(), Some("http://example.com/1.html"));
+        assert_eq!(records[1].date(), Some(date));
+        assert_eq!(records[1].http_payload(), Some(&b"<html>\r\n\r\n</html>"[..]));
+        assert_ne!(records[0].header("WARC-Record-ID"), records[1].header("WARC-Record-ID"));
+    }
+}
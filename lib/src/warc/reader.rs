@@ -0,0 +1,95 @@
+use std::io::BufRead;
+
+use crate::error::*;
+
+use super::*;
+
+/// Reads records from a WARC file.
+pub struct WarcReader<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> WarcReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Read the next record, or None if the end of the file has been reached.
+    pub fn read_record(&mut self) -> Result<Option<WarcRecord>, ChandlerError> {
+        // Skip any blank lines left after the previous record.
+        let version = loop {
+            let Some(line) = self.read_line()? else {
+                return Ok(None);
+            };
+
+            if !line.is_empty() {
+                break line;
+            }
+        };
+
+        if !version.starts_with("WARC/") {
+            return Err(invalid_record(format!("Expected WARC version, found: {version}")));
+        }
+
+        let mut record = WarcRecord::default();
+        let mut content_length: Option<usize> = None;
+
+        loop {
+            let line = self
+                .read_line()?
+                .ok_or_else(|| invalid_record("Unexpected end of file in record header".to_owned()))?;
+
+            if line.is_empty() {
+                break;
+            }
+
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| invalid_record(format!("Invalid field: {line}")))?;
+
+            let (name, value) = (name.trim(), value.trim());
+
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = Some(
+                    value
+                        .parse()
+                        .map_err(|_| invalid_record(format!("Invalid Content-Length: {value}")))?,
+                );
+            } else {
+                record.headers.push((name.to_owned(), value.to_owned()));
+            }
+        }
+
+        let content_length = content_length.ok_or_else(|| invalid_record("Missing Content-Length".to_owned()))?;
+
+        record.block = vec![0; content_length];
+        self.reader
+            .read_exact(&mut record.block)
+            .map_err(ChandlerError::ReadFile)?;
+
+        Ok(Some(record))
+    }
+
+    /// Read a line, without the line ending.
+    fn read_line(&mut self) -> Result<Option<String>, ChandlerError> {
+        let mut line = String::new();
+
+        if self.reader.read_line(&mut line).map_err(ChandlerError::ReadFile)? == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_owned()))
+    }
+}
+
+impl<R: BufRead> Iterator for WarcReader<R> {
+    type Item = Result<WarcRecord, ChandlerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+fn invalid_record(message: String) -> ChandlerError {
+    ChandlerError::Other(format!("Invalid WARC record: {message}").into())
+}
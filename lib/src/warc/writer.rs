@@ -0,0 +1,89 @@
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::*;
+use crate::util;
+
+use super::*;
+
+/// Writes records to a WARC file.
+pub struct WarcWriter<W: Write> {
+    writer: W,
+
+    /// Used to make record IDs unique, both within the file and between files.
+    seed: u128,
+    record_count: u64,
+}
+
+impl<W: Write> WarcWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            seed: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos()),
+            record_count: 0,
+        }
+    }
+
+    /// Write a record, and get its ID. A record ID is generated if it does not have one.
+    pub fn write_record(&mut self, record: &WarcRecord) -> Result<String, ChandlerError> {
+        let mut header = format!("{WARC_VERSION}\r\n");
+
+        let record_id = match record.header("WARC-Record-ID") {
+            Some(record_id) => record_id.to_owned(),
+            None => {
+                let record_id = self.next_record_id(record);
+                header.push_str(&format!("WARC-Record-ID: {record_id}\r\n"));
+
+                record_id
+            }
+        };
+
+        for (name, value) in record.headers.iter() {
+            header.push_str(&format!("{name}: {value}\r\n"));
+        }
+
+        header.push_str(&format!("Content-Length: {}\r\n\r\n", record.block.len()));
+
+        self.write_all(header.as_bytes())?;
+        self.write_all(&record.block)?;
+        self.write_all(b"\r\n\r\n")?;
+
+        self.record_count += 1;
+
+        Ok(record_id)
+    }
+
+    /// Flush any buffered data to the underlying writer.
+    pub fn flush(&mut self) -> Result<(), ChandlerError> {
+        self.writer.flush().map_err(ChandlerError::WriteFile)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> Result<(), ChandlerError> {
+        self.writer.write_all(data).map_err(ChandlerError::WriteFile)
+    }
+
+    /// Generate a record ID in the form of a UUID.
+    fn next_record_id(&self, record: &WarcRecord) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.seed.to_le_bytes());
+        hasher.update(self.record_count.to_le_bytes());
+        hasher.update(record.target_uri().unwrap_or_default());
+
+        let hash = util::finish_hash(hasher);
+
+        format!(
+            "<urn:uuid:{}-{}-{}-{}-{}>",
+            &hash[0..8],
+            &hash[8..12],
+            &hash[12..16],
+            &hash[16..20],
+            &hash[20..32]
+        )
+    }
+}
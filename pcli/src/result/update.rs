@@ -1,12 +1,16 @@
 use serde_derive::Serialize;
 
 use chandler::project::ProjectUpdateResult;
+use chandler::threadparser::ThreadStatus;
 
 #[derive(Debug, Serialize)]
 pub struct PcliUpdateResult {
     pub was_updated: bool,
     pub is_dead: bool,
+    pub status: ThreadStatus,
     pub new_post_count: u32,
+    pub edited_post_count: u32,
+    pub deleted_post_count: u32,
     pub new_file_count: u32,
 }
 
@@ -15,7 +19,10 @@ impl From<ProjectUpdateResult> for PcliUpdateResult {
         PcliUpdateResult {
             was_updated: ur.was_updated,
             is_dead: ur.is_dead,
+            status: ur.status,
             new_post_count: ur.new_post_count,
+            edited_post_count: ur.edited_post_count,
+            deleted_post_count: ur.deleted_post_count,
             new_file_count: ur.new_file_count,
         }
     }